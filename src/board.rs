@@ -1,8 +1,27 @@
+pub mod tables;
+
+pub use tables::Direction;
+
 use core::cmp::Ordering;
 use crate::square::{Square, EMPTY_SQUARE};
 use crate::engine::*;
 use crate::piece::Piece;
 use crate::position::*;
+use crate::util::rng::Rng;
+use tables::{attacks, Bitboard, KNIGHT_ATTACKS};
+use tables::{NOT_A_FILE, NOT_H_FILE};
+
+/// Row and column steps along the ranks and files.
+const ORTHOGONAL: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Row and column steps along the diagonals.
+const DIAGONAL: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Moves as produced by the generators.
+pub type MoveList = Vec<Move>;
+
+/// The deepest perft count supported, well past what fits in a block's gas.
+const MAX_PERFT_DEPTH: u8 = 10;
 
 pub struct BoardBuilder {
   board: Board,
@@ -121,7 +140,7 @@ impl BoardBuilder {
   }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct CastlingRights {
   kingside: bool,
   queenside: bool,
@@ -136,6 +155,42 @@ impl Default for CastlingRights {
   }
 }
 
+/// Zobrist key indices: 12 piece kinds by 64 squares, then castling
+/// rights, then en passant squares.
+const CASTLING_KEYS: u64 = 12 * 64;
+const EN_PASSANT_KEYS: u64 = CASTLING_KEYS + 4;
+
+/// Fixed pseudo-random key for a Zobrist index.
+fn zobrist_key(index: u64) -> u64 {
+  Rng::new(index).next_u64()
+}
+
+fn square_index(pos: Position) -> u64 {
+  (pos.get_row() * 8 + pos.get_col()) as u64
+}
+
+/// The square with its rank flipped.
+fn mirror(pos: Position) -> Position {
+  Position::new(7 - pos.get_row(), pos.get_col())
+}
+
+/// Zobrist key for `piece`'s kind, owned by `color`, on `pos`.
+fn piece_key(piece: &Piece, color: Color, pos: Position) -> u64 {
+  let kind = match piece {
+    Piece::King(..) => 0,
+    Piece::Queen(..) => 1,
+    Piece::Rook(..) => 2,
+    Piece::Bishop(..) => 3,
+    Piece::Knight(..) => 4,
+    Piece::Pawn(..) => 5,
+  };
+  let color = match color {
+    WHITE => 0,
+    BLACK => 6,
+  };
+  zobrist_key((kind + color) * 64 + square_index(pos))
+}
+
 impl CastlingRights {
   pub fn can_kingside_castle(&self) -> bool {
     self.kingside
@@ -174,6 +229,14 @@ impl CastlingRights {
 
 impl Default for Board {
   fn default() -> Self {
+    Self::starting_position()
+  }
+}
+
+impl Board {
+  /// The standard starting position, white to move with all castling
+  /// rights, built square by square rather than parsed from a FEN.
+  pub fn starting_position() -> Self {
     BoardBuilder::default()
       .piece(Piece::Rook(BLACK, A8))
       .piece(Piece::Knight(BLACK, B8))
@@ -198,7 +261,17 @@ impl Default for Board {
   }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// The number of each kind of piece one player has, not counting the king.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PieceCounts {
+  pub pawns: u8,
+  pub knights: u8,
+  pub bishops: u8,
+  pub rooks: u8,
+  pub queens: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Board {
   squares: [Square; 64],
 
@@ -211,21 +284,15 @@ pub struct Board {
 }
 
 impl Evaluate for Board {
+  /// The full evaluation, see `Board::evaluate_detailed`, from
+  /// `ally_color`'s point of view.
   #[inline]
-  fn value_for(&self, ally_color: Color) -> f64 {
-    self.squares
-      .iter()
-      .map(|square| match square.get_piece() {
-        Some(piece) => {
-          if piece.get_color() == ally_color {
-            piece.get_weighted_value()
-          } else {
-            -piece.get_weighted_value()
-          }
-        }
-        None => 0.0,
-      })
-      .sum()
+  fn value_for(&self, ally_color: Color) -> i32 {
+    let total = self.evaluate_detailed().total();
+    match ally_color {
+      WHITE => total,
+      BLACK => -total,
+    }
   }
 
   #[inline]
@@ -240,17 +307,7 @@ impl Evaluate for Board {
 
   #[inline]
   fn get_legal_moves(&self) -> Vec<Move> {
-    let mut result = vec![];
-    let color = self.get_current_player_color();
-    for square in &self.squares {
-      if let Some(piece) = square.get_piece() {
-        if piece.get_color() == color {
-          result.extend(piece.get_legal_moves(self))
-        }
-      }
-    }
-
-    result
+    self.generate_legal_moves()
   }
 }
 
@@ -358,23 +415,24 @@ impl Board {
   pub fn rating_bar(&self, len: usize) -> String {
     let (best_m, _, your_best_val) = self.get_best_next_move(2);
     let (_, _, your_lowest_val) = self.get_worst_next_move(2);
-    let mut your_val = your_best_val + your_lowest_val;
+    let mut your_val = (your_best_val + your_lowest_val) as i64;
     let (_, _, their_best_val) = self.apply_move(best_m).change_turn().get_best_next_move(2);
     let (_, _, their_lowest_val) = self.apply_move(best_m).change_turn().get_worst_next_move(2);
-    let mut their_val = their_best_val + their_lowest_val;
+    let mut their_val = (their_best_val + their_lowest_val) as i64;
 
-    if your_val < 0.0 {
+    if your_val < 0 {
       your_val = -your_val;
-      their_val += your_val * 2.0;
+      their_val += your_val * 2;
     }
 
-    if their_val < 0.0 {
+    if their_val < 0 {
       their_val = -their_val;
-      your_val += their_val * 2.0;
+      your_val += their_val * 2;
     }
 
-    let your_percentage = your_val / (your_val + their_val);
-    let their_percentage = their_val / (your_val + their_val);
+    let total = (your_val + their_val).max(1);
+    let your_len = (your_val * len as i64 / total) as usize;
+    let their_len = (their_val * len as i64 / total) as usize;
 
     let (your_color, their_color) = match self.turn {
       WHITE => ("▓", "░"),
@@ -382,13 +440,13 @@ impl Board {
     };
 
     let white = match self.turn {
-      WHITE => your_color.repeat((your_percentage * len as f64) as usize),
-      BLACK => their_color.repeat((their_percentage * len as f64) as usize),
+      WHITE => your_color.repeat(your_len),
+      BLACK => their_color.repeat(their_len),
     };
 
     let black = match self.turn {
-      BLACK => your_color.repeat((your_percentage * len as f64) as usize),
-      WHITE => their_color.repeat((their_percentage * len as f64) as usize),
+      BLACK => your_color.repeat(your_len),
+      WHITE => their_color.repeat(their_len),
     };
 
     white + &black
@@ -441,6 +499,92 @@ impl Board {
     result
   }
 
+  /// Evaluate the position part by part, in centipawns from white's point
+  /// of view.
+  pub fn evaluate_detailed(&self) -> Evaluation {
+    // mobility is tapered in as the pieces come off, with every piece still
+    // on the board it counts for nothing, and the king's pawn shield is
+    // tapered out the same way
+    let phase = self.get_phase_value() as i32;
+    let endgame_weight = Phase::MAX_VALUE as i32 - phase;
+    let mobility = if endgame_weight == 0 {
+      0
+    } else {
+      (mobility(self, WHITE) as i32 - mobility(self, BLACK) as i32) * MOBILITY_CENTIPAWNS * endgame_weight
+        / Phase::MAX_VALUE as i32
+    };
+    let king_safety = (pawn_shield(self, WHITE) as i32 - pawn_shield(self, BLACK) as i32)
+      * PAWN_SHIELD_CENTIPAWNS
+      * phase
+      / Phase::MAX_VALUE as i32;
+    let mut evaluation = Evaluation {
+      king_safety,
+      material: 0,
+      mobility,
+      pawn_structure: pawn_structure(self, WHITE) - pawn_structure(self, BLACK),
+      piece_square: 0,
+    };
+    for piece in self.squares.iter().filter_map(|square| square.get_piece()) {
+      let sign = if piece.get_color() == WHITE { 1 } else { -1 };
+      if !piece.is_king() {
+        evaluation.material += sign * piece.get_material_value() * 100;
+      }
+      evaluation.piece_square += sign * piece.get_position_weight();
+    }
+    evaluation
+  }
+
+  /// Count the pieces of a certain player.
+  pub fn get_piece_counts(&self, color: Color) -> PieceCounts {
+    let mut counts = PieceCounts::default();
+    for piece in self.squares.iter().filter_map(|square| square.get_piece()) {
+      if piece.get_color() != color {
+        continue;
+      }
+      match piece {
+        Piece::Pawn(_, _) => counts.pawns += 1,
+        Piece::Knight(_, _) => counts.knights += 1,
+        Piece::Bishop(_, _) => counts.bishops += 1,
+        Piece::Rook(_, _) => counts.rooks += 1,
+        Piece::Queen(_, _) => counts.queens += 1,
+        Piece::King(_, _) => {}
+      }
+    }
+    counts
+  }
+
+  /// Get how far the game is from the endgame, from `Phase::MAX_VALUE` with
+  /// all the starting material down to 0 with only kings and pawns.
+  ///
+  /// Knights and bishops count 1, rooks 2 and queens 4, for both players.
+  pub fn get_phase_value(&self) -> u8 {
+    let value: u32 = [WHITE, BLACK]
+      .iter()
+      .map(|color| {
+        let counts = self.get_piece_counts(*color);
+        (counts.knights + counts.bishops) as u32 + counts.rooks as u32 * 2 + counts.queens as u32 * 4
+      })
+      .sum();
+    // promotions can add material past the start of the game
+    value.min(Phase::MAX_VALUE as u32) as u8
+  }
+
+  /// Get the material value of every piece but the kings, for both sides.
+  pub fn get_non_king_material(&self) -> u32 {
+    self
+      .squares
+      .iter()
+      .filter_map(|square| square.get_piece())
+      .filter(|piece| !piece.is_king())
+      .map(|piece| piece.get_material_value() as u32)
+      .sum()
+  }
+
+  /// Get the stage of the game.
+  pub fn get_phase(&self) -> Phase {
+    Phase::from_value(self.get_phase_value())
+  }
+
   /// Get the value of the material advantage of a certain player
   #[inline]
   pub fn get_material_advantage(&self, color: Color) -> i32 {
@@ -531,16 +675,51 @@ impl Board {
     king_pos
   }
 
+  /// Is a square attacked by a pawn of `color`?
+  ///
+  /// Only the two squares a pawn could attack from are looked at.
+  #[inline]
+  pub fn attacked_by_pawn(&self, color: Color, pos: Position) -> bool {
+    let back = pos.pawn_back(color);
+    [back.next_left(), back.next_right()]
+      .iter()
+      .any(|from| matches!(self.get_piece(*from), Some(Piece::Pawn(c, _)) if c == color))
+  }
+
+  /// Every square attacked by a pawn of `color`.
+  ///
+  /// All of the pawns are shifted at once, one diagonal step each way.
+  pub fn pawn_attacks(&self, color: Color) -> Bitboard {
+    let pawns = self
+      .squares
+      .iter()
+      .filter_map(|square| square.get_piece())
+      .filter(|piece| matches!(piece, Piece::Pawn(c, _) if *c == color))
+      .fold(0, |pawns, piece| pawns | 1 << square_index(piece.get_pos()));
+    match color {
+      Color::White => (pawns << 7) & NOT_H_FILE | (pawns << 9) & NOT_A_FILE,
+      Color::Black => (pawns >> 7) & NOT_A_FILE | (pawns >> 9) & NOT_H_FILE,
+    }
+  }
+
   /// Is a square threatened by an enemy piece?
   pub fn is_threatened(&self, pos: Position, ally_color: Color) -> bool {
+    let target = match tables::square_index(pos) {
+      Some(target) => target,
+      None => return false,
+    };
+    if self.pawn_attacks(!ally_color) & 1 << target != 0 {
+      return true;
+    }
+
+    // every square a queen on `pos` would see on an empty board
+    let lines = Direction::ALL.iter().fold(0, |lines, dir| lines | dir.ray_mask(target));
+
     for (i, square) in self.squares.iter().enumerate() {
       let row = 7 - i / 8;
       let col = i % 8;
       let square_pos = Position::new(row as i32, col as i32);
-      if !square_pos.is_orthogonal_to(pos)
-        && !square_pos.is_diagonal_to(pos)
-        && !square_pos.is_knight_move(pos)
-      {
+      if lines & 1 << square_index(square_pos) == 0 && !attacks(&KNIGHT_ATTACKS, square_pos, pos) {
         continue;
       }
 
@@ -616,6 +795,23 @@ impl Board {
       } else if piece.is_kingside_rook() {
         castling_rights.disable_kingside();
       }
+
+      // capturing a rook on its starting square, promoting or not, takes
+      // away the castling right it belonged to
+      if let Some(captured) = self.get_piece(to) {
+        let color = captured.get_color();
+        if captured.is_rook() && to.get_row() == Position::king_pos(color).get_row() {
+          let castling_rights = match color {
+            WHITE => &mut result.white_castling_rights,
+            BLACK => &mut result.black_castling_rights,
+          };
+          if to.is_queenside_rook() {
+            castling_rights.disable_queenside();
+          } else if to.is_kingside_rook() {
+            castling_rights.disable_kingside();
+          }
+        }
+      }
     }
 
     result
@@ -682,26 +878,36 @@ impl Board {
   }
 
   pub(crate) fn is_legal_move(&self, m: Move, player_color: Color) -> bool {
+    self.is_pseudo_legal_move(m, player_color) && self.keeps_king_safe(m, player_color)
+  }
+
+  /// Does `player_color`'s king stay out of check after `m`?
+  #[inline]
+  pub(crate) fn keeps_king_safe(&self, m: Move, player_color: Color) -> bool {
+    m == Move::Resign || !self.apply_move(m).is_in_check(player_color)
+  }
+
+  /// Could `player_color` make move `m`, ignoring their king's safety?
+  ///
+  /// Castling still needs the king's path clear of attacks.
+  pub(crate) fn is_pseudo_legal_move(&self, m: Move, player_color: Color) -> bool {
     match m {
       Move::KingSideCastle => self.can_kingside_castle(player_color),
       Move::QueenSideCastle => self.can_queenside_castle(player_color),
       Move::Piece(from, to) => match self.get_piece(from) {
         Some(Piece::Pawn(c, pos)) => {
           let piece = Piece::Pawn(c, pos);
-          ((if let Some(en_passant) = self.en_passant {
+          (if let Some(en_passant) = self.en_passant {
             (en_passant == from.pawn_up(player_color).next_left()
               || en_passant == from.pawn_up(player_color).next_right()
                 && en_passant == to)
               && c == player_color
           } else {
             false
-          }) || piece.is_legal_move(to, self) && piece.get_color() == player_color)
-            && !self.apply_move(m).is_in_check(player_color)
+          }) || piece.is_legal_move(to, self) && piece.get_color() == player_color
         }
         Some(piece) => {
-          piece.is_legal_move(to, self)
-            && piece.get_color() == player_color
-            && !self.apply_move(m).is_in_check(player_color)
+          piece.is_legal_move(to, self) && piece.get_color() == player_color
         }
         _ => false,
       },
@@ -715,7 +921,6 @@ impl Board {
               // regular piece checks
               && piece.is_legal_move(to, self)
               && piece.get_color() == player_color
-              && !self.apply_move(m).is_in_check(player_color)
           }
           _ => false,
         }
@@ -768,16 +973,447 @@ impl Board {
     !self.has_sufficient_material(color)
   }
 
+  /// Does the current player have at least one legal move?
+  ///
+  /// This stops at the first legal move found instead of generating the
+  /// whole move list. The king is tried first, since in checkmate and
+  /// stalemate positions it is the piece most likely to have a move. In
+  /// check, the other pieces' moves that neither take the checking piece
+  /// nor block it are skipped without playing them out.
+  pub fn has_legal_moves(&self) -> bool {
+    let color = self.get_current_player_color();
+    let evasions = self.check_evasion_mask(color);
+    let mut pieces = self.squares
+      .iter()
+      .filter_map(|square| square.get_piece())
+      .filter(|piece| piece.get_color() == color)
+      .collect::<Vec<Piece>>();
+    // kings sort first
+    pieces.sort();
+
+    pieces.iter().any(|piece| {
+      piece
+        .get_pseudo_legal_moves(self)
+        .into_iter()
+        .filter(|m| {
+          piece.is_king()
+            || match m {
+              Move::Piece(_, to) | Move::Promotion(_, to, _) => evasions & 1 << square_index(*to) != 0,
+              _ => true,
+            }
+        })
+        .any(|m| self.keeps_king_safe(m, color))
+    })
+  }
+
+  /// The squares a piece other than the king of `color` has to move to
+  /// when `color` is in check: the checking piece's square and the squares
+  /// between it and the king.
+  ///
+  /// Every square when `color` is not in check, and none in double check,
+  /// where only the king can move.
+  fn check_evasion_mask(&self, color: Color) -> Bitboard {
+    let king = match self.get_king_pos(color) {
+      Some(king) => king,
+      None => return !0,
+    };
+    let mut checkers = self
+      .squares
+      .iter()
+      .filter_map(|square| square.get_piece())
+      .filter(|piece| piece.get_color() != color && piece.is_legal_attack(king, self));
+
+    match (checkers.next(), checkers.next()) {
+      (None, _) => !0,
+      (Some(checker), None) => {
+        let from = square_index(checker.get_pos());
+        let mut mask = 1 << from | Direction::between(from as usize, square_index(king) as usize);
+        // en passant takes a checking pawn without moving onto its square
+        if let (true, Some(en_passant)) = (checker.is_pawn(), self.en_passant) {
+          mask |= 1 << square_index(en_passant);
+        }
+        mask
+      }
+      _ => 0,
+    }
+  }
+
+  /// Get every legal promotion for the pawns of `color` one step from
+  /// the last rank.
+  ///
+  /// Each pawn move, straight or by capture, is listed four times: once
+  /// each for promoting to a queen, rook, bishop and knight.
+  pub fn generate_promotions(&self, color: Color) -> Vec<Move> {
+    self.promotions(color, |piece| piece.get_legal_moves(self))
+  }
+
+  // promotions for the pawn moves `pawn_moves` finds
+  fn promotions(&self, color: Color, pawn_moves: impl Fn(&Piece) -> Vec<Move>) -> MoveList {
+    let mut result = vec![];
+    for square in &self.squares {
+      if let Some(piece) = square.get_piece() {
+        if piece.get_color() != color || !piece.is_promoting_pawn() {
+          continue;
+        }
+        for pawn_move in pawn_moves(&piece) {
+          if let Move::Piece(from, to) = pawn_move {
+            result.extend([
+              Move::Promotion(from, to, Piece::Queen(color, to)),
+              Move::Promotion(from, to, Piece::Rook(color, to)),
+              Move::Promotion(from, to, Piece::Bishop(color, to)),
+              Move::Promotion(from, to, Piece::Knight(color, to)),
+            ]);
+          }
+        }
+      }
+    }
+    result
+  }
+
+  /// Get every move for the current player, including those that leave
+  /// their king in check.
+  ///
+  /// Promotions come last, as in `generate_legal_moves`.
+  pub fn generate_pseudo_legal_moves(&self) -> MoveList {
+    let mut result = vec![];
+    let color = self.get_current_player_color();
+    for square in &self.squares {
+      if let Some(piece) = square.get_piece() {
+        // promoting pawns are handled by promotions
+        if piece.get_color() == color && !piece.is_promoting_pawn() {
+          result.extend(piece.get_pseudo_legal_moves(self))
+        }
+      }
+    }
+    result.extend(self.promotions(color, |piece| piece.get_pseudo_legal_moves(self)));
+
+    result
+  }
+
+  /// Keep the moves of `pseudo_legal` that don't leave the current
+  /// player's king in check.
+  pub fn filter_legal(&self, pseudo_legal: &MoveList) -> MoveList {
+    let color = self.get_current_player_color();
+    pseudo_legal
+      .iter()
+      .copied()
+      .filter(|m| self.keeps_king_safe(*m, color))
+      .collect()
+  }
+
+  /// Get every legal move for the current player.
+  pub fn generate_legal_moves(&self) -> MoveList {
+    self.filter_legal(&self.generate_pseudo_legal_moves())
+  }
+
+  /// Get the legal moves for the piece on `pos`.
+  ///
+  /// Only that piece's moves are generated. The list is empty when the
+  /// square is empty or holds a piece of the player not to move.
+  pub fn generate_legal_moves_from(&self, pos: Position) -> Vec<Move> {
+    let color = self.get_turn_color();
+    match self.get_piece(pos) {
+      Some(piece) if piece.get_color() == color && piece.is_promoting_pawn() => self
+        .generate_promotions(color)
+        .into_iter()
+        .filter(|m| matches!(m, Move::Promotion(from, _, _) if *from == pos))
+        .collect(),
+      Some(piece) if piece.get_color() == color => piece.get_legal_moves(self),
+      _ => vec![],
+    }
+  }
+
+  /// Get the pseudo-legal and legal moves for the piece on `pos`, for
+  /// either player.
+  ///
+  /// A piece of the player not to move gets the moves it would have on its
+  /// own turn, without en passant. Both lists are empty for an empty square.
+  pub fn generate_moves_for_piece(&self, pos: Position) -> (Vec<Move>, Vec<Move>) {
+    let piece = match self.get_piece(pos) {
+      Some(piece) => piece,
+      None => return (vec![], vec![]),
+    };
+    let color = piece.get_color();
+    let mut board = self.set_turn(color);
+    if color != self.turn {
+      board.en_passant = None;
+    }
+
+    let pseudo_legal: Vec<Move> = if piece.is_promoting_pawn() {
+      board
+        .promotions(color, |pawn| pawn.get_pseudo_legal_moves(&board))
+        .into_iter()
+        .filter(|m| matches!(m, Move::Promotion(from, _, _) if *from == pos))
+        .collect()
+    } else {
+      piece.get_pseudo_legal_moves(&board)
+    };
+    let legal = pseudo_legal
+      .iter()
+      .copied()
+      .filter(|m| board.keeps_king_safe(*m, color))
+      .collect();
+    (pseudo_legal, legal)
+  }
+
+  /// Count the legal moves `color` would have on its turn, the same moves
+  /// `generate_moves_for_piece` lists for each of its pieces.
+  ///
+  /// Out of check, the moves of a piece that is not pinned are counted
+  /// without being played out. The king, pinned pieces, pawns that could
+  /// take en passant and every piece in check go through
+  /// `generate_moves_for_piece`.
+  pub fn count_legal_moves(&self, color: Color) -> u32 {
+    let mut board = self.set_turn(color);
+    if color != self.turn {
+      board.en_passant = None;
+    }
+    let pinned = board.pinned(color);
+
+    let mut count = 0;
+    for piece in self.squares.iter().filter_map(|square| square.get_piece()) {
+      if piece.get_color() != color {
+        continue;
+      }
+      let pos = piece.get_pos();
+      count += if piece.is_king()
+        || piece.is_pawn() && board.en_passant.is_some()
+        || pinned & 1 << square_index(pos) != 0
+      {
+        board.generate_moves_for_piece(pos).1.len() as u32
+      } else {
+        board.count_unpinned_moves(piece)
+      };
+    }
+    count
+  }
+
+  /// The squares of `color`'s pieces pinned to its king, or every square
+  /// while `color` is in check or has no king.
+  fn pinned(&self, color: Color) -> Bitboard {
+    let king = match self.get_king_pos(color) {
+      Some(king) if !self.is_in_check(color) => king,
+      _ => return !0,
+    };
+
+    let mut pinned = 0;
+    for (steps, diagonal) in [(&ORTHOGONAL, false), (&DIAGONAL, true)] {
+      for (row_step, col_step) in steps {
+        let step = |pos: Position| Position::new(pos.get_row() + row_step, pos.get_col() + col_step);
+        let mut pos = step(king);
+        let mut ally = None;
+        while pos.is_on_board() {
+          match self.get_piece(pos) {
+            None => {}
+            Some(piece) if piece.get_color() == color => match ally {
+              None => ally = Some(pos),
+              Some(_) => break,
+            },
+            Some(piece) => {
+              let slides = match piece {
+                Piece::Queen(..) => true,
+                Piece::Bishop(..) => diagonal,
+                Piece::Rook(..) => !diagonal,
+                _ => false,
+              };
+              if let (true, Some(ally)) = (slides, ally) {
+                pinned |= 1 << square_index(ally);
+              }
+              break;
+            }
+          }
+          pos = step(pos);
+        }
+      }
+    }
+    pinned
+  }
+
+  // the moves of a piece other than the king that cannot be pinned, each
+  // promotion piece counting as a move
+  fn count_unpinned_moves(&self, piece: Piece) -> u32 {
+    let color = piece.get_color();
+    let pos = piece.get_pos();
+    let (row, col) = (pos.get_row(), pos.get_col());
+    let open = |to: Position| to.is_on_board() && !self.has_ally_piece(to, color);
+    let slide = |steps: &[(i32, i32)]| {
+      let mut count = 0;
+      for (row_step, col_step) in steps {
+        let mut to = Position::new(row + row_step, col + col_step);
+        while open(to) {
+          count += 1;
+          if self.has_enemy_piece(to, color) {
+            break;
+          }
+          to = Position::new(to.get_row() + row_step, to.get_col() + col_step);
+        }
+      }
+      count
+    };
+
+    match piece {
+      Piece::Pawn(..) => {
+        let up = pos.pawn_up(color);
+        let mut count = 0;
+        if up.is_on_board() && self.has_no_piece(up) {
+          count += 1;
+          let next_up = up.pawn_up(color);
+          if piece.is_starting_pawn() && next_up.is_on_board() && self.has_no_piece(next_up) {
+            count += 1;
+          }
+        }
+        for to in [up.next_left(), up.next_right()] {
+          if to.is_on_board() && self.has_enemy_piece(to, color) {
+            count += 1;
+          }
+        }
+        if piece.is_promoting_pawn() {
+          count * 4
+        } else {
+          count
+        }
+      }
+      Piece::Knight(..) => [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]
+        .iter()
+        .filter(|(row_step, col_step)| open(Position::new(row + row_step, col + col_step)))
+        .count() as u32,
+      Piece::Bishop(..) => slide(&DIAGONAL),
+      Piece::Rook(..) => slide(&ORTHOGONAL),
+      Piece::Queen(..) => slide(&DIAGONAL) + slide(&ORTHOGONAL),
+      Piece::King(..) => self.generate_moves_for_piece(pos).1.len() as u32,
+    }
+  }
+
   /// Is the current player in stalemate?
   pub fn is_stalemate(&self) -> bool {
-    (self.get_legal_moves().is_empty() && !self.is_in_check(self.get_current_player_color()))
+    (!self.has_legal_moves() && !self.is_in_check(self.get_current_player_color()))
       || (self.has_insufficient_material(self.turn)
         && self.has_insufficient_material(!self.turn))
   }
 
   /// Is the current player in checkmate?
   pub fn is_checkmate(&self) -> bool {
-    self.is_in_check(self.get_current_player_color()) && self.get_legal_moves().is_empty()
+    self.is_in_check(self.get_current_player_color()) && !self.has_legal_moves()
+  }
+
+  /// Hash the position so that it and its color mirror (colors swapped,
+  /// ranks flipped) hash the same.
+  ///
+  /// The pieces are keyed as in [`Board::color_normalized_hash`], once as
+  /// they stand and once mirrored with white and black keys swapped, and
+  /// the smaller of the two hashes is returned. Only piece placement is
+  /// hashed.
+  #[allow(dead_code)]
+  pub fn symmetric_hash(&self) -> u64 {
+    let (mut hash, mut mirrored) = (0, 0);
+    for (pos, piece) in self.pieces() {
+      let color = piece.get_color();
+      hash ^= piece_key(&piece, color, pos);
+      mirrored ^= piece_key(&piece, !color, mirror(pos));
+    }
+    hash.min(mirrored)
+  }
+
+  /// Hash the position as if white were to move, along with the color
+  /// actually to move.
+  ///
+  /// With black to move the board is mirrored first, so a position and its
+  /// color mirror get the same hash. Piece placement, castling rights and
+  /// the en passant square are hashed with Zobrist style keys.
+  #[allow(dead_code)]
+  pub fn color_normalized_hash(&self) -> (u64, Color) {
+    let turn = self.get_turn_color();
+    let normalize = |color: Color| match turn {
+      WHITE => color,
+      BLACK => !color,
+    };
+    let normalize_pos = |pos: Position| match turn {
+      WHITE => pos,
+      BLACK => mirror(pos),
+    };
+
+    let mut hash = 0;
+    for (pos, piece) in self.pieces() {
+      hash ^= piece_key(&piece, normalize(piece.get_color()), normalize_pos(pos));
+    }
+    for color in [WHITE, BLACK] {
+      let rights = self.get_castling_rights(color);
+      let offset = match normalize(color) {
+        WHITE => 0,
+        BLACK => 2,
+      };
+      if rights.can_kingside_castle() {
+        hash ^= zobrist_key(CASTLING_KEYS + offset);
+      }
+      if rights.can_queenside_castle() {
+        hash ^= zobrist_key(CASTLING_KEYS + offset + 1);
+      }
+    }
+    if let Some(en_passant) = self.en_passant {
+      hash ^= zobrist_key(EN_PASSANT_KEYS + square_index(normalize_pos(en_passant)));
+    }
+    (hash, turn)
+  }
+
+  // every piece on the board with its square
+  fn pieces(&self) -> impl Iterator<Item = (Position, Piece)> + '_ {
+    (0..8)
+      .flat_map(|row| (0..8).map(move |col| Position::new(row, col)))
+      .filter_map(move |pos| self.get_piece(pos).map(|piece| (pos, piece)))
+  }
+
+  /// Count the leaf positions reachable in exactly `depth` moves.
+  ///
+  /// This is the standard move generation check: the counts from the
+  /// starting position are published, so any difference points at a bug.
+  #[allow(dead_code)]
+  pub fn perft_recursive(&self, depth: u8) -> u64 {
+    debug_assert!(depth <= MAX_PERFT_DEPTH);
+    if depth == 0 {
+      return 1;
+    }
+
+    self.get_legal_moves()
+      .into_iter()
+      .map(|m| self.apply_eval_move(m).perft_recursive(depth - 1))
+      .sum()
+  }
+
+  /// The same count as `perft_recursive`, but walking the tree with an
+  /// explicit stack so deep searches don't overflow the contract's
+  /// limited call stack.
+  #[allow(dead_code)]
+  pub fn perft_iterative(&self, depth: u8) -> u64 {
+    debug_assert!(depth <= MAX_PERFT_DEPTH);
+    let mut count = 0;
+    let mut stack = vec![(*self, depth)];
+
+    while let Some((board, depth_remaining)) = stack.pop() {
+      if depth_remaining == 0 {
+        count += 1;
+        continue;
+      }
+      for m in board.get_legal_moves() {
+        stack.push((board.apply_eval_move(m), depth_remaining - 1));
+      }
+    }
+
+    count
+  }
+
+  /// The same count as `perft_recursive`, but one ply from the leaves the
+  /// legal moves are counted instead of played.
+  #[allow(dead_code)]
+  pub fn perft_bulk_counting(&self, depth: u8) -> u64 {
+    debug_assert!(depth <= MAX_PERFT_DEPTH);
+    match depth {
+      0 => 1,
+      1 => self.get_legal_moves().len() as u64,
+      _ => self.get_legal_moves()
+        .into_iter()
+        .map(|m| self.apply_eval_move(m).perft_bulk_counting(depth - 1))
+        .sum(),
+    }
   }
 
   /// Change the current turn to the next player.
@@ -787,7 +1423,10 @@ impl Board {
     self
   }
 
-  fn apply_move(&self, m: Move) -> Self {
+  /// Apply a move without checking that it is legal.
+  ///
+  /// The turn is not changed.
+  pub fn apply_move(&self, m: Move) -> Self {
     match m {
       Move::KingSideCastle => {
         if let Some(king_pos) = self.get_king_pos(self.turn) {
@@ -857,3 +1496,428 @@ impl Board {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cwchess::DEFAULT_FEN;
+  use crate::util::{format_fen, parse_fen};
+
+  #[test]
+  fn test_pseudo_legal_moves() {
+    let fens = [
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      // kiwipete
+      "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+      "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+      "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+      // in check
+      "4k3/8/8/8/8/8/4r3/4K3 w - - 0 1",
+      // stalemate
+      "7k/5KR1/8/8/8/8/8/8 b - - 0 1",
+    ];
+    for fen in fens {
+      let board = parse_fen(fen).unwrap();
+      let pseudo_legal = board.generate_pseudo_legal_moves();
+      let legal = board.generate_legal_moves();
+      assert!(pseudo_legal.len() >= legal.len(), "{}", fen);
+      assert_eq!(board.filter_legal(&pseudo_legal), legal, "{}", fen);
+      assert!(legal.iter().all(|m| pseudo_legal.contains(m)), "{}", fen);
+    }
+
+    // the pinned knight has pseudo-legal moves only
+    let board = parse_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+    let pinned = |moves: &MoveList| {
+      moves
+        .iter()
+        .filter(|m| matches!(m, Move::Piece(from, _) if *from == Position::pgn("e2").unwrap()))
+        .count()
+    };
+    assert_eq!(pinned(&board.generate_pseudo_legal_moves()), 6);
+    assert_eq!(pinned(&board.generate_legal_moves()), 0);
+  }
+
+  #[test]
+  fn test_promotion_capture_castling_rights() {
+    let board = parse_fen("r3k2r/1P6/8/r7/8/8/8/4K3 w kq - 0 1").unwrap();
+    let pos = |pgn: &str| Position::pgn(pgn).unwrap();
+    let continuing = |result: GameResult| match result {
+      GameResult::Continuing(board) => board,
+      result => panic!("unexpected result: {:?}", result),
+    };
+
+    // bxa8=N takes the queenside rook
+    let promotion = Move::Promotion(pos("b7"), pos("a8"), Piece::Knight(WHITE, pos("a8")));
+    let board = continuing(board.play_move(promotion));
+    assert!(!board.get_castling_rights(BLACK).can_queenside_castle());
+    assert!(board.get_castling_rights(BLACK).can_kingside_castle());
+
+    // another rook back on a8 does not bring the right back
+    let board = continuing(board.play_move(Move::Piece(pos("a5"), pos("a8"))));
+    let board = continuing(board.play_move(Move::Piece(pos("e1"), pos("d1"))));
+    assert_eq!(board.get_piece(pos("a8")), Some(Piece::Rook(BLACK, pos("a8"))));
+    assert!(!board.is_legal_move(Move::QueenSideCastle, BLACK));
+    assert!(board.is_legal_move(Move::KingSideCastle, BLACK));
+  }
+
+  #[test]
+  fn test_pawn_attacks() {
+    let board = parse_fen("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1").unwrap();
+    let square = |name| Position::pgn(name).unwrap();
+    assert!(board.attacked_by_pawn(Color::White, square("d5")));
+    assert!(board.attacked_by_pawn(Color::White, square("f5")));
+    assert!(!board.attacked_by_pawn(Color::White, square("e5")));
+    assert!(!board.attacked_by_pawn(Color::White, square("d4")));
+    assert!(!board.attacked_by_pawn(Color::Black, square("d5")));
+    assert_eq!(
+      board.pawn_attacks(Color::White),
+      1 << square_index(square("d5")) | 1 << square_index(square("f5"))
+    );
+
+    // edge pawns don't wrap around to the other side of the board
+    let board = parse_fen("4k3/p6p/8/8/8/8/P6P/4K3 w - - 0 1").unwrap();
+    for color in [Color::White, Color::Black] {
+      let attacks = board.pawn_attacks(color);
+      assert_eq!(attacks.count_ones(), 2);
+      for row in 0..8 {
+        for col in 0..8 {
+          let pos = Position::new(row, col);
+          assert_eq!(
+            attacks & 1 << square_index(pos) != 0,
+            board.attacked_by_pawn(color, pos),
+            "{}",
+            pos
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_perft() {
+    // published counts from the starting position
+    let expected = [1, 20, 400, 8902, 197281];
+    let board = Board::default();
+    for (depth, nodes) in expected.iter().enumerate() {
+      let depth = depth as u8;
+      assert_eq!(board.perft_recursive(depth), *nodes, "depth {}", depth);
+      assert_eq!(board.perft_iterative(depth), *nodes, "depth {}", depth);
+      assert_eq!(board.perft_bulk_counting(depth), *nodes, "depth {}", depth);
+    }
+  }
+
+  #[test]
+  fn test_perft_promotions() {
+    // published counts for a position with many promotions
+    let board = parse_fen("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1").unwrap();
+    assert_eq!(board.perft_recursive(1), 24);
+    assert_eq!(board.perft_recursive(2), 496);
+    assert_eq!(board.perft_recursive(3), 9483);
+
+    // g2 can push, or capture on f1 or h1, as each of the four pieces
+    let promotions = board.generate_promotions(Color::Black);
+    assert_eq!(promotions.len(), 3 * 4);
+    for name in ["queen", "rook", "bishop", "knight"] {
+      let count = promotions
+        .iter()
+        .filter(|m| matches!(m, Move::Promotion(_, _, piece) if piece.get_name() == name))
+        .count();
+      assert_eq!(count, 3, "{}", name);
+    }
+    assert!(board.generate_promotions(Color::White).iter().all(|m| matches!(
+      m,
+      Move::Promotion(_, _, piece) if piece.get_color() == Color::White
+    )));
+  }
+
+  #[test]
+  fn test_generate_legal_moves_from() {
+    let f3 = Position::pgn("f3").unwrap();
+
+    // nothing in the way of the knight
+    let board = parse_fen("4k3/8/8/8/8/5N2/8/K7 w - - 0 1").unwrap();
+    assert_eq!(board.generate_legal_moves_from(f3).len(), 8);
+
+    // own pawns on d4 and h2 take two squares
+    let board = parse_fen("4k3/8/8/8/3P4/5N2/7P/K7 w - - 0 1").unwrap();
+    let moves = board.generate_legal_moves_from(f3);
+    assert_eq!(moves.len(), 6);
+    assert!(!moves.contains(&Move::Piece(f3, Position::pgn("d4").unwrap())));
+    assert!(!moves.contains(&Move::Piece(f3, Position::pgn("h2").unwrap())));
+
+    // empty square, or the other player's piece
+    assert!(board.generate_legal_moves_from(Position::pgn("f4").unwrap()).is_empty());
+    assert!(board.generate_legal_moves_from(Position::pgn("e8").unwrap()).is_empty());
+
+    // a pinned knight has no moves
+    let board = parse_fen("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1").unwrap();
+    assert!(board.generate_legal_moves_from(Position::pgn("c3").unwrap()).is_empty());
+  }
+
+  #[test]
+  fn test_starting_position() {
+    let board = Board::starting_position();
+    assert_eq!(format_fen(&board, 0, 1).unwrap(), DEFAULT_FEN);
+    assert_eq!(board, parse_fen(DEFAULT_FEN).unwrap());
+    assert_eq!(board, Board::default());
+  }
+
+  #[test]
+  fn test_generate_moves_for_piece() {
+    let b4 = Position::pgn("b4").unwrap();
+    let c3 = Position::pgn("c3").unwrap();
+
+    // the knight on c3 is pinned, black's bishop isn't to move
+    let board = parse_fen("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1").unwrap();
+    let (pseudo_legal, legal) = board.generate_moves_for_piece(c3);
+    assert_eq!(pseudo_legal.len(), 8);
+    assert!(legal.is_empty());
+    let (pseudo_legal, legal) = board.generate_moves_for_piece(b4);
+    assert_eq!(pseudo_legal.len(), 7);
+    assert_eq!(legal, pseudo_legal);
+    assert!(legal.contains(&Move::Piece(b4, c3)));
+
+    // white's en passant square isn't black's
+    let board = parse_fen("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1").unwrap();
+    let (pseudo_legal, _) = board.generate_moves_for_piece(Position::pgn("d5").unwrap());
+    assert_eq!(pseudo_legal.len(), 2);
+    let (pseudo_legal, _) = board.generate_moves_for_piece(Position::pgn("e5").unwrap());
+    assert_eq!(pseudo_legal.len(), 1);
+
+    let (pseudo_legal, legal) = board.generate_moves_for_piece(Position::pgn("a1").unwrap());
+    assert!(pseudo_legal.is_empty() && legal.is_empty());
+  }
+
+  #[test]
+  fn test_count_legal_moves() {
+    for fen in [
+      DEFAULT_FEN,
+      // pins, a check and en passant
+      "4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1",
+      "4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1",
+      "4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1",
+      "rnb1kbnr/pppp1ppp/8/4p3/5PPq/8/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+      // promotions, both ways
+      "r3k3/1P6/8/8/8/8/6p1/4K2R w Kq - 0 1",
+      "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+      "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1",
+    ] {
+      let board = parse_fen(fen).unwrap();
+      for color in [WHITE, BLACK] {
+        let listed: usize = board
+          .pieces()
+          .filter(|(_, piece)| piece.get_color() == color)
+          .map(|(pos, _)| board.generate_moves_for_piece(pos).1.len())
+          .sum();
+        assert_eq!(board.count_legal_moves(color) as usize, listed, "{} {:?}", fen, color);
+      }
+    }
+  }
+
+  // colors swapped and ranks flipped, e.g. for a black to move copy of a puzzle
+  fn mirror_fen(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split(' ').collect();
+    let swap_case = |s: &str| -> String {
+      s.chars()
+        .map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+        .collect()
+    };
+    let placement = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>().join("/");
+    let turn = if fields[1] == "w" { "b" } else { "w" };
+    let en_passant = match fields[3] {
+      "-" => "-".to_string(),
+      square => format!("{}{}", &square[..1], 9 - square[1..].parse::<u8>().unwrap()),
+    };
+    format!("{} {} {} {} {} {}", placement, turn, swap_case(fields[2]), en_passant, fields[4], fields[5])
+  }
+
+  #[test]
+  fn test_color_normalized_hash() {
+    for fen in [
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "r3k2r/ppp2ppp/2n5/3qp3/8/2N2N2/PPPP1PPP/R1BQK2R w Kkq - 0 1",
+      "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+      "8/5k2/8/3K4/8/8/1P6/8 b - - 0 1",
+    ] {
+      let board = parse_fen(fen).unwrap();
+      let mirrored = parse_fen(&mirror_fen(fen)).unwrap();
+      let (hash, turn) = board.color_normalized_hash();
+      let (mirrored_hash, mirrored_turn) = mirrored.color_normalized_hash();
+      assert_eq!(hash, mirrored_hash, "{}", fen);
+      assert_eq!(turn, !mirrored_turn);
+      assert_eq!(board.symmetric_hash(), mirrored.symmetric_hash(), "{}", fen);
+    }
+
+    // side to move, castling rights and en passant all count
+    let hash = |fen: &str| parse_fen(fen).unwrap().color_normalized_hash().0;
+    let e4 = hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+    assert_ne!(e4, hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"));
+    assert_ne!(e4, hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w Kkq - 0 1"));
+    assert_ne!(
+      hash("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+      hash("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+    );
+    // different placement
+    let board = parse_fen("8/5k2/8/3K4/8/8/1P6/8 w - - 0 1").unwrap();
+    let moved = parse_fen("8/5k2/8/3K4/8/1P6/8/8 w - - 0 1").unwrap();
+    assert_ne!(board.symmetric_hash(), moved.symmetric_hash());
+
+    // color symmetric positions do not cancel out
+    let kings = parse_fen("8/8/8/4k3/8/8/8/4K3 w - - 0 1").unwrap();
+    let other_kings = parse_fen("8/8/8/3k4/8/8/8/3K4 w - - 0 1").unwrap();
+    assert_ne!(Board::default().symmetric_hash(), 0);
+    assert_ne!(kings.symmetric_hash(), 0);
+    assert_ne!(kings.symmetric_hash(), other_kings.symmetric_hash());
+    assert_ne!(kings.symmetric_hash(), Board::default().symmetric_hash());
+  }
+
+  #[test]
+  fn test_board_hash() {
+    use std::collections::HashMap;
+
+    let board = Board::default();
+    let no_castle = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").unwrap();
+    let black_to_move = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+
+    // same placement, different castling rights or side to move
+    assert_ne!(board, no_castle);
+    assert_ne!(board, black_to_move);
+
+    let mut table = HashMap::new();
+    table.insert(board, 1);
+    table.insert(no_castle, 2);
+    table.insert(black_to_move, 3);
+    assert_eq!(table.len(), 3);
+
+    // transposition: knights out and back reach the starting position
+    let mut transposed = board;
+    for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+      transposed = transposed.apply_eval_move(Move::parse(m.to_string()).unwrap());
+    }
+    assert_eq!(table.get(&transposed), Some(&1));
+  }
+
+  #[test]
+  fn test_evaluate_detailed() {
+    // symmetric, so every part cancels out
+    let evaluation = Board::default().evaluate_detailed();
+    assert_eq!(evaluation.king_safety, 0);
+    assert_eq!(evaluation.material, 0);
+    assert_eq!(evaluation.mobility, 0);
+    assert_eq!(evaluation.pawn_structure, 0);
+    assert_eq!(evaluation.piece_square, 0);
+    assert_eq!(evaluation.total(), 0);
+
+    // 1. e4 improves white's pawn placement
+    let evaluation = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+      .unwrap()
+      .evaluate_detailed();
+    assert_eq!(evaluation.material, 0);
+    // every piece is still on the board, mobility does not count yet
+    assert_eq!(evaluation.mobility, 0);
+    assert!(evaluation.piece_square > 0);
+    // but the e-pawn no longer covers the king
+    assert_eq!(evaluation.king_safety, -PAWN_SHIELD_CENTIPAWNS);
+    assert_eq!(evaluation.pawn_structure, 0);
+
+    // 1. e4 f5 2. exf5 doubles white's f-pawns
+    let evaluation = parse_fen("rnbqkbnr/ppppp1pp/8/5P2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2")
+      .unwrap()
+      .evaluate_detailed();
+    assert_eq!(evaluation.pawn_structure, -DOUBLED_PAWN_CENTIPAWNS);
+
+    // rook endgame, the rook has 10 moves and each king 5 and 8
+    let evaluation = parse_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 40").unwrap().evaluate_detailed();
+    assert_eq!(evaluation.mobility, 7 * MOBILITY_CENTIPAWNS * 22 / Phase::MAX_VALUE as i32);
+
+    // black is missing the queen
+    let evaluation = parse_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+      .unwrap()
+      .evaluate_detailed();
+    assert_eq!(evaluation.material, 900);
+    assert_eq!(
+      evaluation.total(),
+      evaluation.king_safety
+        + evaluation.material
+        + evaluation.mobility
+        + evaluation.pawn_structure
+        + evaluation.piece_square
+    );
+
+    // the search sees the same evaluation
+    for fen in [
+      DEFAULT_FEN,
+      "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "8/8/8/4k3/8/8/8/R3K3 b - - 0 40",
+    ] {
+      let board = parse_fen(fen).unwrap();
+      assert_eq!(board.value_for(WHITE), board.evaluate_detailed().total());
+      assert_eq!(board.value_for(BLACK), -board.evaluate_detailed().total());
+    }
+  }
+
+  #[test]
+  fn test_get_phase() {
+    let board = Board::default();
+    assert_eq!(board.get_phase_value(), Phase::MAX_VALUE);
+    assert_eq!(board.get_phase(), Phase::Opening);
+    assert_eq!(
+      board.get_piece_counts(WHITE),
+      PieceCounts {
+        pawns: 8,
+        knights: 2,
+        bishops: 2,
+        rooks: 2,
+        queens: 1,
+      }
+    );
+
+    // queens traded
+    let board = parse_fen("rnb1kbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 0 3").unwrap();
+    assert_eq!(board.get_phase_value(), 16);
+    assert_eq!(board.get_phase(), Phase::Middlegame);
+
+    // king and pawn endgame
+    let board = parse_fen("8/5k2/8/3p4/3P4/8/5K2/8 w - - 0 1").unwrap();
+    assert_eq!(board.get_phase_value(), 0);
+    assert_eq!(board.get_phase(), Phase::Endgame);
+    assert_eq!(board.get_piece_counts(BLACK).pawns, 1);
+  }
+
+  #[test]
+  fn test_has_legal_moves() {
+    // fool's mate, white is checkmated
+    let board = parse_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert!(!board.has_legal_moves());
+    assert!(board.is_checkmate());
+
+    // black king is stalemated by the queen
+    let board = parse_fen("7k/8/6QK/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(!board.has_legal_moves());
+    assert!(board.is_stalemate());
+
+    // in check, the only moves block with the knight or take the checking
+    // pawn en passant
+    for fen in ["4r1k1/8/8/8/8/8/3P1P2/R2NKR2 w - - 0 1", "8/8/7R/kp6/1Pp5/2NN4/8/7K b - b3 0 1"] {
+      let board = parse_fen(fen).unwrap();
+      assert_eq!(board.get_legal_moves().len(), 1, "{}", fen);
+      assert!(board.has_legal_moves(), "{}", fen);
+    }
+
+    // walk through a deterministic game and compare with the full move list
+    let mut board = Board::default();
+    for ply in 0..50 {
+      assert_eq!(
+        board.has_legal_moves(),
+        !board.get_legal_moves().is_empty(),
+        "ply {}",
+        ply
+      );
+      let moves = board.get_legal_moves();
+      board = match board.play_move(moves[(ply * 7) % moves.len()]) {
+        GameResult::Continuing(next) => next,
+        _ => Board::default(),
+      };
+    }
+  }
+}
@@ -0,0 +1,231 @@
+//! Precomputed attack tables for the pieces whose moves don't depend on
+//! the rest of the board, and ray masks for the sliding pieces.
+//!
+//! Squares are numbered `row * 8 + col`, so a1 is 0, h1 is 7 and h8 is 63.
+//! The tables are built by `const fn`s at compile time, so no build script
+//! is needed.
+use crate::position::Position;
+
+/// A set of squares, one bit per square.
+pub type Bitboard = u64;
+
+/// `KING_ATTACKS[sq]` is every square a king on `sq` can move to.
+pub const KING_ATTACKS: [Bitboard; 64] = attack_table(&[
+  (-1, -1), (-1, 0), (-1, 1),
+  (0, -1),           (0, 1),
+  (1, -1),  (1, 0),  (1, 1),
+]);
+
+/// `KNIGHT_ATTACKS[sq]` is every square a knight on `sq` can move to.
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = attack_table(&[
+  (-2, -1), (-2, 1), (-1, -2), (-1, 2),
+  (1, -2),  (1, 2),  (2, -1),  (2, 1),
+]);
+
+/// Every square except those on the a file.
+pub const NOT_A_FILE: Bitboard = !0x0101_0101_0101_0101;
+
+/// Every square except those on the h file.
+pub const NOT_H_FILE: Bitboard = !0x8080_8080_8080_8080;
+
+/// `RAY_MASKS[sq][dir]` is every square from `sq` to the edge of the board
+/// in [`Direction`] `dir`, not including `sq`.
+pub const RAY_MASKS: [[Bitboard; 8]; 64] = ray_table();
+
+/// One of the eight directions a queen can slide in, clockwise from north.
+///
+/// North is towards the eighth rank and east towards the h file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+  N = 0,
+  NE = 1,
+  E = 2,
+  SE = 3,
+  S = 4,
+  SW = 5,
+  W = 6,
+  NW = 7,
+}
+
+impl Direction {
+  /// Every direction, in index order.
+  pub const ALL: [Direction; 8] = [
+    Direction::N,
+    Direction::NE,
+    Direction::E,
+    Direction::SE,
+    Direction::S,
+    Direction::SW,
+    Direction::W,
+    Direction::NW,
+  ];
+
+  /// Every square from square number `from` to the edge of the board in
+  /// this direction, not including `from`.
+  #[inline]
+  pub fn ray_mask(self, from: usize) -> Bitboard {
+    RAY_MASKS[from][self as usize]
+  }
+
+  /// The squares strictly between square numbers `a` and `b`.
+  ///
+  /// Empty when the squares are not on a common rank, file or diagonal.
+  pub fn between(a: usize, b: usize) -> Bitboard {
+    Direction::ALL
+      .iter()
+      .find(|dir| dir.ray_mask(a) & (1 << b) != 0)
+      .map(|dir| dir.ray_mask(a) & !dir.ray_mask(b) & !(1 << b))
+      .unwrap_or(0)
+  }
+}
+
+// row and column steps of each direction, in Direction order
+const RAY_OFFSETS: [(i32, i32); 8] = [
+  (1, 0), (1, 1), (0, 1), (-1, 1),
+  (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+
+const fn ray_table() -> [[Bitboard; 8]; 64] {
+  let mut table = [[0; 8]; 64];
+  let mut sq = 0;
+  while sq < 64 {
+    let mut dir = 0;
+    while dir < 8 {
+      let mut row = (sq / 8) as i32 + RAY_OFFSETS[dir].0;
+      let mut col = (sq % 8) as i32 + RAY_OFFSETS[dir].1;
+      while row >= 0 && row < 8 && col >= 0 && col < 8 {
+        table[sq][dir] |= 1 << (row * 8 + col);
+        row += RAY_OFFSETS[dir].0;
+        col += RAY_OFFSETS[dir].1;
+      }
+      dir += 1;
+    }
+    sq += 1;
+  }
+  table
+}
+
+const fn attack_table(offsets: &[(i32, i32); 8]) -> [Bitboard; 64] {
+  let mut table = [0; 64];
+  let mut sq = 0;
+  while sq < 64 {
+    let row = (sq / 8) as i32;
+    let col = (sq % 8) as i32;
+    let mut i = 0;
+    while i < offsets.len() {
+      let to_row = row + offsets[i].0;
+      let to_col = col + offsets[i].1;
+      if to_row >= 0 && to_row < 8 && to_col >= 0 && to_col < 8 {
+        table[sq] |= 1 << (to_row * 8 + to_col);
+      }
+      i += 1;
+    }
+    sq += 1;
+  }
+  table
+}
+
+/// The square number of a position, or None when it is off the board.
+#[inline]
+pub fn square_index(pos: Position) -> Option<usize> {
+  if pos.is_on_board() {
+    Some((pos.get_row() * 8 + pos.get_col()) as usize)
+  } else {
+    None
+  }
+}
+
+/// Does `table` hold a move from `from` to `to`?
+#[inline]
+pub fn attacks(table: &[Bitboard; 64], from: Position, to: Position) -> bool {
+  match (square_index(from), square_index(to)) {
+    (Some(from), Some(to)) => table[from] & (1 << to) != 0,
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn squares(names: &[&str]) -> Bitboard {
+    names
+      .iter()
+      .map(|name| 1 << square_index(Position::pgn(name).unwrap()).unwrap())
+      .fold(0, |acc, bit| acc | bit)
+  }
+
+  #[test]
+  fn test_king_attacks() {
+    assert_eq!(KING_ATTACKS[0], squares(&["b1", "a2", "b2"]));
+    assert_eq!(
+      KING_ATTACKS[27],
+      squares(&["c3", "d3", "e3", "c4", "e4", "c5", "d5", "e5"])
+    );
+    assert_eq!(KING_ATTACKS[63], squares(&["g8", "g7", "h7"]));
+  }
+
+  #[test]
+  fn test_knight_attacks() {
+    assert_eq!(KNIGHT_ATTACKS[0], squares(&["b3", "c2"]));
+    assert_eq!(KNIGHT_ATTACKS[27].count_ones(), 8);
+    assert_eq!(KNIGHT_ATTACKS[6], squares(&["e2", "f3", "h3"]));
+  }
+
+  #[test]
+  fn test_ray_masks_center() {
+    let d4 = square_index(Position::pgn("d4").unwrap()).unwrap();
+    let expected = [
+      (Direction::N, squares(&["d5", "d6", "d7", "d8"])),
+      (Direction::NE, squares(&["e5", "f6", "g7", "h8"])),
+      (Direction::E, squares(&["e4", "f4", "g4", "h4"])),
+      (Direction::SE, squares(&["e3", "f2", "g1"])),
+      (Direction::S, squares(&["d3", "d2", "d1"])),
+      (Direction::SW, squares(&["c3", "b2", "a1"])),
+      (Direction::W, squares(&["c4", "b4", "a4"])),
+      (Direction::NW, squares(&["c5", "b6", "a7"])),
+    ];
+    for (dir, mask) in expected {
+      assert_eq!(dir.ray_mask(d4), mask, "{:?}", dir);
+    }
+    // a queen on d4 sees 27 squares on an empty board
+    let queen = Direction::ALL.iter().fold(0, |acc, dir| acc | dir.ray_mask(d4));
+    assert_eq!(queen.count_ones(), 27);
+  }
+
+  #[test]
+  fn test_ray_masks_corners() {
+    let a1 = square_index(Position::pgn("a1").unwrap()).unwrap();
+    let h8 = square_index(Position::pgn("h8").unwrap()).unwrap();
+    for dir in [Direction::SE, Direction::S, Direction::SW, Direction::W, Direction::NW] {
+      assert_eq!(dir.ray_mask(a1), 0, "{:?}", dir);
+    }
+    assert_eq!(Direction::N.ray_mask(a1).count_ones(), 7);
+    assert_eq!(Direction::NE.ray_mask(a1), 0x8040_2010_0804_0200);
+    assert_eq!(
+      Direction::E.ray_mask(a1),
+      squares(&["b1", "c1", "d1", "e1", "f1", "g1", "h1"])
+    );
+
+    for dir in [Direction::N, Direction::NE, Direction::E, Direction::SE, Direction::NW] {
+      assert_eq!(dir.ray_mask(h8), 0, "{:?}", dir);
+    }
+    assert_eq!(Direction::SW.ray_mask(h8), 0x0040_2010_0804_0201);
+  }
+
+  #[test]
+  fn test_between() {
+    let sq = |name: &str| square_index(Position::pgn(name).unwrap()).unwrap();
+    assert_eq!(
+      Direction::between(sq("a1"), sq("h8")),
+      squares(&["b2", "c3", "d4", "e5", "f6", "g7"])
+    );
+    assert_eq!(Direction::between(sq("h8"), sq("a1")), Direction::between(sq("a1"), sq("h8")));
+    assert_eq!(Direction::between(sq("d1"), sq("d4")), squares(&["d2", "d3"]));
+    assert_eq!(Direction::between(sq("g2"), sq("b7")), squares(&["f3", "e4", "d5", "c6"]));
+    // neighbours and unaligned squares
+    assert_eq!(Direction::between(sq("e4"), sq("e5")), 0);
+    assert_eq!(Direction::between(sq("e4"), sq("f6")), 0);
+    assert_eq!(Direction::between(sq("e4"), sq("e4")), 0);
+  }
+}
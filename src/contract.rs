@@ -1,24 +1,47 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-  to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage
+  to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply,
+  Response, StdError, StdResult, Storage, SubMsg, WasmMsg
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw_storage_plus::Bound;
+use std::cmp::Reverse;
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+use crate::cwchess::{
+  forced_mate, normalize_start_fen, set_square, start_position_hash, CwChessAction, CwChessAdjudication, CwChessColor, CwChessEvaluation,
+  CwChessFenAnalysis, CwChessGame, CwChessGameOver, CwChessGamePhase, CwChessIllegalMove, CwChessLegalMove,
+  CwChessMaterialImbalance, CwChessMovePreview, CwChessPhantom, CwChessPieceMoves, DEFAULT_FEN, MAX_ADJUDICATION_CENTIPAWNS, MAX_ADJUDICATION_DEPTH,
+  MOVE_CONFIRM_BLOCKS, RESIGN_CONFIRM_BLOCKS,
+  EMPTY_FEN, VARIANTS
+};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg, RatingSummary};
+use crate::msg::{
+  validate_create_challenge, ExecuteMsg, GameReplay, GameStatus, GameSummary, InstantiateMsg, MigrateMsg, QueryMsg,
+  LeaderboardEntry, MoveEvaluation, RatingSummary, SubscriberMsg, VariantSummary,
+  MAX_INITIAL_RATING, MIN_INITIAL_RATING
+};
 use crate::state::{
-  get_challenges_map, get_games_map, merge_iters, next_challenge_id,
-  next_game_id, Challenge, State, STATE, RATINGS
+  backfill_standings, game_status_key, get_challenges_map, get_games_map, get_standings_map, merge_iters, next_challenge_id, next_game_id,
+  pairing_key, atomic_elo_update, rank_key, record_opening_result, reindex_games, save_game_record, save_rating, Challenge, GameRecord, OpeningStats,
+  Standing, State, ARCHIVED_GAME_NOTES, COMPLETED_GAMES, GAME_NOTES, MAX_NOTE_CHARS, MAX_SUBSCRIBERS,
+  NOTE_ARCHIVE_BLOCKS, DEFAULT_RATE_LIMIT_PER_BLOCK, OPENING_STATS, RATINGS, RECENT_GAMES, STATE, SUBSCRIBERS
 };
-use crate::elo::{elo, EloRating, EloConfig, Outcomes};
+use crate::elo::{elo, validate_k_schedule, EloRating, EloConfig, Outcomes};
+use crate::util::chess_notation::{is_valid_san, is_valid_uci};
+use crate::util::rate_limiter::check_and_increment;
+use crate::util::rng::seed_from;
+
+pub mod reply;
+use reply::ReplyId;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "cosmos-chess";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const DEFAULT_LIMIT: u32 = 25;
+const MAX_LIMIT: u32 = 100;
+// gas each subscriber gets to handle a notification
+const SUBSCRIBER_GAS_LIMIT: u64 = 200_000;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -28,7 +51,12 @@ pub fn instantiate(
   _msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
   let state = State {
+    adjudication: CwChessAdjudication::default(),
+    k_schedule: vec![],
+    max_rejected_moves: 0,
+    min_draw_plies: 0,
     owner: info.sender.clone(),
+    rate_limit_per_block: DEFAULT_RATE_LIMIT_PER_BLOCK,
   };
   set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
   STATE.save(deps.storage, &state)?;
@@ -38,6 +66,29 @@ pub fn instantiate(
     .add_attribute("owner", info.sender))
 }
 
+// games are stored as they are played, with the block height of every move,
+// and clocks are counted from those heights, so in-progress games keep their
+// clocks across a migration without being rewritten
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+  let version = get_contract_version(deps.storage)?;
+  if version.contract != CONTRACT_NAME {
+    return Err(ContractError::WrongContract {
+      contract: version.contract,
+    });
+  }
+  // games and ratings from before the upgrade are missing from the newer
+  // indexes and the standings
+  reindex_games(deps.storage)?;
+  backfill_standings(deps.storage)?;
+  set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+  Ok(Response::new()
+    .add_attribute("method", "migrate")
+    .add_attribute("from_version", version.version)
+    .add_attribute("to_version", CONTRACT_VERSION))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
   deps: DepsMut,
@@ -45,6 +96,13 @@ pub fn execute(
   info: MessageInfo,
   msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+  // the owner's admin messages are not counted, e.g. setting the initial
+  // rating of many players at once
+  let state = STATE.load(deps.storage)?;
+  if !(is_owner_message(&msg) && info.sender == state.owner) {
+    check_and_increment(deps.storage, &info.sender, env.block.height, state.rate_limit_per_block)?;
+  }
+
   match msg {
     ExecuteMsg::AcceptChallenge { challenge_id } => {
       execute_accept_challenge(deps, env, info, challenge_id)
@@ -53,17 +111,127 @@ pub fn execute(
       execute_cancel_challenge(deps, info, challenge_id)
     }
     ExecuteMsg::CreateChallenge {
+      adjudication,
+      armageddon,
       block_limit,
+      confirm_moves,
+      confirm_resign,
+      max_plies,
       opponent,
       play_as,
-    } => execute_create_challenge(deps, env, info, block_limit, opponent, play_as),
+      start_at,
+      start_fen,
+    } => execute_create_challenge(
+      deps,
+      env,
+      info,
+      opponent,
+      play_as,
+      ChallengeOptions {
+        adjudication,
+        armageddon,
+        block_limit,
+        confirm_moves,
+        confirm_resign,
+        draft: false,
+        max_plies,
+        start_at,
+        start_fen,
+      },
+    ),
+    ExecuteMsg::CreateDraft {
+      adjudication,
+      block_limit,
+      max_plies,
+      opponent,
+      play_as,
+    } => execute_create_challenge(
+      deps,
+      env,
+      info,
+      opponent,
+      play_as,
+      ChallengeOptions {
+        adjudication,
+        block_limit,
+        draft: true,
+        max_plies,
+        start_fen: Some(EMPTY_FEN.to_string()),
+        ..ChallengeOptions::default()
+      },
+    ),
+    ExecuteMsg::CreatePractice {
+      phantom_rating,
+      play_as,
+      start_fen,
+    } => execute_create_practice(deps, env, info, phantom_rating, play_as, start_fen),
+    ExecuteMsg::PlacePiece {
+      challenge_id,
+      piece,
+      square,
+    } => execute_edit_draft(deps, info, challenge_id, square, Some(piece)),
+    ExecuteMsg::RemovePiece {
+      challenge_id,
+      square,
+    } => execute_edit_draft(deps, info, challenge_id, square, None),
+    ExecuteMsg::LockPosition { challenge_id } => {
+      execute_lock_position(deps, info, challenge_id)
+    }
+    ExecuteMsg::AgreeDraw { game_id } => execute_agree_draw(deps, env, info, game_id),
     ExecuteMsg::DeclareTimeout { game_id } => execute_declare_timeout(deps, env, game_id),
+    ExecuteMsg::ConfirmMove { game_id } => execute_confirm_move(deps, env, info, game_id),
+    ExecuteMsg::CancelMove { game_id } => execute_cancel_move(deps, info, game_id),
+    ExecuteMsg::Adjudicate { game_id, winner } => execute_adjudicate(deps, env, info, game_id, winner),
+    ExecuteMsg::SetGameNote { game_id, note } => execute_set_game_note(deps, info, game_id, note),
+    ExecuteMsg::ArchiveGameNotes { game_id } => execute_archive_game_notes(deps, env, game_id),
+    ExecuteMsg::SetInitialRating { player, rating } => {
+      execute_set_initial_rating(deps, info, player, rating)
+    }
+    ExecuteMsg::SetAdjudication {
+      depth,
+      min_moves,
+      threshold_centipawns,
+    } => execute_set_adjudication(deps, info, depth, min_moves, threshold_centipawns),
+    ExecuteMsg::SetKSchedule { schedule } => execute_set_k_schedule(deps, info, schedule),
+    ExecuteMsg::SetMaxRejectedMoves { max } => execute_set_max_rejected_moves(deps, info, max),
+    ExecuteMsg::SetMinDrawPlies { plies } => execute_set_min_draw_plies(deps, info, plies),
+    ExecuteMsg::SetRateLimit { per_block } => execute_set_rate_limit(deps, info, per_block),
+    ExecuteMsg::Subscribe { game_id, callback } => {
+      execute_subscribe(deps, info, game_id, callback)
+    }
     ExecuteMsg::Turn { action, game_id } => execute_turn(deps, env, info, action, game_id),
+    ExecuteMsg::Unsubscribe { game_id, callback } => {
+      execute_unsubscribe(deps, info, game_id, callback)
+    }
+  }
+}
+
+// messages only the owner can send
+fn is_owner_message(msg: &ExecuteMsg) -> bool {
+  matches!(
+    msg,
+    ExecuteMsg::Adjudicate { .. }
+      | ExecuteMsg::SetAdjudication { .. }
+      | ExecuteMsg::SetInitialRating { .. }
+      | ExecuteMsg::SetKSchedule { .. }
+      | ExecuteMsg::SetMaxRejectedMoves { .. }
+      | ExecuteMsg::SetMinDrawPlies { .. }
+      | ExecuteMsg::SetRateLimit { .. }
+  )
+}
+
+// a failing subscriber must not block the game, so its error is dropped
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+  match ReplyId::from_u64(msg.id).ok_or_else(|| StdError::generic_err("Unknown reply ID"))? {
+    ReplyId::SubscriberNotification => Ok(Response::new()
+      .add_attribute("action", "subscriber_failed")
+      .add_attribute("reply_id", msg.id.to_string())),
   }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
   match msg {
     QueryMsg::GetGame {
       game_id
@@ -80,13 +248,103 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
       game_over,
       player,
     } => to_binary(&query_get_games(deps, after, game_over, player)?),
+    QueryMsg::ActiveGameBetween {
+      player_a,
+      player_b,
+    } => to_binary(&query_active_game_between(deps, player_a, player_b)?),
+    QueryMsg::GamesByStatus {
+      status,
+      start_after,
+      limit,
+    } => to_binary(&query_games_by_status(deps, status, start_after, limit)?),
+    QueryMsg::GamesByPlayer {
+      player,
+      status,
+      start_after,
+      limit,
+    } => to_binary(&query_games_by_player(deps, player, status, start_after, limit)?),
     QueryMsg::ValidMove {
       game_id,
       player,
       move_str,
     } => to_binary(&query_valid_move(deps, game_id, &player, &move_str)?),
+    QueryMsg::LegalMoves {
+      game_id,
+      perspective,
+      square,
+    } => to_binary(&query_legal_moves(deps, game_id, perspective, square)?),
+    QueryMsg::LegalMovesSan {
+      game_id,
+    } => to_binary(&query_legal_moves_san(deps, game_id)?),
+    QueryMsg::Replay { game_id } => to_binary(&query_replay(deps, game_id)?),
+    QueryMsg::UciMoves { game_id } => to_binary(&query_uci_moves(deps, game_id)?),
+    QueryMsg::PieceMoves {
+      game_id,
+      square,
+    } => to_binary(&query_piece_moves(deps, game_id, &square)?),
+    QueryMsg::ExplainMove {
+      game_id,
+      player,
+      from,
+      to,
+    } => to_binary(&query_explain_move(deps, game_id, &player, &from, &to)?),
+    QueryMsg::GamePhase {
+      game_id,
+    } => to_binary(&query_game_phase(deps, game_id)?),
+    QueryMsg::MaterialImbalance {
+      game_id,
+    } => to_binary(&query_material_imbalance(deps, game_id)?),
+    QueryMsg::ShouldResign {
+      game_id,
+      for_color,
+    } => to_binary(&query_should_resign(deps, game_id, for_color)?),
+    QueryMsg::TopMoves {
+      game_id,
+      n,
+      depth,
+    } => to_binary(&query_top_moves(deps, game_id, n, depth)?),
+    QueryMsg::PositionEvaluation {
+      fen,
+    } => to_binary(&query_position_evaluation(&fen)?),
+    QueryMsg::AnalyzeFen {
+      fen,
+    } => to_binary(&query_analyze_fen(&fen)?),
+    QueryMsg::ForcedMate {
+      fen,
+      moves,
+    } => to_binary(&query_forced_mate(&fen, moves)?),
+    QueryMsg::GamesFromPosition {
+      fen,
+      start_after,
+      limit,
+    } => to_binary(&query_games_from_position(deps, &fen, start_after, limit)?),
+    QueryMsg::CompletedGame {
+      game_id,
+    } => to_binary(&query_completed_game(deps, game_id)?),
+    QueryMsg::ArchivedPgn {
+      game_id,
+    } => to_binary(&query_archived_pgn(deps, env, game_id)?),
+    QueryMsg::GameNotes {
+      game_id,
+    } => to_binary(&query_game_notes(deps, game_id)?),
+    QueryMsg::RecentCompletedGames {
+      limit,
+    } => to_binary(&query_recent_completed_games(deps, limit)?),
+    QueryMsg::OpeningStats {
+      eco_code,
+    } => to_binary(&query_opening_stats(deps, &eco_code)?),
+    QueryMsg::BestOpeningsForColor {
+      color,
+      min_games,
+    } => to_binary(&query_best_openings_for_color(deps, color, min_games)?),
     QueryMsg::GetRatings {
     } => to_binary(&query_get_ratings(deps)?),
+    QueryMsg::Leaderboard {
+      start_after,
+      limit,
+    } => to_binary(&query_leaderboard(deps, start_after, limit)?),
+    QueryMsg::SupportedVariants {
+    } => to_binary(&query_supported_variants()),
     QueryMsg::GetTurn {
       game_id,
       player,
@@ -109,6 +367,9 @@ fn execute_accept_challenge(
       if challenge.created_by == player {
         return Err(ContractError::CannotPlaySelf {});
       }
+      if challenge.draft {
+        return Err(ContractError::PositionNotLocked {});
+      }
       if let Some(opponent) = challenge.opponent.clone() {
         if opponent != player {
           return Err(ContractError::NotYourChallenge {});
@@ -129,18 +390,38 @@ fn execute_accept_challenge(
     challenge.created_by.clone(),
     player,
     challenge.play_as,
-    block_start,
+    seed_from(&env, &challenge_id.to_be_bytes()),
   );
   // create game
   let game = CwChessGame {
+    adjudication: if challenge.adjudication {
+      Some(STATE.load(deps.storage)?.adjudication)
+    } else {
+      None
+    },
+    armageddon: challenge.armageddon,
+    black_agrees_draw: false,
+    black_rejected_moves: 0,
     block_limit: challenge.block_limit,
     block_start,
-    fen: DEFAULT_FEN.to_string(),
+    confirm_moves: challenge.confirm_moves,
+    confirm_resign: challenge.confirm_resign,
+    fen: challenge.start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string()),
     game_id,
+    last_move_uci: None,
+    mate_pattern: None,
+    max_plies: challenge.max_plies,
     player1: player1.clone(),
     player2: player2.clone(),
     moves: vec![],
+    phantom: None,
+    pending_move: None,
+    resign_pending_at: None,
+    start_at: challenge.start_at,
+    start_fen: challenge.start_fen.clone(),
     status: None,
+    white_agrees_draw: false,
+    white_rejected_moves: 0,
   };
   // update storage
   let games_map = get_games_map();
@@ -155,6 +436,81 @@ fn execute_accept_challenge(
     .add_attribute("player2", player2))
 }
 
+fn execute_create_practice(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  phantom_rating: u64,
+  play_as: Option<CwChessColor>,
+  start_fen: Option<String>,
+) -> Result<Response, ContractError> {
+  if !(MIN_INITIAL_RATING..=MAX_INITIAL_RATING).contains(&phantom_rating) {
+    return Err(ContractError::InvalidRating {});
+  }
+  let start_fen = match start_fen {
+    Some(fen) => normalize_start_fen(&fen)?,
+    None => None,
+  };
+  let player = info.sender;
+  def_player_rating(deps.storage, &player)?;
+
+  let phantom_color = match play_as {
+    Some(CwChessColor::Black) => CwChessColor::White,
+    _ => CwChessColor::Black,
+  };
+  let game_id = next_game_id(deps.storage)?;
+  // player is on both sides, the engine moves for the phantom
+  let mut game = CwChessGame {
+    adjudication: None,
+    armageddon: false,
+    black_agrees_draw: false,
+    black_rejected_moves: 0,
+    block_limit: None,
+    block_start: env.block.height,
+    confirm_moves: false,
+    confirm_resign: false,
+    fen: start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string()),
+    game_id,
+    last_move_uci: None,
+    mate_pattern: None,
+    max_plies: None,
+    player1: player.clone(),
+    player2: player.clone(),
+    moves: vec![],
+    phantom: Some(CwChessPhantom {
+      color: phantom_color,
+      rating: phantom_rating,
+    }),
+    pending_move: None,
+    resign_pending_at: None,
+    start_at: None,
+    start_fen,
+    status: None,
+    white_agrees_draw: false,
+    white_rejected_moves: 0,
+  };
+  // the phantom opens as white
+  let phantom_move = game.play_phantom(env.block.height)?;
+  get_games_map().save(deps.storage, game_id, &game)?;
+
+  let mut response = Response::new();
+  if let Some(phantom_move) = &phantom_move {
+    response = response.add_event(phantom_move_event(game_id, phantom_move));
+  }
+  Ok(response
+    .add_attribute("action", "create_practice")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("player", player)
+    .add_attribute("phantom_rating", phantom_rating.to_string()))
+}
+
+// the engine's reply in a practice game
+fn phantom_move_event(game_id: u64, phantom_move: &CwChessAction) -> Event {
+  Event::new("phantom-move")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("move", phantom_move.notation())
+}
+
 fn execute_cancel_challenge(
   deps: DepsMut,
   info: MessageInfo,
@@ -180,45 +536,143 @@ fn execute_cancel_challenge(
     .add_attribute("challenge_id", challenge_id.to_string()))
 }
 
+/// load a draft challenge for its creator to edit
+fn load_draft(
+  store: &dyn Storage,
+  sender: &Addr,
+  challenge_id: u64,
+) -> Result<Challenge, ContractError> {
+  let challenge = get_challenges_map()
+    .may_load(store, challenge_id)?
+    .ok_or(ContractError::ChallengeNotFound {})?;
+  if &challenge.created_by != sender {
+    return Err(ContractError::NotYourChallenge {});
+  }
+  if !challenge.draft {
+    return Err(ContractError::PositionLocked {});
+  }
+  Ok(challenge)
+}
+
+/// place or remove a piece in a draft position
+fn execute_edit_draft(
+  deps: DepsMut,
+  info: MessageInfo,
+  challenge_id: u64,
+  square: String,
+  piece: Option<String>,
+) -> Result<Response, ContractError> {
+  let mut challenge = load_draft(deps.storage, &info.sender, challenge_id)?;
+  let piece = match piece.as_deref().map(|piece| piece.chars().collect::<Vec<_>>()) {
+    None => None,
+    Some(chars) if chars.len() == 1 => Some(chars[0]),
+    Some(_) => {
+      return Err(ContractError::InvalidFen {
+        reason: "unexpected piece".to_string(),
+      })
+    }
+  };
+  let fen = challenge.start_fen.as_deref().unwrap_or(EMPTY_FEN);
+  let fen = set_square(fen, &square, piece)?;
+  challenge.start_fen = Some(fen.clone());
+  get_challenges_map().save(deps.storage, challenge_id, &challenge)?;
+
+  Ok(Response::new()
+    .add_attribute("action", if piece.is_some() { "place_piece" } else { "remove_piece" })
+    .add_attribute("challenge_id", challenge_id.to_string())
+    .add_attribute("fen", fen))
+}
+
+/// check a draft position and open the challenge for play
+fn execute_lock_position(
+  deps: DepsMut,
+  info: MessageInfo,
+  challenge_id: u64,
+) -> Result<Response, ContractError> {
+  let mut challenge = load_draft(deps.storage, &info.sender, challenge_id)?;
+  let fen = challenge.start_fen.as_deref().unwrap_or(EMPTY_FEN);
+  challenge.start_fen = normalize_start_fen(fen)?;
+  challenge.draft = false;
+  get_challenges_map().save(deps.storage, challenge_id, &challenge)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "lock_position")
+    .add_attribute("challenge_id", challenge_id.to_string()))
+}
+
 /// save player rating
 fn def_player_rating(
   storage: &mut dyn Storage,
   addr: &Addr
 ) -> StdResult<()> {
   if let None = RATINGS.may_load(storage, addr.clone())? {
-    RATINGS.save(storage, addr.clone(), &EloRating::new().into())?;
+    save_rating(storage, addr, EloRating::new().into(), false)?;
   };
   Ok(())
 }
 
+// challenge settings besides the players, see Challenge
+#[derive(Default)]
+struct ChallengeOptions {
+  adjudication: bool,
+  armageddon: bool,
+  block_limit: Option<u64>,
+  confirm_moves: bool,
+  confirm_resign: bool,
+  draft: bool,
+  max_plies: Option<u64>,
+  start_at: Option<u64>,
+  start_fen: Option<String>,
+}
+
 fn execute_create_challenge(
   deps: DepsMut,
   env: Env,
   info: MessageInfo,
-  block_limit: Option<u64>,
   opponent: Option<String>,
   play_as: Option<CwChessColor>,
+  options: ChallengeOptions,
 ) -> Result<Response, ContractError> {
+  let ChallengeOptions {
+    adjudication,
+    armageddon,
+    block_limit,
+    confirm_moves,
+    confirm_resign,
+    draft,
+    max_plies,
+    start_at,
+    start_fen,
+  } = options;
   let block_created = env.block.height;
-  let challenge_id = next_challenge_id(deps.storage)?;
   let created_by = info.sender;
   let opponent = match opponent {
-    Some(addr) => {
-      let addr = deps.api.addr_validate(&addr)?;
-      if created_by == addr {
-        return Err(ContractError::CannotPlaySelf {});
-      }
-      Some(addr)
-    }
+    Some(addr) => Some(deps.api.addr_validate(&addr)?),
     None => None,
   };
+  validate_create_challenge(block_limit, opponent.as_ref(), &created_by)?;
+  let start_fen = match start_fen {
+    // checked when the draft is locked
+    Some(fen) if draft => Some(fen),
+    Some(fen) => normalize_start_fen(&fen)?,
+    None => None,
+  };
+  let challenge_id = next_challenge_id(deps.storage)?;
   let challenge = Challenge {
+    adjudication,
+    armageddon,
     block_created,
     block_limit,
     challenge_id,
+    confirm_moves,
+    confirm_resign,
     created_by: created_by.clone(),
+    draft,
+    max_plies,
     opponent: opponent.clone(),
     play_as,
+    start_at,
+    start_fen,
   };
   let challenges_map = get_challenges_map();
   challenges_map.save(deps.storage, challenge_id, &challenge)?;
@@ -238,6 +692,44 @@ fn execute_create_challenge(
     ))
 }
 
+/// agree to draw, the game ends once both players have agreed
+fn execute_agree_draw(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let games_map = get_games_map();
+  let height = env.block.height;
+  let min_draw_plies = STATE.load(deps.storage)?.min_draw_plies;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound { game_id }),
+      Some(mut game) => {
+        // discourage pre-arranged draws
+        if game.status.is_none() && (game.ply_count() as u64) < min_draw_plies {
+          return Err(ContractError::DrawTooEarly {
+            min_plies: min_draw_plies,
+          });
+        }
+        game.agree_draw(&info.sender, height)?;
+        Ok(game)
+      }
+    }
+  })?;
+
+  let mut response = Response::new();
+  if game.status.is_some() {
+    finish_game(deps.storage, &game, height)?;
+    response = response.add_submessages(notify_subscribers(deps.storage, &game)?);
+  }
+
+  Ok(response
+    .add_attribute("action", "agree_draw")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("player", info.sender))
+}
+
 fn execute_declare_timeout(
   deps: DepsMut,
   env: Env,
@@ -247,7 +739,7 @@ fn execute_declare_timeout(
   let height = env.block.height;
   let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
     match game {
-      None => Err(ContractError::GameNotFound {}),
+      None => Err(ContractError::GameNotFound { game_id }),
       Some(mut game) => match game.check_timeout(height)? {
         None => Err(ContractError::GameNotTimedOut {}),
         _ => Ok(game),
@@ -255,11 +747,374 @@ fn execute_declare_timeout(
     }
   })?;
 
+  finish_game(deps.storage, &game, height)?;
+  let notifications = notify_subscribers(deps.storage, &game)?;
+
   Ok(Response::new()
+    .add_submessages(notifications)
     .add_attribute("action", "declare_timeout")
     .add_attribute("game_id", game.game_id.to_string()))
 }
 
+/// arbiter ruling, only the owner can end a game this way
+fn execute_adjudicate(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+  winner: Option<CwChessColor>,
+) -> Result<Response, ContractError> {
+  let state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  let result = CwChessGameOver::Adjudicated { winner };
+  let games_map = get_games_map();
+  let height = env.block.height;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound { game_id }),
+      Some(game) if game.status.is_some() => Err(game.already_over()),
+      Some(mut game) => {
+        game.status = Some(result.clone());
+        Ok(game)
+      }
+    }
+  })?;
+
+  finish_game(deps.storage, &game, height)?;
+  let notifications = notify_subscribers(deps.storage, &game)?;
+
+  Ok(Response::new()
+    .add_submessages(notifications)
+    .add_attribute("action", "adjudicate")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("status", format!("{:?}", result)))
+}
+
+/// import a rating for a player who has not played yet
+fn execute_set_initial_rating(
+  deps: DepsMut,
+  info: MessageInfo,
+  player: String,
+  rating: u64,
+) -> Result<Response, ContractError> {
+  let state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  if !(MIN_INITIAL_RATING..=MAX_INITIAL_RATING).contains(&rating) {
+    return Err(ContractError::InvalidRating {});
+  }
+  let player = deps.api.addr_validate(&player)?;
+  // games are rated when they end, so any game rules the player out
+  let games_map = get_games_map();
+  let has_games = games_map
+    .idx
+    .player1
+    .prefix(player.clone())
+    .keys(deps.storage, None, None, Order::Ascending)
+    .next()
+    .is_some()
+    || games_map
+      .idx
+      .player2
+      .prefix(player.clone())
+      .keys(deps.storage, None, None, Order::Ascending)
+      .next()
+      .is_some();
+  if has_games {
+    return Err(ContractError::PlayerHasGames {});
+  }
+  // the rating is imported once, later changes come from games
+  if RATINGS.has(deps.storage, player.clone()) {
+    return Err(ContractError::RatingAlreadySet {});
+  }
+  update_player_rating(deps.storage, &player, rating, false)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_initial_rating")
+    .add_attribute("player", player)
+    .add_attribute("rating", rating.to_string()))
+}
+
+/// settings copied into games that opt in to adjudication, games already
+/// started keep theirs
+fn execute_set_adjudication(
+  deps: DepsMut,
+  info: MessageInfo,
+  depth: u8,
+  min_moves: u64,
+  threshold_centipawns: u32,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  if !(1..=MAX_ADJUDICATION_DEPTH).contains(&depth) {
+    return Err(ContractError::InvalidAdjudicationSettings {
+      reason: format!("depth must be 1 to {}", MAX_ADJUDICATION_DEPTH),
+    });
+  }
+  if !(1..=MAX_ADJUDICATION_CENTIPAWNS).contains(&threshold_centipawns) {
+    return Err(ContractError::InvalidAdjudicationSettings {
+      reason: format!("threshold must be 1 to {} centipawns", MAX_ADJUDICATION_CENTIPAWNS),
+    });
+  }
+  state.adjudication = CwChessAdjudication {
+    depth,
+    min_moves,
+    threshold_centipawns,
+  };
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_adjudication")
+    .add_attribute("depth", depth.to_string())
+    .add_attribute("threshold_centipawns", threshold_centipawns.to_string()))
+}
+
+/// K-factor schedule used when rating games
+fn execute_set_k_schedule(
+  deps: DepsMut,
+  info: MessageInfo,
+  schedule: Vec<(u64, u64)>,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  validate_k_schedule(&schedule)?;
+  let tiers = schedule.len();
+  state.k_schedule = schedule;
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_k_schedule")
+    .add_attribute("tiers", tiers.to_string()))
+}
+
+/// illegal moves a player can send in a row before forfeiting
+fn execute_set_max_rejected_moves(
+  deps: DepsMut,
+  info: MessageInfo,
+  max: u64,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  state.max_rejected_moves = max;
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_max_rejected_moves")
+    .add_attribute("max", max.to_string()))
+}
+
+/// plies that must be played before a draw can be offered or accepted
+fn execute_set_min_draw_plies(
+  deps: DepsMut,
+  info: MessageInfo,
+  plies: u64,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  state.min_draw_plies = plies;
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_min_draw_plies")
+    .add_attribute("plies", plies.to_string()))
+}
+
+/// messages one address can send per block
+fn execute_set_rate_limit(
+  deps: DepsMut,
+  info: MessageInfo,
+  per_block: u8,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  if per_block == 0 {
+    return Err(ContractError::InvalidRateLimit {});
+  }
+  state.rate_limit_per_block = per_block;
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_rate_limit")
+    .add_attribute("per_block", per_block.to_string()))
+}
+
+/// add the sender's note to a game they play in
+fn execute_set_game_note(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+  note: String,
+) -> Result<Response, ContractError> {
+  let game = get_games_map()
+    .may_load(deps.storage, game_id)?
+    .ok_or(ContractError::GameNotFound { game_id })?;
+  let player = info.sender;
+  if player != game.player1 && player != game.player2 {
+    return Err(ContractError::Unauthorized {});
+  }
+  if note.chars().count() > MAX_NOTE_CHARS {
+    return Err(ContractError::NoteTooLong {
+      max: MAX_NOTE_CHARS as u64,
+    });
+  }
+  let key = (game_id, player.clone());
+  if GAME_NOTES.has(deps.storage, key.clone()) || ARCHIVED_GAME_NOTES.has(deps.storage, key.clone()) {
+    return Err(ContractError::NoteAlreadySet {});
+  }
+  GAME_NOTES.save(deps.storage, key, &note)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_game_note")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("player", player))
+}
+
+/// move notes of a game over for NOTE_ARCHIVE_BLOCKS to archival storage
+fn execute_archive_game_notes(
+  deps: DepsMut,
+  env: Env,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let record = COMPLETED_GAMES
+    .may_load(deps.storage, game_id)?
+    .ok_or(ContractError::NotesNotExpired {})?;
+  if env.block.height < record.ended_at_block + NOTE_ARCHIVE_BLOCKS {
+    return Err(ContractError::NotesNotExpired {});
+  }
+  let notes = GAME_NOTES
+    .prefix(game_id)
+    .range(deps.storage, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+  for (player, note) in &notes {
+    GAME_NOTES.remove(deps.storage, (game_id, player.clone()));
+    ARCHIVED_GAME_NOTES.save(deps.storage, (game_id, player.clone()), note)?;
+  }
+
+  Ok(Response::new()
+    .add_attribute("action", "archive_game_notes")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("notes", notes.len().to_string()))
+}
+
+/// register a contract to be notified of a game's moves and result
+// only the players subscribe, every subscriber adds a submessage to each move
+fn execute_subscribe(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+  callback: String,
+) -> Result<Response, ContractError> {
+  let callback = deps.api.addr_validate(&callback)?;
+  let game = get_games_map()
+    .may_load(deps.storage, game_id)?
+    .ok_or(ContractError::GameNotFound { game_id })?;
+  if info.sender != game.player1 && info.sender != game.player2 {
+    return Err(ContractError::Unauthorized {});
+  }
+  if game.status.is_some() {
+    return Err(game.already_over());
+  }
+  SUBSCRIBERS.update(deps.storage, game_id, |subscribers| -> Result<_, ContractError> {
+    let mut subscribers = subscribers.unwrap_or_default();
+    if subscribers.contains(&callback) {
+      return Err(ContractError::AlreadySubscribed {});
+    }
+    if subscribers.len() >= MAX_SUBSCRIBERS {
+      return Err(ContractError::TooManySubscribers {
+        max: MAX_SUBSCRIBERS as u64,
+      });
+    }
+    subscribers.push(callback.clone());
+    Ok(subscribers)
+  })?;
+
+  Ok(Response::new()
+    .add_attribute("action", "subscribe")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("callback", callback))
+}
+
+fn execute_unsubscribe(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+  callback: String,
+) -> Result<Response, ContractError> {
+  let callback = deps.api.addr_validate(&callback)?;
+  let game = get_games_map()
+    .may_load(deps.storage, game_id)?
+    .ok_or(ContractError::GameNotFound { game_id })?;
+  if info.sender != game.player1 && info.sender != game.player2 {
+    return Err(ContractError::Unauthorized {});
+  }
+  let mut subscribers = SUBSCRIBERS.may_load(deps.storage, game_id)?.unwrap_or_default();
+  let count = subscribers.len();
+  subscribers.retain(|subscriber| *subscriber != callback);
+  if subscribers.len() == count {
+    return Err(ContractError::NotSubscribed {});
+  }
+  if subscribers.is_empty() {
+    SUBSCRIBERS.remove(deps.storage, game_id);
+  } else {
+    SUBSCRIBERS.save(deps.storage, game_id, &subscribers)?;
+  }
+
+  Ok(Response::new()
+    .add_attribute("action", "unsubscribe")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("callback", callback))
+}
+
+/// messages telling subscribers about the game's latest move or result
+/// subscriptions end with the game
+fn notify_subscribers(
+  store: &mut dyn Storage,
+  game: &CwChessGame,
+) -> StdResult<Vec<SubMsg>> {
+  let subscribers = SUBSCRIBERS.may_load(store, game.game_id)?.unwrap_or_default();
+  if subscribers.is_empty() {
+    return Ok(vec![]);
+  }
+  if game.status.is_some() {
+    SUBSCRIBERS.remove(store, game.game_id);
+  }
+  let msg = to_binary(&SubscriberMsg::GameUpdate {
+    fen: game.fen.clone(),
+    game_id: game.game_id,
+    last_move_uci: game.last_move_uci.clone(),
+    status: game.status.clone(),
+  })?;
+  Ok(
+    subscribers
+      .into_iter()
+      .map(|subscriber| {
+        SubMsg::reply_on_error(
+          WasmMsg::Execute {
+            contract_addr: subscriber.to_string(),
+            msg: msg.clone(),
+            funds: vec![],
+          },
+          ReplyId::SubscriberNotification.into(),
+        )
+        .with_gas_limit(SUBSCRIBER_GAS_LIMIT)
+      })
+      .collect(),
+  )
+}
+
 /// get the player's rating
 fn get_player_rating(
   store:&mut dyn Storage,
@@ -276,35 +1131,116 @@ fn get_player_rating(
 fn update_player_rating(
   store: &mut dyn Storage,
   addr: &Addr,
-  rating: u64
+  rating: u64,
+  played_game: bool,
 ) -> StdResult<()> {
-  RATINGS.update(store, addr.clone(), |_| -> StdResult<u64> {
-    Ok(rating)
-  })?;
-  Ok(())
+  save_rating(store, addr, rating, played_game)
 }
 
-// update the players rating
-fn update_players_rating(
+// get the (white, black) ratings
+fn get_players_rating(
   store: &mut dyn Storage,
   game: &CwChessGame,
-  outcome: Outcomes,
-) -> StdResult<()> {
-  let player1 = &game.player1;
-  let player2 = &game.player2;
+) -> StdResult<(u64, u64)> {
+  Ok((
+    get_player_rating(store, &game.player1)?,
+    get_player_rating(store, &game.player2)?,
+  ))
+}
 
-  let (rate1, rate2) = elo(
-    &get_player_rating(store, player1)?.into(),
-    &get_player_rating(store, player2)?.into(),
-    &outcome,
-    &EloConfig::new(),
+// rate a finished game and keep its permanent record
+fn finish_game(
+  store: &mut dyn Storage,
+  game: &CwChessGame,
+  ended_at_block: u64,
+) -> Result<(), ContractError> {
+  let status = match &game.status {
+    Some(status) => status,
+    None => return Err(ContractError::Std(StdError::generic_err("game is not over"))),
+  };
+  let outcome = match status {
+    CwChessGameOver::WhiteCheckmates |
+    CwChessGameOver::WhiteWinsByAdjudication |
+    CwChessGameOver::Adjudicated { winner: Some(CwChessColor::White) } |
+    CwChessGameOver::BlackResigns |
+    CwChessGameOver::BlackForfeits |
+    CwChessGameOver::BlackTimeout => Outcomes::WIN,
+
+    CwChessGameOver::BlackCheckmates |
+    CwChessGameOver::BlackWinsByAdjudication |
+    CwChessGameOver::Adjudicated { winner: Some(CwChessColor::Black) } |
+    CwChessGameOver::WhiteResigns |
+    CwChessGameOver::WhiteForfeits |
+    CwChessGameOver::WhiteTimeout => Outcomes::LOSS,
+
+    CwChessGameOver::Adjudicated { winner: None } |
+    CwChessGameOver::DrawAccepted |
+    CwChessGameOver::DrawAgreed |
+    CwChessGameOver::DrawDeclared |
+    CwChessGameOver::PlyLimit |
+    CwChessGameOver::Stalemate => Outcomes::DRAW,
+  };
+  // white had to win
+  let outcome = match outcome {
+    Outcomes::DRAW if game.armageddon => Outcomes::LOSS,
+    outcome => outcome,
+  };
+  if let Some(phantom) = &game.phantom {
+    return finish_practice_game(store, game, phantom, &outcome, ended_at_block);
+  }
+  let ratings_before = get_players_rating(store, game)?;
+  let elo_config = EloConfig::scheduled(
+    &STATE.load(store)?.k_schedule,
+    &EloRating::from(ratings_before.0),
+    &EloRating::from(ratings_before.1),
   );
-  update_player_rating(store, player1, rate1.into())?;
-  update_player_rating(store, player2, rate2.into())?;
+  let (white, black) = atomic_elo_update(
+    store,
+    &game.player1,
+    &game.player2,
+    &outcome,
+    &elo_config,
+  )?;
+  if let Some(eco_code) = game.opening_eco_code() {
+    record_opening_result(store, &eco_code, &outcome)?;
+  }
+  record_completed_game(store, game, ratings_before, (white.into(), black.into()), ended_at_block)?;
 
   Ok(())
 }
 
+// rate only the player against the phantom's fixed rating
+// practice games are kept out of the opening stats
+fn finish_practice_game(
+  store: &mut dyn Storage,
+  game: &CwChessGame,
+  phantom: &CwChessPhantom,
+  outcome: &Outcomes,
+  ended_at_block: u64,
+) -> Result<(), ContractError> {
+  let player = &game.player1;
+  let rating_before = get_player_rating(store, player)?;
+  // outcome is from white's perspective
+  let outcome = match (&phantom.color, outcome) {
+    (CwChessColor::White, Outcomes::WIN) => Outcomes::LOSS,
+    (CwChessColor::White, Outcomes::LOSS) => Outcomes::WIN,
+    (_, outcome) => *outcome,
+  };
+  let player_rating = EloRating::from(rating_before);
+  let phantom_rating = EloRating::from(phantom.rating);
+  let elo_config = EloConfig::scheduled(&STATE.load(store)?.k_schedule, &player_rating, &phantom_rating);
+  let (rating_after, _) = elo(&player_rating, &phantom_rating, &outcome, &elo_config);
+  let rating_after: u64 = rating_after.into();
+  update_player_rating(store, player, rating_after, true)?;
+
+  let (ratings_before, ratings_after) = match phantom.color {
+    CwChessColor::Black => ((rating_before, phantom.rating), (rating_after, phantom.rating)),
+    CwChessColor::White => ((phantom.rating, rating_before), (phantom.rating, rating_after)),
+  };
+  record_completed_game(store, game, ratings_before, ratings_after, ended_at_block)?;
+  Ok(())
+}
+
 fn execute_turn(
   deps: DepsMut,
   env: Env,
@@ -312,38 +1248,197 @@ fn execute_turn(
   action: CwChessAction,
   game_id: u64,
 ) -> Result<Response, ContractError> {
+  play_turn(deps, env, info, action, game_id, false)
+}
+
+/// play the sender's pending move in a confirm_moves game
+fn execute_confirm_move(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let game = get_games_map()
+    .may_load(deps.storage, game_id)?
+    .ok_or(ContractError::GameNotFound { game_id })?;
+  let action = match game.pending_move {
+    Some((player, action, expires_at)) if player == info.sender => {
+      if env.block.height > expires_at {
+        return Err(ContractError::MoveConfirmationExpired {
+          expired_at: expires_at,
+        });
+      }
+      action
+    }
+    _ => return Err(ContractError::NoPendingMove {}),
+  };
+  play_turn(deps, env, info, action, game_id, true)
+}
+
+/// drop the sender's pending move, expired or not
+fn execute_cancel_move(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let games_map = get_games_map();
+  games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    let mut game = game.ok_or(ContractError::GameNotFound { game_id })?;
+    match &game.pending_move {
+      Some((player, _, _)) if *player == info.sender && game.status.is_none() => {
+        game.pending_move = None;
+        Ok(game)
+      }
+      _ => Err(ContractError::NoPendingMove {}),
+    }
+  })?;
+
+  Ok(Response::new()
+    .add_attribute("action", "cancel_move")
+    .add_attribute("game_id", game_id.to_string()))
+}
+
+// a move from Turn is only proposed in a confirm_moves game, ConfirmMove
+// plays it with confirmed set
+fn play_turn(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  action: CwChessAction,
+  game_id: u64,
+  confirmed: bool,
+) -> Result<Response, ContractError> {
+  // turn away malformed moves before loading anything
+  if let CwChessAction::MakeMove(notation) | CwChessAction::OfferDraw(notation) = &action {
+    if !is_valid_san(notation) && !is_valid_uci(notation) {
+      return Err(ContractError::InvalidMoveNotation {
+        notation: notation.clone(),
+      });
+    }
+  }
   let games_map = get_games_map();
   let height = env.block.height;
   let player = info.sender;
+  let state = STATE.load(deps.storage)?;
+  let min_draw_plies = state.min_draw_plies;
+  let mut move_events = vec![];
+  let mut stalemate_risk = false;
+  let mut rejected = None;
+  let mut proposed = false;
+  let mut phantom_move = None;
   let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
     match game {
-      None => Err(ContractError::GameNotFound {}),
+      None => Err(ContractError::GameNotFound { game_id }),
       Some(mut game) => {
-        game.make_move(&player, (height, action.clone()))?;
+        game.check_practice_action(&player, &action)?;
+        // discourage pre-arranged draws
+        if game.status.is_none()
+          && matches!(action, CwChessAction::AcceptDraw | CwChessAction::OfferDraw(_))
+          && (game.ply_count() as u64) < min_draw_plies
+        {
+          return Err(ContractError::DrawTooEarly {
+            min_plies: min_draw_plies,
+          });
+        }
+        let preview = match game.status {
+          None => game.preview_move(&action).unwrap_or_default(),
+          _ => CwChessMovePreview::default(),
+        };
+        proposed = game.confirm_moves
+          && !confirmed
+          && matches!(action, CwChessAction::MakeMove(_) | CwChessAction::OfferDraw(_));
+        let played = if proposed {
+          game.propose_move(&player, (height, action.clone()))
+        } else {
+          game.make_move(&player, (height, action.clone()))
+        };
+        match played {
+          // counted instead of failing, so the count is kept
+          Err(ContractError::IllegalMove { reason, .. }) if state.max_rejected_moves > 0 => {
+            rejected = Some(reason);
+            game.reject_move(state.max_rejected_moves);
+            return Ok(game);
+          }
+          result => result?,
+        };
+        // a timeout ends the game without playing the move
+        if !proposed && !matches!(
+          game.status,
+          Some(CwChessGameOver::WhiteTimeout) | Some(CwChessGameOver::BlackTimeout)
+        ) {
+          move_events = preview.events;
+        } else if proposed {
+          stalemate_risk = preview.stalemate_risk;
+        }
+        if !proposed {
+          phantom_move = game.play_phantom(height)?;
+        }
         Ok(game)
       }
     }
   })?;
 
-  if let Some(status) = &game.status {
-    let outcome = match status {
-      CwChessGameOver::WhiteCheckmates |
-      CwChessGameOver::BlackResigns |
-      CwChessGameOver::BlackTimeout => Outcomes::WIN,
+  if game.status.is_some() {
+    finish_game(deps.storage, &game, height)?;
+  }
 
-      CwChessGameOver::BlackCheckmates |
-      CwChessGameOver::WhiteResigns |
-      CwChessGameOver::WhiteTimeout => Outcomes::LOSS,
+  let mut response = Response::new();
+  // waiting for the player to confirm, see CwChessGame::confirm_resign
+  let resign_pending = action == CwChessAction::Resign && game.status.is_none();
+  // waiting for ConfirmMove, see CwChessGame::confirm_moves
+  let move_pending = proposed && rejected.is_none() && game.status.is_none();
+  // a rejected move only changes the board by forfeiting
+  if (rejected.is_none() && !resign_pending && !move_pending) || game.status.is_some() {
+    response = response.add_submessages(notify_subscribers(deps.storage, &game)?);
+  }
+  if let Some(phantom_move) = &phantom_move {
+    response = response.add_event(phantom_move_event(game.game_id, phantom_move));
+  }
+  if move_pending {
+    response = response.add_event(
+      Event::new("move-pending")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("move", action.notation())
+        .add_attribute("confirm_by", (height + MOVE_CONFIRM_BLOCKS).to_string()),
+    );
+  }
+  if resign_pending {
+    response = response.add_event(
+      Event::new("resign-pending")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("confirm_by", (height + RESIGN_CONFIRM_BLOCKS).to_string()),
+    );
+  }
+  if let Some(reason) = rejected {
+    response = response.add_event(
+      Event::new("move-rejected")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("move", action.notation())
+        .add_attribute("reason", reason.to_string()),
+    );
+  }
+  if let Some(CwChessGameOver::WhiteWinsByAdjudication)
+    | Some(CwChessGameOver::BlackWinsByAdjudication) = &game.status
+  {
+    let evaluation = CwChessEvaluation::from_fen(&game.fen)?;
+    response = response.add_event(
+      Event::new("game-adjudicated")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("evaluation", evaluation.total_centipawns.to_string()),
+    );
+  }
 
-      CwChessGameOver::DrawAccepted |
-      CwChessGameOver::DrawDeclared |
-      CwChessGameOver::Stalemate => Outcomes::DRAW,
-    };
-    update_players_rating(deps.storage, &game, outcome)?;
+  // informational only, warned while the pending move can still be cancelled
+  if move_pending && stalemate_risk {
+    response = response.add_event(
+      Event::new("stalemate-risk")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("move", action.notation()),
+    );
   }
 
-  Ok(Response::new()
-    .add_attribute("action", "turn")
+  Ok(response
+    .add_attribute("action", if confirmed { "confirm_move" } else { "turn" })
     .add_attribute("game_id", game.game_id.to_string())
     .add_attribute(
       "status",
@@ -351,7 +1446,39 @@ fn execute_turn(
         .as_ref()
         .map(|s| format!("{:?}", s))
         .unwrap_or_else(|| format!("{:?}", game.turn_color())),
-    ))
+    )
+    .add_attribute("move_events", move_events.join(",")))
+}
+
+/// save the permanent record of a finished game
+fn record_completed_game(
+  store: &mut dyn Storage,
+  game: &CwChessGame,
+  ratings_before: (u64, u64),
+  ratings_after: (u64, u64),
+  ended_at_block: u64,
+) -> StdResult<()> {
+  let result = game
+    .status
+    .clone()
+    .ok_or_else(|| StdError::generic_err("game is not over"))?;
+
+  save_game_record(store, &GameRecord {
+    armageddon: game.armageddon,
+    black: game.player2.clone(),
+    black_elo_after: ratings_after.1,
+    black_elo_before: ratings_before.1,
+    created_at_block: game.block_start,
+    ended_at_block,
+    game_duration_blocks: ended_at_block.saturating_sub(game.block_start),
+    game_id: game.game_id,
+    move_count: game.ply_count() as u16,
+    pgn: game.pgn(),
+    result,
+    white: game.player1.clone(),
+    white_elo_after: ratings_after.0,
+    white_elo_before: ratings_before.0,
+  })
 }
 
 fn query_get_challenge(deps: Deps, challenge_id: u64) -> StdResult<Challenge> {
@@ -448,7 +1575,9 @@ fn query_get_games(
         .player2
         .prefix(addr)
         .range(deps.storage, after, None, Order::Ascending)
-        .map(|result| -> CwChessGame { result.unwrap().1 });
+        .map(|result| -> CwChessGame { result.unwrap().1 })
+        // practice games are already listed as player1
+        .filter(|g| -> bool { g.player1 != g.player2 });
 
       merge_iters(player1, player2, |g1, g2| -> bool {
         g1.game_id <= g2.game_id
@@ -463,6 +1592,107 @@ fn query_get_games(
   Ok(games)
 }
 
+fn query_active_game_between(
+  deps: Deps,
+  player_a: String,
+  player_b: String,
+) -> StdResult<Option<u64>> {
+  let player_a = deps.api.addr_validate(&player_a)?;
+  let player_b = deps.api.addr_validate(&player_b)?;
+  // newest first if the pair has several games in progress
+  get_games_map()
+    .idx
+    .pairing_status
+    .prefix((pairing_key(&player_a, &player_b), game_status_key(false)))
+    .keys(deps.storage, None, None, Order::Descending)
+    .next()
+    .transpose()
+}
+
+fn query_games_by_status(
+  deps: Deps,
+  status: GameStatus,
+  start_after: Option<u64>,
+  limit: Option<u32>,
+) -> StdResult<Vec<GameSummary>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start_after = start_after.map(Bound::exclusive);
+
+  get_games_map()
+    .idx
+    .status
+    .prefix(game_status_key(status == GameStatus::Finished))
+    .range(deps.storage, start_after, None, Order::Ascending)
+    .take(limit)
+    .map(|result| result.map(|(_, game)| GameSummary::from(&game)))
+    .collect()
+}
+
+fn query_games_by_player(
+  deps: Deps,
+  player: String,
+  status: Option<GameStatus>,
+  start_after: Option<u64>,
+  limit: Option<u32>,
+) -> StdResult<Vec<GameSummary>> {
+  let player = deps.api.addr_validate(&player)?;
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start_after = start_after.map(Bound::exclusive);
+  let games_map = get_games_map();
+
+  let white = games_map
+    .idx
+    .player1
+    .prefix(player.clone())
+    .range(deps.storage, start_after.clone(), None, Order::Ascending);
+  let black = games_map
+    .idx
+    .player2
+    .prefix(player)
+    .range(deps.storage, start_after, None, Order::Ascending);
+  // practice games are already listed as player1
+  let black = black.filter(|result| !matches!(result, Ok((_, g)) if g.player1 == g.player2));
+
+  merge_iters(white, black, |a, b| -> bool {
+    match (a, b) {
+      (Ok((a, _)), Ok((b, _))) => a <= b,
+      // errors first so they are returned
+      (Err(_), _) => true,
+      (_, Err(_)) => false,
+    }
+  })
+  // filtered rather than indexed, each index is rewritten on every move
+  .filter(|result| match (result, &status) {
+    (Ok((_, game)), Some(status)) => game.status.is_some() == (*status == GameStatus::Finished),
+    _ => true,
+  })
+  .take(limit)
+  .map(|result| result.map(|(_, game)| GameSummary::from(&game)))
+  .collect()
+}
+
+fn query_games_from_position(
+  deps: Deps,
+  fen: &str,
+  start_after: Option<u64>,
+  limit: Option<u32>,
+) -> StdResult<Vec<GameSummary>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start_after = start_after.map(Bound::exclusive);
+  let start_fen = normalize_start_fen(fen).map_err(|e| StdError::generic_err(e.to_string()))?;
+
+  get_games_map()
+    .idx
+    .start_position
+    .prefix(start_position_hash(start_fen.as_deref()))
+    .range(deps.storage, start_after, None, Order::Ascending)
+    // hashes can collide, compare the positions too
+    .filter(|result| !matches!(result, Ok((_, game)) if game.start_fen != start_fen))
+    .take(limit)
+    .map(|result| result.map(|(_, game)| GameSummary::from(&game)))
+    .collect()
+}
+
 fn query_valid_move(
   deps: Deps,
   game_id: u64,
@@ -483,6 +1713,205 @@ fn query_valid_move(
   }
 }
 
+fn query_legal_moves(
+  deps: Deps,
+  game_id: u64,
+  perspective: Option<CwChessColor>,
+  square: Option<String>,
+) -> StdResult<Vec<String>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  game
+    .legal_moves(perspective, square)
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_legal_moves_san(
+  deps: Deps,
+  game_id: u64,
+) -> StdResult<Vec<CwChessLegalMove>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  game
+    .legal_moves_san()
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_replay(deps: Deps, game_id: u64) -> StdResult<GameReplay> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  game
+    .replay()
+    .map(GameReplay::from)
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_uci_moves(deps: Deps, game_id: u64) -> StdResult<Vec<String>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  game.uci_moves().map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_piece_moves(deps: Deps, game_id: u64, square: &str) -> StdResult<CwChessPieceMoves> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  game
+    .piece_moves(square)
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_explain_move(
+  deps: Deps,
+  game_id: u64,
+  player: &str,
+  from: &str,
+  to: &str,
+) -> StdResult<Option<CwChessIllegalMove>> {
+  // load the game
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  // validate the player
+  let addr = deps.api.addr_validate(player)?;
+
+  game
+    .explain_move(&addr, from, to)
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_game_phase(deps: Deps, game_id: u64) -> StdResult<CwChessGamePhase> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  game.game_phase().map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_material_imbalance(deps: Deps, game_id: u64) -> StdResult<CwChessMaterialImbalance> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  game.material_imbalance().map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_should_resign(deps: Deps, game_id: u64, for_color: CwChessColor) -> StdResult<bool> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  game
+    .should_resign(&for_color)
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_top_moves(deps: Deps, game_id: u64, n: u8, depth: u8) -> StdResult<Vec<MoveEvaluation>> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  let top_moves = game
+    .top_moves(depth, n)
+    .map_err(|e| StdError::generic_err(e.to_string()))?;
+  Ok(top_moves.into_iter().map(MoveEvaluation::from).collect())
+}
+
+fn query_position_evaluation(fen: &str) -> StdResult<CwChessEvaluation> {
+  CwChessEvaluation::from_fen(fen).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_analyze_fen(fen: &str) -> StdResult<CwChessFenAnalysis> {
+  CwChessFenAnalysis::from_fen(fen).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_forced_mate(fen: &str, moves: u8) -> StdResult<bool> {
+  forced_mate(fen, moves).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_completed_game(deps: Deps, game_id: u64) -> StdResult<GameRecord> {
+  COMPLETED_GAMES.load(deps.storage, game_id)
+}
+
+fn query_archived_pgn(deps: Deps, env: Env, game_id: u64) -> StdResult<String> {
+  let record = COMPLETED_GAMES.load(deps.storage, game_id)?;
+  record
+    .archived_pgn(env.contract.address.as_str())
+    .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn query_game_notes(deps: Deps, game_id: u64) -> StdResult<Vec<(String, String)>> {
+  let mut notes = vec![];
+  for map in [GAME_NOTES, ARCHIVED_GAME_NOTES] {
+    for note in map.prefix(game_id).range(deps.storage, None, None, Order::Ascending) {
+      let (player, note) = note?;
+      notes.push((player.to_string(), note));
+    }
+  }
+  notes.sort();
+  Ok(notes)
+}
+
+fn query_recent_completed_games(deps: Deps, limit: u32) -> StdResult<Vec<GameRecord>> {
+  RECENT_GAMES
+    .may_load(deps.storage)?
+    .unwrap_or_default()
+    .iter()
+    .take(limit as usize)
+    .map(|game_id| COMPLETED_GAMES.load(deps.storage, *game_id))
+    .collect()
+}
+
+fn query_opening_stats(deps: Deps, eco_code: &str) -> StdResult<OpeningStats> {
+  Ok(OPENING_STATS.may_load(deps.storage, eco_code)?.unwrap_or_default())
+}
+
+fn query_best_openings_for_color(
+  deps: Deps,
+  color: CwChessColor,
+  min_games: u64,
+) -> StdResult<Vec<(String, Decimal)>> {
+  let mut openings = vec![];
+  for item in OPENING_STATS.range(deps.storage, None, None, Order::Ascending) {
+    let (eco_code, stats) = item?;
+    if stats.total_games >= min_games {
+      openings.push((eco_code, stats.win_rate(&color)));
+    }
+  }
+  // stable sort keeps eco codes ascending for equal win rates
+  openings.sort_by_key(|(_, win_rate)| Reverse(*win_rate));
+  Ok(openings)
+}
+
+fn query_leaderboard(
+  deps: Deps,
+  start_after: Option<String>,
+  limit: Option<u32>,
+) -> StdResult<Vec<LeaderboardEntry>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let standings = get_standings_map();
+  // continue after the player's current place
+  let start_after = match start_after {
+    Some(player) => {
+      let player = deps.api.addr_validate(&player)?;
+      // without a standing the player is placed by rating alone
+      let standing = match standings.may_load(deps.storage, player.clone())? {
+        Some(standing) => standing,
+        None => Standing {
+          games_played: 0,
+          rating: RATINGS.may_load(deps.storage, player.clone())?.unwrap_or_default(),
+        },
+      };
+      Some(Bound::exclusive((rank_key(&standing), player)))
+    }
+    None => None,
+  };
+
+  standings
+    .idx
+    .rank
+    .range(deps.storage, start_after, None, Order::Ascending)
+    .take(limit)
+    .map(|result| result.map(LeaderboardEntry::from))
+    .collect()
+}
+
+fn query_supported_variants() -> Vec<VariantSummary> {
+  VARIANTS.iter().map(VariantSummary::from).collect()
+}
+
 fn query_get_ratings(
   deps: Deps
 ) -> StdResult<Vec<RatingSummary>> {
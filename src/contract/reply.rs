@@ -0,0 +1,46 @@
+//! Ids of the submessages the contract asks to be replied to.
+//!
+//! Every submessage sent with a reply uses one of these, so ids can't
+//! collide as features are added and the `reply` entry point can turn away
+//! ids it never sent.
+
+/// A submessage reply id. Ids start at 1 with no gaps, add new ones at the
+/// end of `ReplyId::ALL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyId {
+  /// A subscriber contract failed to handle a game update.
+  SubscriberNotification = 1,
+}
+
+impl ReplyId {
+  /// Every reply id, in id order.
+  pub const ALL: [ReplyId; 1] = [ReplyId::SubscriberNotification];
+
+  /// The reply id for `id`, if the contract uses it.
+  pub fn from_u64(id: u64) -> Option<ReplyId> {
+    ReplyId::ALL.iter().copied().find(|reply_id| u64::from(*reply_id) == id)
+  }
+}
+
+impl From<ReplyId> for u64 {
+  fn from(reply_id: ReplyId) -> u64 {
+    reply_id as u64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reply_id_roundtrip() {
+    for (i, reply_id) in ReplyId::ALL.iter().enumerate() {
+      let id = u64::from(*reply_id);
+      // contiguous from 1
+      assert_eq!(id, i as u64 + 1);
+      assert_eq!(ReplyId::from_u64(id), Some(*reply_id));
+    }
+    assert_eq!(ReplyId::from_u64(0), None);
+    assert_eq!(ReplyId::from_u64(ReplyId::ALL.len() as u64 + 1), None);
+  }
+}
@@ -1,14 +1,34 @@
 #[cfg(test)]
 mod tests {
-  use crate::contract::{execute, instantiate, query};
-  use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+  use crate::contract::reply::ReplyId;
+  use crate::contract::{execute, instantiate, migrate, query};
+  use crate::cwchess::{
+    CwChessAction, CwChessAdjudication, CwChessColor, CwChessEvaluation, CwChessFenAnalysis, CwChessGame,
+    CwChessGameOver, CwChessIllegalMove, CwChessLegalMove, CwChessMatePattern, CwChessMaterialImbalance,
+    CwChessPieceCounts, CwChessPieceDifference, CwChessPieceMoves, DEFAULT_FEN, MAX_ADJUDICATION_CENTIPAWNS,
+    MAX_ADJUDICATION_DEPTH, MOVE_CONFIRM_BLOCKS, RESIGN_CONFIRM_BLOCKS, VARIANTS,
+  };
+  use crate::elo::EloRating;
   use crate::error::ContractError;
-  use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg};
+  use crate::game::unpack_move;
+  use crate::msg::{
+    ExecuteMsg, GameReplay, GameStatus, GameSummary, InstantiateMsg, MigrateMsg, QueryMsg, RatingSummary,
+    SubscriberMsg, LeaderboardEntry, MoveEvaluation, VariantSummary,
+  };
+  use crate::state::{
+    get_games_map, save_rating, GameRecord, OpeningStats, GAME_NOTES, MAX_NOTE_CHARS, MAX_SUBSCRIBERS, NOTE_ARCHIVE_BLOCKS,
+    RATINGS,
+  };
+  use crate::util::{format_san_move, parse_fen};
 
   use cosmwasm_std::testing::{
     mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
   };
-  use cosmwasm_std::{coins, from_binary, Env};
+  use cosmwasm_std::{
+    coins, from_binary, Addr, CosmosMsg, Decimal, Deps, DepsMut, Env, Order, ReplyOn, Response, WasmMsg,
+  };
+  use cw_storage_plus::Map;
+  use std::cell::Cell;
 
   #[test]
   fn test_initialize() {
@@ -22,6 +42,31 @@ mod tests {
     assert_eq!(0, res.messages.len());
   }
 
+  #[test]
+  fn test_supported_variants() {
+    let deps = mock_dependencies();
+
+    let variants = from_binary::<Vec<VariantSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::SupportedVariants {}).unwrap(),
+    )
+    .unwrap();
+    assert!(variants.contains(&VariantSummary {
+      id: "standard".to_string(),
+      name: "Standard".to_string(),
+      start_fen: DEFAULT_FEN.to_string(),
+    }));
+    assert!(variants.contains(&VariantSummary {
+      id: "armageddon".to_string(),
+      name: "Armageddon".to_string(),
+      start_fen: DEFAULT_FEN.to_string(),
+    }));
+    // every registered variant is listed
+    assert_eq!(variants.len(), VARIANTS.len());
+    for registered in VARIANTS {
+      assert!(variants.iter().any(|variant| variant.id == registered.id));
+    }
+  }
+
   #[test]
   fn test_accept_challenge_open() {
     let mut deps = mock_dependencies();
@@ -40,9 +85,16 @@ mod tests {
       mock_env(),
       mock_info("creator", &[]),
       ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
         block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: None,
         play_as: None,
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
@@ -96,9 +148,16 @@ mod tests {
       mock_env(),
       mock_info("creator", &[]),
       ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
         block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: Some("opponent".to_string()),
         play_as: None,
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
@@ -148,9 +207,16 @@ mod tests {
 
     // create a challenge with an opponent
     let msg = ExecuteMsg::CreateChallenge {
+      adjudication: false,
+      armageddon: false,
       block_limit: None,
+      confirm_moves: false,
+      confirm_resign: false,
+      max_plies: None,
       opponent: Some("opponent".to_string()),
       play_as: None,
+      start_at: None,
+      start_fen: None,
     };
     let mut env = mock_env();
     env.block.height = 456;
@@ -163,6 +229,52 @@ mod tests {
     let attr = attrs[1].clone();
     assert_eq!(&attr.key, "challenge_id");
     assert_eq!(&attr.value, "1");
+
+    // cannot challenge self
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: Some("creator".to_string()),
+        play_as: None,
+        start_at: None,
+        start_fen: None,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::CannotPlaySelf { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // block limit must allow at least a minute per player
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: Some(5),
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: None,
+        start_at: None,
+        start_fen: None,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::BlockLimitTooShort { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
   }
 
   #[test]
@@ -183,10 +295,17 @@ mod tests {
       mock_env(),
       mock_info("black", &[]),
       ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
         block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: None,
         // creator is black
         play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
@@ -210,7 +329,7 @@ mod tests {
       },
     );
     match response.unwrap_err() {
-      ContractError::InvalidMove { .. } => {}
+      ContractError::IllegalMove { .. } => {}
       e => panic!("unexpected error: {:?}", e),
     }
 
@@ -243,10 +362,12 @@ mod tests {
     )
     .unwrap();
     assert_eq!(game.status, Some(CwChessGameOver::DrawAccepted {}));
+    // accepting the draw is not a board move
+    assert_eq!(game.last_move_uci, Some("d2d4".to_string()));
   }
 
   #[test]
-  fn test_get_games() {
+  fn test_agree_draw() {
     let mut deps = mock_dependencies();
 
     // initialize
@@ -257,107 +378,118 @@ mod tests {
       InstantiateMsg {},
     )
     .unwrap();
-
-    // create first game (with two as white, one as black)
+    // create game
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("one", &[]),
+      mock_info("black", &[]),
       ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
         block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: None,
+        // creator is black
         play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("two", &[]),
+      mock_info("white", &[]),
       ExecuteMsg::AcceptChallenge { challenge_id: 1 },
     )
     .unwrap();
 
-    // get_games should return the game
-    let games = from_binary::<Vec<GameSummary>>(
-      &query(
-        deps.as_ref(),
-        mock_env(),
-        QueryMsg::GetGames {
-          after: None,
-          game_over: None,
-          player: None,
-        },
-      )
-      .unwrap(),
-    )
-    .unwrap();
-    assert_eq!(games.len(), 1);
-    assert_eq!(games[0].turn_color, Some(CwChessColor::White));
+    // only players can agree
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("spectator", &[]),
+      ExecuteMsg::AgreeDraw { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
 
-    // create second game (with one as white, two as black)
+    // black agrees out of turn, then white moves
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("two", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
-        // creator is black
-        play_as: Some(CwChessColor::Black),
-      },
+      mock_info("black", &[]),
+      ExecuteMsg::AgreeDraw { game_id: 1 },
     )
     .unwrap();
-    // opponent can accept
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("one", &[]),
-      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("e4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
     )
     .unwrap();
+    assert!(game.black_agrees_draw);
+    assert!(!game.white_agrees_draw);
 
-    // white makes move in first game
+    // black moving withdraws their agreement
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("two", &[]),
+      mock_info("black", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d4".to_string()),
+        action: CwChessAction::MakeMove("e5".to_string()),
         game_id: 1,
       },
     )
     .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AgreeDraw { game_id: 1 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, None);
+    assert!(!game.black_agrees_draw);
 
-    // get games
-    // internally player1 index is scanned before player2 index
-    // this should still return games in order by game_id
-    let games = from_binary::<Vec<GameSummary>>(
-      &query(
-        deps.as_ref(),
-        mock_env(),
-        QueryMsg::GetGames {
-          after: None,
-          game_over: None,
-          player: Some("one".to_string()),
-        },
-      )
-      .unwrap(),
+    // both agree
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AgreeDraw { game_id: 1 },
     )
     .unwrap();
-    assert_eq!(games.len(), 2);
-    assert_eq!(games[0].game_id, 1);
-    assert_eq!(games[0].turn_color, Some(CwChessColor::Black));
-    assert_eq!(games[0].player1, "two");
-    assert_eq!(games[0].player2, "one");
-    assert_eq!(games[1].game_id, 2);
-    assert_eq!(games[1].turn_color, Some(CwChessColor::White));
-    assert_eq!(games[1].player1, "one");
-    assert_eq!(games[1].player2, "two");
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::DrawAgreed));
+    let record = from_binary::<GameRecord>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::CompletedGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(record.result, CwChessGameOver::DrawAgreed);
   }
 
   #[test]
-  fn test_make_move() {
+  fn test_min_draw_plies() {
     let mut deps = mock_dependencies();
 
     // initialize
@@ -368,20 +500,46 @@ mod tests {
       InstantiateMsg {},
     )
     .unwrap();
-    // create challenge
+
+    // only the owner can set the minimum
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetMinDrawPlies { plies: 4 },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetMinDrawPlies { plies: 4 },
+    )
+    .unwrap();
+
+    // create game
     execute(
       deps.as_mut(),
       mock_env(),
       mock_info("black", &[]),
       ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
         block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: None,
         // creator is black
         play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
-    // opponent can accept
     execute(
       deps.as_mut(),
       mock_env(),
@@ -390,92 +548,143 @@ mod tests {
     )
     .unwrap();
 
-    // first move by white
-    let mut env = mock_env();
-    env.block.height = 123;
-    execute(
-      deps.as_mut(),
-      env,
-      mock_info("white", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d4".to_string()),
-        game_id: 1,
-      },
-    )
-    .unwrap();
-
-    // white cannot go when blacks turn
+    // offer before the minimum
     let response = execute(
       deps.as_mut(),
       mock_env(),
       mock_info("white", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("c4".to_string()),
+        action: CwChessAction::OfferDraw("d4".to_string()),
         game_id: 1,
       },
     );
     match response.unwrap_err() {
-      ContractError::NotYourTurn { .. } => {}
+      ContractError::DrawTooEarly { min_plies: 4 } => {}
       e => panic!("unexpected error: {:?}", e),
     }
 
-    // first move by black
-    let mut env = mock_env();
-    env.block.height = 456;
-    execute(
-      deps.as_mut(),
-      env,
-      mock_info("black", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d5".to_string()),
-        game_id: 1,
-      },
-    )
-    .unwrap();
-
-    // check in on game status
-    let game = from_binary::<CwChessGame>(
-      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
-    )
-    .unwrap();
-    assert_eq!(
-      game.moves,
-      vec![
-        (123, CwChessAction::MakeMove("d4".to_string())),
-        (456, CwChessAction::MakeMove("d5".to_string())),
-      ]
-    );
-
-    // white cannot make invalid move (pawn already there)
+    // still too early after two plies
+    let moves = [
+      ("white", CwChessAction::from("d4")),
+      ("black", CwChessAction::from("d5")),
+    ];
+    for (player, action) in moves {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+      .unwrap();
+    }
     let response = execute(
       deps.as_mut(),
       mock_env(),
       mock_info("white", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d5".to_string()),
+        action: CwChessAction::OfferDraw("c4".to_string()),
         game_id: 1,
       },
     );
     match response.unwrap_err() {
-      ContractError::InvalidMove { .. } => {}
+      ContractError::DrawTooEarly { .. } => {}
       e => panic!("unexpected error: {:?}", e),
     }
 
-    // white can make a valid move
-    execute(
+    // offer and accept once the minimum is reached
+    let moves = [
+      ("white", CwChessAction::from("c4")),
+      ("black", CwChessAction::from("e6")),
+      ("white", CwChessAction::OfferDraw("Nc3".to_string())),
+      ("black", CwChessAction::AcceptDraw),
+    ];
+    for (player, action) in moves {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+      .unwrap();
+    }
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::DrawAccepted));
+  }
+
+  #[test]
+  fn test_rate_limit() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
       deps.as_mut(),
       mock_env(),
-      mock_info("white", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("c4".to_string()),
-        game_id: 1,
-      },
+      mock_info("owner", &[]),
+      InstantiateMsg {},
     )
     .unwrap();
+
+    // only the owner sets the limit, and it can't stop every message
+    let set_rate_limit = |deps: DepsMut, sender: &str, per_block: u8| {
+      execute(deps, block_env(1), mock_info(sender, &[]), ExecuteMsg::SetRateLimit { per_block })
+    };
+    match set_rate_limit(deps.as_mut(), "white", 2).unwrap_err() {
+      ContractError::Unauthorized {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match set_rate_limit(deps.as_mut(), "owner", 0).unwrap_err() {
+      ContractError::InvalidRateLimit {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    set_rate_limit(deps.as_mut(), "owner", 2).unwrap();
+
+    let create_challenge = |deps: DepsMut, sender: &str, height: u64| {
+      execute(
+        deps,
+        block_env(height),
+        mock_info(sender, &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          play_as: None,
+          start_at: None,
+          start_fen: None,
+        },
+      )
+    };
+    create_challenge(deps.as_mut(), "white", 10).unwrap();
+    create_challenge(deps.as_mut(), "white", 10).unwrap();
+    match create_challenge(deps.as_mut(), "white", 10).unwrap_err() {
+      ContractError::RateLimitExceeded { max } => assert_eq!(max, 2),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    // other senders and the next block are not limited
+    create_challenge(deps.as_mut(), "black", 10).unwrap();
+    create_challenge(deps.as_mut(), "white", 11).unwrap();
+
+    // neither are the owner's admin messages, but the owner's others are
+    for per_block in 1..=3 {
+      set_rate_limit(deps.as_mut(), "owner", per_block).unwrap();
+    }
+    create_challenge(deps.as_mut(), "owner", 1).unwrap();
+    create_challenge(deps.as_mut(), "owner", 1).unwrap();
+    create_challenge(deps.as_mut(), "owner", 1).unwrap();
+    match create_challenge(deps.as_mut(), "owner", 1).unwrap_err() {
+      ContractError::RateLimitExceeded { max } => assert_eq!(max, 3),
+      e => panic!("unexpected error: {:?}", e),
+    }
   }
 
   #[test]
-  fn test_resign() {
+  fn test_max_rejected_moves() {
     let mut deps = mock_dependencies();
 
     // initialize
@@ -486,81 +695,3535 @@ mod tests {
       InstantiateMsg {},
     )
     .unwrap();
-    // create game
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("black", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
-        // creator is black
-        play_as: Some(CwChessColor::Black),
-      },
+      mock_info("owner", &[]),
+      ExecuteMsg::SetMaxRejectedMoves { max: 2 },
     )
     .unwrap();
     execute(
       deps.as_mut(),
       mock_env(),
       mock_info("white", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::White),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let turn = |deps: DepsMut, player: &str, move_str: &str| {
+      execute(
+        deps,
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap()
+    };
+    let get_game = |deps: Deps| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+
+    // rejected moves are counted, not errors
+    let response = turn(deps.as_mut(), "white", "e5");
+    let rejected = response.events.iter().find(|event| event.ty == "move-rejected").unwrap();
+    assert!(rejected
+      .attributes
+      .iter()
+      .any(|attr| attr.key == "reason" && attr.value == "piece cannot reach that square"));
+    turn(deps.as_mut(), "white", "Ke2");
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.white_rejected_moves, 2);
+    assert_eq!(game.ply_count(), 0);
+
+    // a legal move resets the count
+    let response = turn(deps.as_mut(), "white", "e4");
+    assert!(response.events.is_empty());
+    assert_eq!(get_game(deps.as_ref()).white_rejected_moves, 0);
+    turn(deps.as_mut(), "black", "e4");
+    assert_eq!(get_game(deps.as_ref()).black_rejected_moves, 1);
+    turn(deps.as_mut(), "black", "e5");
+    assert_eq!(get_game(deps.as_ref()).black_rejected_moves, 0);
+
+    // the third in a row forfeits
+    for move_str in ["e4", "Qh6", "Kf3"] {
+      turn(deps.as_mut(), "white", move_str);
+    }
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteForfeits));
+    let record = from_binary::<GameRecord>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::CompletedGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert!(record.pgn.ends_with(" 0-1"));
+    assert!(record.black_elo_after > record.black_elo_before);
+  }
+
+  #[test]
+  fn test_games_from_position() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // two games from the same puzzle, move counters do not matter
+    // and one from the standard position
+    let puzzle = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    let start_fens = [
+      Some(puzzle.to_string()),
+      None,
+      Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 12 40".to_string()),
+    ];
+    for (i, start_fen) in start_fens.iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          // creator is black
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: start_fen.clone(),
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::AcceptChallenge {
+          challenge_id: i as u64 + 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GamesFromPosition {
+          fen: puzzle.to_string(),
+          start_after: None,
+          limit: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.iter().map(|game| game.game_id).collect::<Vec<_>>(), vec![1, 3]);
+
+    // the puzzle is played from its own position
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("Ra8"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteCheckmates));
+    assert_eq!(game.mate_pattern, Some(CwChessMatePattern::BackRank));
+
+    // the standard position
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GamesFromPosition {
+          fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+          start_after: None,
+          limit: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.iter().map(|game| game.game_id).collect::<Vec<_>>(), vec![2]);
+
+    // black to move is not supported
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: None,
+        start_at: None,
+        start_fen: Some("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1".to_string()),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidFen { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_get_games() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // create first game (with two as white, one as black)
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("one", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // get_games should return the game
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGames {
+          after: None,
+          game_over: None,
+          player: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].turn_color, Some(CwChessColor::White));
+
+    // create second game (with one as white, two as black)
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    // opponent can accept
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("one", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+
+    // white makes move in first game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // get games
+    // internally player1 index is scanned before player2 index
+    // this should still return games in order by game_id
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGames {
+          after: None,
+          game_over: None,
+          player: Some("one".to_string()),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].game_id, 1);
+    assert_eq!(games[0].turn_color, Some(CwChessColor::Black));
+    assert_eq!(games[0].player1, "two");
+    assert_eq!(games[0].player2, "one");
+    assert_eq!(games[1].game_id, 2);
+    assert_eq!(games[1].turn_color, Some(CwChessColor::White));
+    assert_eq!(games[1].player1, "one");
+    assert_eq!(games[1].player2, "two");
+  }
+
+  #[test]
+  fn test_games_by_status() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create three games
+    for challenge_id in 1..=3 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          // creator is black
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    // finish the second game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 2,
+      },
+    )
+    .unwrap();
+
+    let games_by_status = |status: GameStatus, start_after: Option<u64>, limit: Option<u32>| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GamesByStatus {
+            status,
+            start_after,
+            limit,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+
+    assert_eq!(games_by_status(GameStatus::InProgress, None, None), vec![1, 3]);
+    assert_eq!(games_by_status(GameStatus::Finished, None, None), vec![2]);
+    // pagination
+    assert_eq!(games_by_status(GameStatus::InProgress, None, Some(1)), vec![1]);
+    assert_eq!(games_by_status(GameStatus::InProgress, Some(1), Some(1)), vec![3]);
+    assert_eq!(games_by_status(GameStatus::InProgress, Some(3), None), Vec::<u64>::new());
+  }
+
+  #[test]
+  fn test_games_by_player() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // alice plays 20 games against bob, alternating colors
+    for challenge_id in 1..=20 {
+      let alice_color = if challenge_id % 2 == 0 {
+        CwChessColor::Black
+      } else {
+        CwChessColor::White
+      };
+      // a block per game, under the rate limit
+      let env = block_env(mock_env().block.height + challenge_id);
+      execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          play_as: Some(alice_color),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        env,
+        mock_info("bob", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    // a game alice isn't in
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("bob", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::White),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("carol", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 21 },
+    )
+    .unwrap();
+    // white resigns every third game
+    for game_id in (3..=21).step_by(3) {
+      let white = match game_id {
+        21 => "bob",
+        _ if game_id % 2 == 0 => "bob",
+        _ => "alice",
+      };
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::Resign {},
+          game_id,
+        },
+      )
+      .unwrap();
+    }
+
+    let games_by_player = |status: Option<GameStatus>, start_after: Option<u64>, limit: Option<u32>| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GamesByPlayer {
+            player: "alice".to_string(),
+            status,
+            start_after,
+            limit,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+
+    assert_eq!(games_by_player(None, None, None), (1..=20).collect::<Vec<_>>());
+    assert_eq!(
+      games_by_player(Some(GameStatus::Finished), None, None),
+      vec![3, 6, 9, 12, 15, 18]
+    );
+    assert_eq!(
+      games_by_player(Some(GameStatus::InProgress), None, None),
+      (1..=20).filter(|game_id| game_id % 3 != 0).collect::<Vec<_>>()
+    );
+    // pagination
+    assert_eq!(games_by_player(Some(GameStatus::InProgress), Some(10), Some(3)), vec![11, 13, 14]);
+    assert_eq!(games_by_player(Some(GameStatus::Finished), Some(18), None), Vec::<u64>::new());
+  }
+
+  #[test]
+  fn test_active_game_between() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    let active_game_between = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player_a: &str, player_b: &str| {
+      from_binary::<Option<u64>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::ActiveGameBetween {
+            player_a: player_a.to_string(),
+            player_b: player_b.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    assert_eq!(active_game_between(&deps, "white", "black"), None);
+
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    // an open challenge is not a game yet
+    assert_eq!(active_game_between(&deps, "white", "black"), None);
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // either order finds the game
+    assert_eq!(active_game_between(&deps, "white", "black"), Some(1));
+    assert_eq!(active_game_between(&deps, "black", "white"), Some(1));
+    assert_eq!(active_game_between(&deps, "white", "other"), None);
+
+    // no longer active once over
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(active_game_between(&deps, "white", "black"), None);
+  }
+
+  #[test]
+  fn test_make_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create challenge
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    // opponent can accept
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // first move by white
+    let mut env = mock_env();
+    env.block.height = 123;
+    execute(
+      deps.as_mut(),
+      env,
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // white cannot go when blacks turn
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("c4".to_string()),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // first move by black
+    let mut env = mock_env();
+    env.block.height = 456;
+    execute(
+      deps.as_mut(),
+      env,
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d5".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // check in on game status
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      game.moves,
+      vec![
+        (123, CwChessAction::MakeMove("d4".to_string())),
+        (456, CwChessAction::MakeMove("d5".to_string())),
+      ]
+    );
+
+    // white cannot make invalid move (pawn already there)
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d5".to_string()),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      e @ ContractError::IllegalMove { .. } => {
+        assert_eq!(e.to_string(), "illegal move d5: path is blocked");
+      }
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the error says why castling is not possible
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("0-0".to_string()),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::IllegalMove { notation, reason } => {
+        assert_eq!(notation, "0-0");
+        assert_eq!(reason, CwChessIllegalMove::PathBlocked);
+      }
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // malformed notation is rejected, even for a game that does not exist
+    for game_id in [1, 99] {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::OfferDraw("c4!!".to_string()),
+          game_id,
+        },
+      );
+      match response.unwrap_err() {
+        ContractError::InvalidMoveNotation { notation } => assert_eq!(notation, "c4!!"),
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    // white can make a valid move
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("c4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn test_resign() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // check game status
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.player1, "white");
+    assert_eq!(game.player2, "black");
+    assert_eq!(game.status, None);
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+
+    // white resigns
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // game status updated
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
+
+    // cannot move after game over
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d4".to_string()),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_confirm_resign() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: true,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: DepsMut, height: u64, player: &str, action: CwChessAction| {
+      execute(
+        deps,
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+      .unwrap()
+    };
+    let get_game = |deps: Deps| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+
+    // the first resign only asks for confirmation
+    let response = turn(deps.as_mut(), 100, "white", CwChessAction::Resign);
+    assert_eq!(response.events[0].ty, "resign-pending");
+    assert_eq!(response.events[0].attributes[1].value, "110");
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.status, None);
+    assert_eq!(game.resign_pending_at, Some(100));
+    assert!(game.moves.is_empty());
+
+    // a move in between clears it, so the next resign is a first one again
+    turn(deps.as_mut(), 101, "white", CwChessAction::from("e4"));
+    assert_eq!(get_game(deps.as_ref()).resign_pending_at, None);
+    turn(deps.as_mut(), 102, "black", CwChessAction::Resign);
+    turn(deps.as_mut(), 103, "black", CwChessAction::from("e5"));
+    assert_eq!(get_game(deps.as_ref()).status, None);
+
+    // confirming after the window is another first resign
+    turn(deps.as_mut(), 104, "white", CwChessAction::Resign);
+    turn(deps.as_mut(), 104 + RESIGN_CONFIRM_BLOCKS + 1, "white", CwChessAction::Resign);
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.status, None);
+    assert_eq!(game.resign_pending_at, Some(104 + RESIGN_CONFIRM_BLOCKS + 1));
+
+    // the second resign in time finalizes
+    turn(deps.as_mut(), 120, "white", CwChessAction::Resign);
+    assert_eq!(get_game(deps.as_ref()).status, Some(CwChessGameOver::WhiteResigns));
+  }
+
+  #[test]
+  fn test_confirm_moves() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: true,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let send = |deps: DepsMut, height: u64, player: &str, msg: ExecuteMsg| {
+      execute(deps, block_env(height), mock_info(player, &[]), msg)
+    };
+    let turn = |action: &str| ExecuteMsg::Turn {
+      action: CwChessAction::from(action),
+      game_id: 1,
+    };
+    let get_game = |deps: Deps| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+
+    // a move is only proposed
+    let response = send(deps.as_mut(), 100, "white", turn("e4")).unwrap();
+    assert_eq!(response.events[0].ty, "move-pending");
+    assert_eq!(response.events[0].attributes[2].value, (100 + MOVE_CONFIRM_BLOCKS).to_string());
+    let game = get_game(deps.as_ref());
+    assert!(game.moves.is_empty());
+    assert_eq!(game.fen, DEFAULT_FEN);
+    assert_eq!(
+      game.pending_move,
+      Some((Addr::unchecked("white"), CwChessAction::from("e4"), 100 + MOVE_CONFIRM_BLOCKS))
+    );
+
+    // illegal moves are refused right away, only the proposer can confirm
+    send(deps.as_mut(), 101, "white", turn("e5")).unwrap_err();
+    match send(deps.as_mut(), 102, "black", ExecuteMsg::ConfirmMove { game_id: 1 }) {
+      Err(ContractError::NoPendingMove {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    // confirming plays the proposed move
+    let response = send(deps.as_mut(), 103, "white", ExecuteMsg::ConfirmMove { game_id: 1 }).unwrap();
+    assert_eq!(response.attributes[0].value, "confirm_move");
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.moves, vec![(103, CwChessAction::from("e4"))]);
+    assert_eq!(game.pending_move, None);
+    match send(deps.as_mut(), 104, "white", ExecuteMsg::ConfirmMove { game_id: 1 }) {
+      Err(ContractError::NoPendingMove {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    // cancelling drops it, and a new proposal replaces the old one
+    send(deps.as_mut(), 105, "black", turn("e6")).unwrap();
+    send(deps.as_mut(), 106, "black", ExecuteMsg::CancelMove { game_id: 1 }).unwrap();
+    assert_eq!(get_game(deps.as_ref()).pending_move, None);
+    send(deps.as_mut(), 107, "black", ExecuteMsg::CancelMove { game_id: 1 }).unwrap_err();
+    send(deps.as_mut(), 108, "black", turn("e6")).unwrap();
+    send(deps.as_mut(), 109, "black", turn("c5")).unwrap();
+    send(deps.as_mut(), 110, "black", ExecuteMsg::ConfirmMove { game_id: 1 }).unwrap();
+    assert_eq!(get_game(deps.as_ref()).moves[1], (110, CwChessAction::from("c5")));
+
+    // an unconfirmed move expires
+    send(deps.as_mut(), 111, "white", turn("Nf3")).unwrap();
+    let expired_at = 111 + MOVE_CONFIRM_BLOCKS;
+    match send(deps.as_mut(), expired_at + 1, "white", ExecuteMsg::ConfirmMove { game_id: 1 }) {
+      Err(ContractError::MoveConfirmationExpired { expired_at: at }) => assert_eq!(at, expired_at),
+      result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(get_game(deps.as_ref()).moves.len(), 2);
+
+    // resigning is not held back
+    send(deps.as_mut(), expired_at + 2, "white", ExecuteMsg::Turn {
+      action: CwChessAction::Resign,
+      game_id: 1,
+    })
+    .unwrap();
+    let game = get_game(deps.as_ref());
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
+    assert_eq!(game.pending_move, None);
+  }
+
+  #[test]
+  fn test_legal_moves_perspective() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let legal_moves_from = |perspective: Option<CwChessColor>, square: Option<&str>| {
+      from_binary::<Vec<String>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::LegalMoves {
+            game_id: 1,
+            perspective,
+            square: square.map(String::from),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let legal_moves = |perspective: Option<CwChessColor>| legal_moves_from(perspective, None);
+
+    let white = legal_moves(Some(CwChessColor::White));
+    let black = legal_moves(Some(CwChessColor::Black));
+    assert_eq!(white, legal_moves(None));
+    assert_eq!(white.len(), 20);
+    assert_eq!(black.len(), 20);
+    assert!(white.contains(&"e2e4".to_string()));
+    assert!(white.contains(&"g1f3".to_string()));
+    // black sees the same moves with the board rotated
+    assert!(!black.contains(&"e2e4".to_string()));
+    assert!(black.contains(&"d7d5".to_string()));
+    assert!(black.contains(&"b8c6".to_string()));
+
+    // moves of a single piece
+    let mut knight = legal_moves_from(None, Some("g1"));
+    knight.sort();
+    assert_eq!(knight, vec!["g1f3", "g1h3"]);
+    let knight = legal_moves_from(Some(CwChessColor::Black), Some("b8"));
+    assert_eq!(knight.len(), 2);
+    assert!(knight.contains(&"b8c6".to_string()));
+    assert!(legal_moves_from(None, Some("e4")).is_empty());
+    assert!(legal_moves_from(None, Some("e7")).is_empty());
+  }
+
+  #[test]
+  fn test_piece_moves() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // white's knight on c3 is pinned by the bishop on b4, white to move
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::White),
+        start_at: None,
+        start_fen: Some("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1".to_string()),
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let piece_moves = |square: &str| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PieceMoves {
+          game_id: 1,
+          square: square.to_string(),
+        },
+      )
+      .and_then(|binary| from_binary::<CwChessPieceMoves>(&binary))
+    };
+
+    // the pinned knight can't leave the ray
+    let knight = piece_moves("c3").unwrap();
+    assert!(knight.to_move);
+    assert_eq!(knight.pseudo_legal.len(), 8);
+    assert!(knight.pseudo_legal.contains(&"c3d5".to_string()));
+    assert!(knight.legal.is_empty());
+
+    // black's bishop, probed on white's turn
+    let bishop = piece_moves("b4").unwrap();
+    assert!(!bishop.to_move);
+    assert_eq!(bishop.pseudo_legal.len(), 7);
+    assert!(bishop.pseudo_legal.contains(&"b4c3".to_string()));
+    assert_eq!(bishop.legal, bishop.pseudo_legal);
+
+    let empty = piece_moves("d4").unwrap();
+    assert!(!empty.to_move);
+    assert!(empty.pseudo_legal.is_empty() && empty.legal.is_empty());
+    assert!(piece_moves("z9").is_err());
+  }
+
+  #[test]
+  fn test_material_imbalance() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::White),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let material_imbalance = |deps: Deps| {
+      from_binary::<CwChessMaterialImbalance>(
+        &query(deps, mock_env(), QueryMsg::MaterialImbalance { game_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+    let even = CwChessPieceDifference {
+      pawns: 0,
+      knights: 0,
+      bishops: 0,
+      rooks: 0,
+      queens: 0,
+    };
+    assert_eq!(material_imbalance(deps.as_ref()).net, even);
+
+    // exchange variation of the Ruy Lopez, white gives a bishop for a knight
+    let moves = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6"];
+    for (i, move_str) in moves.iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(if i % 2 == 0 { "white" } else { "black" }, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    let imbalance = material_imbalance(deps.as_ref());
+    assert_eq!(
+      imbalance.white,
+      CwChessPieceCounts {
+        pawns: 8,
+        knights: 2,
+        bishops: 1,
+        rooks: 2,
+        queens: 1,
+      }
+    );
+    assert_eq!(
+      imbalance.black,
+      CwChessPieceCounts {
+        pawns: 8,
+        knights: 1,
+        bishops: 2,
+        rooks: 2,
+        queens: 1,
+      }
+    );
+    assert_eq!(
+      imbalance.net,
+      CwChessPieceDifference {
+        knights: 1,
+        bishops: -1,
+        ..even
+      }
+    );
+  }
+
+  #[test]
+  fn test_replay() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    let start_fen = "4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1";
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::White),
+        start_at: None,
+        start_fen: Some(start_fen.to_string()),
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // castling, an underpromotion and a capture
+    let moves = [
+      ("white", CwChessAction::from("0-0")),
+      ("black", CwChessAction::from("Kd7")),
+      ("white", CwChessAction::OfferDraw("b8N".to_string())),
+      ("black", CwChessAction::from("Kc7")),
+      ("white", CwChessAction::from("Rf7")),
+      ("black", CwChessAction::from("Kxb8")),
+    ];
+    for (player, action) in moves {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+      .unwrap();
+    }
+
+    let replay = from_binary::<GameReplay>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::Replay { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(replay.start_fen, start_fen);
+    assert_eq!(replay.moves.len(), 6);
+
+    // decoding the moves replays the game
+    let mut board = parse_fen(&replay.start_fen).unwrap();
+    let mut decoded = vec![];
+    for packed in replay.moves {
+      let chess_move = unpack_move(&board, packed).unwrap();
+      decoded.push(format_san_move(&board, chess_move));
+      board = board.apply_move(chess_move).change_turn();
+    }
+    assert_eq!(decoded, ["0-0", "Kd7", "b8N+", "Kc7", "Rf7+", "Kxb8"]);
+    let uci_moves = from_binary::<Vec<String>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::UciMoves { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(uci_moves, ["e1g1", "e8d7", "b7b8n", "d7c7", "f1f7", "c7b8"]);
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(board, parse_fen(&game.fen).unwrap());
+  }
+
+  #[test]
+  fn test_explain_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let explain = |player: &str, from: &str, to: &str| -> Option<CwChessIllegalMove> {
+      from_binary::<Option<CwChessIllegalMove>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::ExplainMove {
+            game_id: 1,
+            player: player.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+
+    // legal move has no explanation
+    assert_eq!(explain("white", "e2", "e4"), None);
+    // black cannot move first
+    assert_eq!(explain("black", "e7", "e5"), Some(CwChessIllegalMove::WrongTurn));
+    assert_eq!(explain("white", "e7", "e5"), Some(CwChessIllegalMove::NotYourPiece));
+    assert_eq!(explain("white", "e4", "e5"), Some(CwChessIllegalMove::NoPiece));
+    assert_eq!(explain("white", "c1", "e3"), Some(CwChessIllegalMove::PathBlocked));
+    assert_eq!(explain("white", "b1", "b3"), Some(CwChessIllegalMove::PieceCannotReach));
+  }
+
+  #[test]
+  fn test_adjudication() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetAdjudication {
+        depth: 1,
+        min_moves: 0,
+        threshold_centipawns: 50,
+      },
+    )
+    .unwrap();
+    // create game adjudicated as soon as a pawn is won for good
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: true,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let moves = [("white", "e4"), ("black", "f5"), ("white", "exf5")];
+    let mut response = None;
+    for (player, move_str) in moves.iter() {
+      response = Some(
+        execute(
+          deps.as_mut(),
+          mock_env(),
+          mock_info(player, &[]),
+          ExecuteMsg::Turn {
+            action: CwChessAction::from(*move_str),
+            game_id: 1,
+          },
+        )
+        .unwrap(),
+      );
+    }
+
+    // black cannot win the pawn back
+    let response = response.unwrap();
+    assert_eq!(response.attributes[2].value, "WhiteWinsByAdjudication");
+    assert_eq!(response.events.len(), 1);
+    assert_eq!(response.events[0].ty, "game-adjudicated");
+    assert_eq!(response.events[0].attributes[1].key, "evaluation");
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteWinsByAdjudication));
+  }
+
+  #[test]
+  fn test_set_adjudication() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    let set = |deps: DepsMut, sender: &str, depth: u8, threshold_centipawns: u32| {
+      execute(
+        deps,
+        mock_env(),
+        mock_info(sender, &[]),
+        ExecuteMsg::SetAdjudication {
+          depth,
+          min_moves: 30,
+          threshold_centipawns,
+        },
+      )
+    };
+
+    match set(deps.as_mut(), "white", 2, 1000) {
+      Err(ContractError::Unauthorized {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    // depth is capped, the search runs after every move
+    let invalid = [
+      (0, 1000),
+      (MAX_ADJUDICATION_DEPTH + 1, 1000),
+      (2, 0),
+      (2, MAX_ADJUDICATION_CENTIPAWNS + 1),
+    ];
+    for (depth, threshold) in invalid {
+      match set(deps.as_mut(), "owner", depth, threshold) {
+        Err(ContractError::InvalidAdjudicationSettings { .. }) => {}
+        result => panic!("unexpected result: {:?}", result),
+      }
+    }
+    set(deps.as_mut(), "owner", 2, 1000).unwrap();
+
+    // a challenge that opts in gets the owner's settings
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: true,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      game.adjudication,
+      Some(CwChessAdjudication {
+        depth: 2,
+        min_moves: 30,
+        threshold_centipawns: 1000,
+      })
+    );
+  }
+
+  #[test]
+  fn test_arbiter_adjudication() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // two games between the same players
+    for challenge_id in [1, 2] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    let adjudicate = |deps: DepsMut, sender: &str, game_id: u64, winner: Option<CwChessColor>| {
+      execute(
+        deps,
+        mock_env(),
+        mock_info(sender, &[]),
+        ExecuteMsg::Adjudicate { game_id, winner },
+      )
+    };
+    // (white, black)
+    let ratings = |deps: Deps| {
+      let ratings =
+        from_binary::<Vec<RatingSummary>>(&query(deps, mock_env(), QueryMsg::GetRatings {}).unwrap())
+          .unwrap();
+      let rating = |player: &str| ratings.iter().find(|summary| summary.player == player).unwrap().rating;
+      (rating("white"), rating("black"))
+    };
+
+    // only the owner is an arbiter, players cannot award themselves the game
+    for sender in ["white", "black", "someone"] {
+      match adjudicate(deps.as_mut(), sender, 1, Some(CwChessColor::White)).unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    match adjudicate(deps.as_mut(), "owner", 9, None).unwrap_err() {
+      ContractError::GameNotFound { game_id } => assert_eq!(game_id, 9),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let before = ratings(deps.as_ref());
+    assert_eq!(before.0, before.1);
+
+    // a win is rated like any other
+    let response = adjudicate(deps.as_mut(), "owner", 1, Some(CwChessColor::White)).unwrap();
+    assert_eq!(response.attributes[0].value, "adjudicate");
+    assert_eq!(response.attributes[2].value, "Adjudicated { winner: Some(White) }");
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    // told apart from the engine's adjudication
+    assert_eq!(
+      game.status,
+      Some(CwChessGameOver::Adjudicated {
+        winner: Some(CwChessColor::White)
+      })
+    );
+    let after_win = ratings(deps.as_ref());
+    assert!(after_win.0 > before.0);
+    assert!(after_win.1 < before.1);
+
+    // a finished game cannot be ruled on again
+    match adjudicate(deps.as_mut(), "owner", 1, Some(CwChessColor::Black)).unwrap_err() {
+      ContractError::GameAlreadyOver { game_id, .. } => assert_eq!(game_id, 1),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // a ruled draw brings the lower rated player up
+    adjudicate(deps.as_mut(), "owner", 2, None).unwrap();
+    let after_draw = ratings(deps.as_ref());
+    assert!(after_draw.0 < after_win.0);
+    assert!(after_draw.1 > after_win.1);
+  }
+
+  #[test]
+  fn test_stalemate_risk() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // black king cornered by king and rook
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: true,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: Some("7k/5K2/8/8/8/8/8/6R1 w - - 0 1".to_string()),
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let turn = |deps: DepsMut, msg: ExecuteMsg| {
+      execute(deps, mock_env(), mock_info("white", &[]), msg).unwrap()
+    };
+    let get_game = |deps: Deps| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+
+    // warned while the move can still be cancelled
+    let rg7 = ExecuteMsg::Turn {
+      action: CwChessAction::MakeMove("Rg7".to_string()),
+      game_id: 1,
+    };
+    let response = turn(deps.as_mut(), rg7.clone());
+    assert_eq!(response.events.len(), 2);
+    assert_eq!(response.events[0].ty, "move-pending");
+    assert_eq!(response.events[1].ty, "stalemate-risk");
+    assert_eq!(response.events[1].attributes[1].value, "Rg7");
+    assert_eq!(get_game(deps.as_ref()).status, None);
+    turn(deps.as_mut(), ExecuteMsg::CancelMove { game_id: 1 });
+
+    // a safe move has no warning
+    let response = turn(
+      deps.as_mut(),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("Rg2".to_string()),
+        game_id: 1,
+      },
+    );
+    assert_eq!(response.events.len(), 1);
+    turn(deps.as_mut(), ExecuteMsg::CancelMove { game_id: 1 });
+
+    // the warning doesn't stop the move
+    turn(deps.as_mut(), rg7);
+    let response = turn(deps.as_mut(), ExecuteMsg::ConfirmMove { game_id: 1 });
+    assert!(response.events.is_empty());
+    assert_eq!(get_game(deps.as_ref()).status, Some(CwChessGameOver::Stalemate));
+  }
+
+  #[test]
+  fn test_draft_position() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create draft
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateDraft {
+        adjudication: false,
+        block_limit: None,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+      },
+    )
+    .unwrap();
+
+    // every message after this one is sent a block later than the last, the
+    // draft takes more edits than the rate limit allows in one block
+    let height = Cell::new(mock_env().block.height);
+    let next_block = || {
+      height.set(height.get() + 1);
+      block_env(height.get())
+    };
+
+    let edit = |deps: DepsMut, sender: &str, square: &str, piece: Option<&str>| {
+      let msg = match piece {
+        Some(piece) => ExecuteMsg::PlacePiece {
+          challenge_id: 1,
+          piece: piece.to_string(),
+          square: square.to_string(),
+        },
+        None => ExecuteMsg::RemovePiece {
+          challenge_id: 1,
+          square: square.to_string(),
+        },
+      };
+      execute(deps, next_block(), mock_info(sender, &[]), msg)
+    };
+    let lock = |deps: DepsMut, sender: &str| {
+      execute(
+        deps,
+        next_block(),
+        mock_info(sender, &[]),
+        ExecuteMsg::LockPosition { challenge_id: 1 },
+      )
+    };
+
+    // K+Q vs K, with a knight placed and taken back
+    for (square, piece) in [("e1", "K"), ("d1", "Q"), ("a5", "n")] {
+      edit(deps.as_mut(), "black", square, Some(piece)).unwrap();
+    }
+    edit(deps.as_mut(), "black", "a5", None).unwrap();
+
+    // only the creator edits
+    match edit(deps.as_mut(), "white", "h1", Some("R")).unwrap_err() {
+      ContractError::NotYourChallenge {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match edit(deps.as_mut(), "black", "i9", Some("R")).unwrap_err() {
+      ContractError::InvalidPosition {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match edit(deps.as_mut(), "black", "h1", Some("X")).unwrap_err() {
+      ContractError::InvalidFen { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // not playable yet
+    let response = execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::PositionNotLocked {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    // black has no king
+    match lock(deps.as_mut(), "black").unwrap_err() {
+      ContractError::InvalidFen { reason } => assert_eq!(reason, "each side needs one king"),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    edit(deps.as_mut(), "black", "h8", Some("k")).unwrap();
+    match lock(deps.as_mut(), "white").unwrap_err() {
+      ContractError::NotYourChallenge {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    lock(deps.as_mut(), "black").unwrap();
+
+    // locked positions cannot change
+    match edit(deps.as_mut(), "black", "d1", None).unwrap_err() {
+      ContractError::PositionLocked {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match lock(deps.as_mut(), "black").unwrap_err() {
+      ContractError::PositionLocked {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("Qd5"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.start_fen, Some("7k/8/8/8/8/8/8/3QK3 w - - 0 1".to_string()));
+    assert_eq!(game.fen, "7k/8/8/3Q4/8/8/8/4K3 b - - 0 0");
+  }
+
+  #[test]
+  fn test_should_resign() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // standard position, and black down a queen
+    let start_fens = [None, Some("4k3/8/8/8/8/8/8/3QK3 w - - 0 1".to_string())];
+    for (i, start_fen) in start_fens.iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          // creator is black
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: start_fen.clone(),
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::AcceptChallenge {
+          challenge_id: i as u64 + 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let should_resign = |game_id: u64, for_color: CwChessColor| {
+      from_binary::<bool>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::ShouldResign { game_id, for_color },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    assert!(!should_resign(1, CwChessColor::White));
+    assert!(!should_resign(1, CwChessColor::Black));
+    assert!(!should_resign(2, CwChessColor::White));
+    assert!(should_resign(2, CwChessColor::Black));
+  }
+
+  #[test]
+  fn test_top_moves() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // white can take the queen
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: Some("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1".to_string()),
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let top_moves = |n: u8| {
+      from_binary::<Vec<MoveEvaluation>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::TopMoves { game_id: 1, n, depth: 1 },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let moves = top_moves(3);
+    assert_eq!(moves.len(), 3);
+    assert_eq!(moves[0].algebraic, "Rxd5");
+    assert_eq!(moves[0].uci, "d2d5");
+    assert_eq!(moves[0].pv[0], "Rxd5");
+    assert_eq!(moves[0].pv.len(), 2);
+    assert!(moves[0].evaluation > 0);
+    assert!(moves.windows(2).all(|pair| pair[0].evaluation >= pair[1].evaluation));
+
+    // at most 5
+    assert_eq!(top_moves(10).len(), 5);
+  }
+
+  #[test]
+  fn test_completed_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // no record while the game is being played
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::CompletedGame { game_id: 1 });
+    assert!(response.is_err());
+
+    // fool's mate
+    let moves = [("white", "f3"), ("black", "e5"), ("white", "g4"), ("black", "Qh4")];
+    let mut move_events = String::new();
+    for (i, (player, move_str)) in moves.iter().enumerate() {
+      let response = execute(
+        deps.as_mut(),
+        block_env(110 + i as u64),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+      assert_eq!(response.attributes[3].key, "move_events");
+      move_events = response.attributes[3].value.clone();
+    }
+    assert_eq!(move_events, "move d8 h4,checkmate");
+    // the queen mates in the open, no known pattern
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.mate_pattern, None);
+
+    let record = from_binary::<GameRecord>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::CompletedGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(record.result, CwChessGameOver::BlackCheckmates);
+    assert_eq!(record.pgn, "1. f3 e5 2. g4 Qh4# 0-1");
+    assert_eq!(record.move_count, 4);
+    assert_eq!(record.created_at_block, 100);
+    assert_eq!(record.ended_at_block, 113);
+    assert_eq!(record.game_duration_blocks, 13);
+    assert!(record.black_elo_after > record.black_elo_before);
+    assert!(record.white_elo_after < record.white_elo_before);
+
+    let recent = from_binary::<Vec<GameRecord>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::RecentCompletedGames { limit: 5 },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(recent, vec![record]);
+  }
+
+  #[test]
+  fn test_archived_pgn() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // not archived while the game is being played
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::ArchivedPgn { game_id: 1 });
+    assert!(response.is_err());
+
+    // scholar's mate
+    let moves = ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7"];
+    for (i, move_str) in moves.iter().enumerate() {
+      let player = if i % 2 == 0 { "white" } else { "black" };
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let pgn = from_binary::<String>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::ArchivedPgn { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      pgn,
+      "[Event \"On-Chain Game\"]\n\
+       [Site \"cosmos2contract\"]\n\
+       [Date \"????.??.??\"]\n\
+       [Round \"-\"]\n\
+       [White \"white\"]\n\
+       [Black \"black\"]\n\
+       [Result \"1-0\"]\n\
+       \n\
+       1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"
+    );
+  }
+
+  #[test]
+  fn test_game_notes() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // only players can add notes
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("spectator", &[]),
+      ExecuteMsg::SetGameNote {
+        game_id: 1,
+        note: "great game".to_string(),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetGameNote {
+        game_id: 1,
+        note: "x".repeat(MAX_NOTE_CHARS + 1),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NoteTooLong { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    for (player, note) in [("white", "trying the king's gambit"), ("black", "study the endgame")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::SetGameNote {
+          game_id: 1,
+          note: note.to_string(),
+        },
+      )
+      .unwrap();
+    }
+
+    // notes can't be changed
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetGameNote {
+        game_id: 1,
+        note: "changed my mind".to_string(),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NoteAlreadySet { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    let expected = vec![
+      ("black".to_string(), "study the endgame".to_string()),
+      ("white".to_string(), "trying the king's gambit".to_string()),
+    ];
+    let notes = from_binary::<Vec<(String, String)>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GameNotes { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(notes, expected);
+
+    // notes of an active game stay where they are
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ArchiveGameNotes { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::NotesNotExpired { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.height += NOTE_ARCHIVE_BLOCKS - 1;
+    let response = execute(
+      deps.as_mut(),
+      env.clone(),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ArchiveGameNotes { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::NotesNotExpired { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    env.block.height += 1;
+    execute(
+      deps.as_mut(),
+      env,
+      mock_info("anyone", &[]),
+      ExecuteMsg::ArchiveGameNotes { game_id: 1 },
+    )
+    .unwrap();
+    assert!(GAME_NOTES
+      .prefix(1)
+      .range(&deps.storage, None, None, Order::Ascending)
+      .next()
+      .is_none());
+    let notes = from_binary::<Vec<(String, String)>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GameNotes { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(notes, expected);
+
+    // archived notes still can't be replaced
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetGameNote {
+        game_id: 1,
+        note: "changed my mind".to_string(),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NoteAlreadySet { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_ply_limit() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game capped at two plies
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: Some(2),
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("e4"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let result = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("e5"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(result.attributes[2].value, "PlyLimit");
+
+    // game is drawn and closed
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::PlyLimit));
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("d4"),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_set_initial_rating() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // only the owner can import ratings
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetInitialRating {
+        player: "white".to_string(),
+        rating: 2800,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // rating must be in range
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetInitialRating {
+        player: "white".to_string(),
+        rating: 9000,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidRating { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // owner can set a rating before any games
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetInitialRating {
+        player: "white".to_string(),
+        rating: 2100,
+      },
+    )
+    .unwrap();
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings {}).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      ratings,
+      vec![RatingSummary {
+        player: "white".to_string(),
+        rating: 2100,
+      }]
+    );
+
+    // but only once
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetInitialRating {
+        player: "white".to_string(),
+        rating: 2400,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::RatingAlreadySet { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // play a rated game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // rating can no longer be imported
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetInitialRating {
+        player: "white".to_string(),
+        rating: 2100,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::PlayerHasGames { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_k_schedule() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // only the owner can set the schedule
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::SetKSchedule {
+        schedule: vec![(0, 40), (1400, 10)],
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // thresholds must be sorted
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetKSchedule {
+        schedule: vec![(1400, 10), (0, 40)],
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidKSchedule { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::SetKSchedule {
+        schedule: vec![(0, 40), (1400, 10)],
+      },
+    )
+    .unwrap();
+    for (player, rating) in [("carol", 1500), ("dave", 1500)] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::SetInitialRating {
+          player: player.to_string(),
+          rating,
+        },
+      )
+      .unwrap();
+    }
+
+    // black wins a game between each pair
+    let pairs = [("white", "black"), ("carol", "dave")];
+    for (challenge_id, (white, black)) in pairs.iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(black, &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          // creator is black
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        ExecuteMsg::AcceptChallenge {
+          challenge_id: challenge_id as u64 + 1,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::Resign {},
+          game_id: challenge_id as u64 + 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings {}).unwrap(),
+    )
+    .unwrap();
+    let rating = |player: &str| ratings.iter().find(|summary| summary.player == player).unwrap().rating;
+    // below the threshold k is 40
+    assert_eq!(rating("black"), 1020);
+    assert_eq!(rating("white"), 980);
+    // above it k is 10
+    assert_eq!(rating("dave"), 1505);
+    assert_eq!(rating("carol"), 1495);
+  }
+
+  #[test]
+  fn test_practice_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // phantom rating must be in range
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreatePractice {
+        phantom_rating: 9000,
+        play_as: None,
+        start_fen: None,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidRating { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreatePractice {
+        phantom_rating: 1500,
+        play_as: Some(CwChessColor::White),
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGames {
+          after: None,
+          game_over: None,
+          player: Some("white".to_string()),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.len(), 1);
+
+    let get_game = |deps: Deps, game_id: u64| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id }).unwrap())
+        .unwrap()
+    };
+    let turn = |deps: DepsMut, game_id: u64, action: CwChessAction| {
+      execute(deps, mock_env(), mock_info("white", &[]), ExecuteMsg::Turn { action, game_id })
+    };
+
+    // the engine replies for the phantom
+    let response = turn(deps.as_mut(), 1, CwChessAction::from("e4")).unwrap();
+    assert_eq!(response.events[0].ty, "phantom-move");
+    let game = get_game(deps.as_ref(), 1);
+    assert_eq!(game.moves.len(), 2);
+    assert_eq!(game.moves[1].1.notation(), response.events[0].attributes[1].value);
+
+    // the phantom never agrees to a draw
+    for action in [CwChessAction::AcceptDraw, CwChessAction::OfferDraw("d4".to_string())] {
+      match turn(deps.as_mut(), 1, action) {
+        Err(ContractError::PhantomDraw {}) => {}
+        result => panic!("unexpected result: {:?}", result),
+      }
+    }
+    match execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AgreeDraw { game_id: 1 },
+    ) {
+      Err(ContractError::PhantomDraw {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    // playing black, the phantom opens
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreatePractice {
+        phantom_rating: 1500,
+        play_as: Some(CwChessColor::Black),
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    assert_eq!(get_game(deps.as_ref(), 2).moves.len(), 1);
+
+    // mate in one
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::CreatePractice {
+        phantom_rating: 1500,
+        play_as: Some(CwChessColor::White),
+        start_fen: Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()),
+      },
+    )
+    .unwrap();
+    turn(deps.as_mut(), 3, CwChessAction::from("Ra8#")).unwrap();
+    assert_eq!(get_game(deps.as_ref(), 3).status, Some(CwChessGameOver::WhiteCheckmates));
+
+    // only the player is rated, beating a 1500 phantom from the default rating
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings {}).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(ratings.len(), 1);
+    assert_eq!(ratings[0].player, "white");
+    let default_rating: u64 = EloRating::new().into();
+    assert!(ratings[0].rating > default_rating);
+  }
+
+  #[test]
+  fn test_leaderboard() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    let ratings = [
+      ("alice", 1500, 0),
+      ("bob", 1500, 2),
+      ("carol", 1200, 0),
+      ("dave", 1800, 1),
+      ("erin", 1500, 0),
+    ];
+    for (player, rating, games_played) in ratings {
+      let player = Addr::unchecked(player);
+      save_rating(&mut deps.storage, &player, rating, false).unwrap();
+      for _ in 0..games_played {
+        save_rating(&mut deps.storage, &player, rating, true).unwrap();
+      }
+    }
+    // a new rating moves the player up
+    save_rating(&mut deps.storage, &Addr::unchecked("carol"), 1900, true).unwrap();
+
+    let leaderboard = |start_after: Option<&str>, limit: Option<u32>| {
+      from_binary::<Vec<LeaderboardEntry>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::Leaderboard {
+            start_after: start_after.map(String::from),
+            limit,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+
+    assert_eq!(
+      leaderboard(None, None)[..2],
+      [
+        LeaderboardEntry {
+          games_played: 1,
+          player: "carol".to_string(),
+          rating: 1900,
+        },
+        LeaderboardEntry {
+          games_played: 1,
+          player: "dave".to_string(),
+          rating: 1800,
+        },
+      ]
+    );
+    let players = |entries: Vec<LeaderboardEntry>| {
+      entries.into_iter().map(|entry| entry.player).collect::<Vec<_>>()
+    };
+    // ties go to more games played, then address
+    assert_eq!(players(leaderboard(None, None)), ["carol", "dave", "bob", "alice", "erin"]);
+    // pagination
+    assert_eq!(players(leaderboard(None, Some(2))), ["carol", "dave"]);
+    assert_eq!(players(leaderboard(Some("dave"), Some(2))), ["bob", "alice"]);
+    assert_eq!(players(leaderboard(Some("alice"), Some(2))), ["erin"]);
+    assert!(leaderboard(Some("erin"), None).is_empty());
+  }
+
+  #[test]
+  fn test_leaderboard_backfill() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    save_rating(&mut deps.storage, &Addr::unchecked("alice"), 1500, true).unwrap();
+    save_rating(&mut deps.storage, &Addr::unchecked("bob"), 1400, true).unwrap();
+    // a rating saved before standings were kept
+    RATINGS.save(&mut deps.storage, Addr::unchecked("carol"), &1450).unwrap();
+
+    let leaderboard = |deps: Deps, start_after: Option<&str>| {
+      from_binary::<Vec<LeaderboardEntry>>(
+        &query(
+          deps,
+          mock_env(),
+          QueryMsg::Leaderboard {
+            start_after: start_after.map(String::from),
+            limit: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .into_iter()
+      .map(|entry| entry.player)
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(leaderboard(deps.as_ref(), None), ["alice", "bob"]);
+    // placed by rating until it has a standing
+    assert_eq!(leaderboard(deps.as_ref(), Some("carol")), ["bob"]);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(leaderboard(deps.as_ref(), None), ["alice", "carol", "bob"]);
+    assert_eq!(leaderboard(deps.as_ref(), Some("carol")), ["bob"]);
+  }
+
+  #[test]
+  fn test_position_evaluation() {
+    let deps = mock_dependencies();
+
+    let evaluation = from_binary::<CwChessEvaluation>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PositionEvaluation {
+          fen: "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(evaluation.material, 900);
+    // black's king can step to d8, too early in the game to count
+    assert_eq!(evaluation.mobility, 0);
+    // a queen is 4 of the 24 phase points
+    assert_eq!(evaluation.phase, 20);
+    assert_eq!(
+      evaluation.total_centipawns,
+      evaluation.king_safety
+        + evaluation.material
+        + evaluation.mobility
+        + evaluation.pawn_structure
+        + evaluation.piece_square
+    );
+
+    // invalid fen
+    let response = query(
+      deps.as_ref(),
+      mock_env(),
+      QueryMsg::PositionEvaluation {
+        fen: "not a fen".to_string(),
+      },
+    );
+    assert!(response.is_err());
+  }
+
+  #[test]
+  fn test_analyze_fen() {
+    let deps = mock_dependencies();
+    let analyze = |fen: &str| {
+      query(deps.as_ref(), mock_env(), QueryMsg::AnalyzeFen { fen: fen.to_string() })
+        .map(|binary| from_binary::<CwChessFenAnalysis>(&binary).unwrap())
+    };
+
+    let analysis = analyze(DEFAULT_FEN).unwrap();
+    assert_eq!(analysis.to_move, CwChessColor::White);
+    assert!(!analysis.in_check);
+    assert_eq!(analysis.legal_moves.len(), 20);
+    assert!(!analysis.only_king_can_move);
+    assert_eq!(analysis.only_move, None);
+    assert!(analysis.pins.is_empty());
+
+    // the queen checks from d2 and only the king can answer, by taking it or stepping to f1
+    let analysis = analyze("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1").unwrap();
+    assert!(analysis.in_check);
+    assert!(analysis.only_king_can_move);
+    assert_eq!(
+      analysis.legal_moves,
+      vec![
+        CwChessLegalMove {
+          coordinates: "e1f1".to_string(),
+          san: "Kf1".to_string(),
+        },
+        CwChessLegalMove {
+          coordinates: "e1d2".to_string(),
+          san: "Kxd2".to_string(),
+        },
+      ]
+    );
+
+    // the rook covers the g-file, so the king's only move is to h7
+    let analysis = analyze("7k/8/8/8/8/8/8/K5R1 b - - 0 1").unwrap();
+    assert_eq!(
+      analysis.only_move,
+      Some(CwChessLegalMove {
+        coordinates: "h8h7".to_string(),
+        san: "Kh7".to_string(),
+      })
+    );
+
+    // the bishop on b4 pins the knight on c3 to the king
+    let analysis = analyze("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(analysis.pins, vec![("c3".to_string(), "b4".to_string())]);
+
+    // black to move, Nc2+ forks the king and the rook
+    let analysis = analyze("4k3/8/8/8/3n4/8/8/R3K3 b - - 0 1").unwrap();
+    assert_eq!(analysis.to_move, CwChessColor::Black);
+    assert!(!analysis.in_check);
+    assert!(analysis
+      .legal_moves
+      .iter()
+      .any(|legal_move| legal_move.coordinates == "d4c2" && legal_move.san == "Nc2+"));
+
+    // no moves once mated
+    let analysis = analyze("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert!(analysis.in_check);
+    assert!(analysis.legal_moves.is_empty());
+
+    for fen in ["not a fen", "8/8/8/8/8/8/8/8 w - - 0 1"] {
+      let error = analyze(fen).unwrap_err();
+      assert!(error.to_string().contains("invalid fen"), "{}", error);
+    }
+  }
+
+  #[test]
+  fn test_forced_mate() {
+    let deps = mock_dependencies();
+    let forced_mate = |fen: &str, moves: u8| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ForcedMate {
+          fen: fen.to_string(),
+          moves,
+        },
+      )
+      .map(|binary| from_binary::<bool>(&binary).unwrap())
+    };
+
+    // Ra8+ has to be blocked and the rook takes the blocker with mate
+    assert!(forced_mate("7k/4bppp/8/8/8/8/8/R5K1 w - - 0 1", 2).unwrap());
+    assert!(forced_mate("7k/4bppp/8/8/8/8/8/R5K1 w - - 0 1", u8::MAX).unwrap());
+    // the knight guards both blocking squares
+    assert!(!forced_mate("7k/4bppp/4n3/8/8/8/8/R5K1 w - - 0 1", 1).unwrap());
+
+    let error = forced_mate("not a fen", 1).unwrap_err();
+    assert!(error.to_string().contains("invalid fen"), "{}", error);
+  }
+
+  #[test]
+  fn test_opening_stats() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+
+    // ruy lopez, then white wins 6, draws 2 and loses 2
+    let ruy_lopez = [("white", "e4"), ("black", "e5"), ("white", "Nf3"), ("black", "Nc6"), ("white", "Bb5")];
+    let endings = [
+      vec![("black", CwChessAction::Resign)],
+      vec![("black", CwChessAction::Resign)],
+      vec![("black", CwChessAction::OfferDraw("a6".to_string())), ("white", CwChessAction::AcceptDraw)],
+      vec![("black", CwChessAction::Resign)],
+      vec![("black", CwChessAction::from("a6")), ("white", CwChessAction::Resign)],
+      vec![("black", CwChessAction::Resign)],
+      vec![("black", CwChessAction::OfferDraw("a6".to_string())), ("white", CwChessAction::AcceptDraw)],
+      vec![("black", CwChessAction::from("a6")), ("white", CwChessAction::Resign)],
+      vec![("black", CwChessAction::Resign)],
+      vec![("black", CwChessAction::Resign)],
+    ];
+    for (i, ending) in endings.iter().enumerate() {
+      let game_id = i as u64 + 1;
+      // a block per game, under the rate limit
+      let env = block_env(mock_env().block.height + game_id);
+      execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("black", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: false,
+          block_limit: None,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          // creator is black
+          play_as: Some(CwChessColor::Black),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("white", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+
+      let opening = ruy_lopez.iter().map(|(player, move_str)| (*player, CwChessAction::from(*move_str)));
+      for (player, action) in opening.chain(ending.iter().cloned()) {
+        execute(
+          deps.as_mut(),
+          env.clone(),
+          mock_info(player, &[]),
+          ExecuteMsg::Turn { action, game_id },
+        )
+        .unwrap();
+      }
+    }
+
+    let stats = from_binary::<OpeningStats>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::OpeningStats {
+          eco_code: "C60".to_string(),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      stats,
+      OpeningStats {
+        black_wins: 2,
+        draws: 2,
+        total_games: 10,
+        white_wins: 6,
+      }
+    );
+
+    let best = from_binary::<Vec<(String, Decimal)>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::BestOpeningsForColor {
+          color: CwChessColor::White,
+          min_games: 10,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(best, vec![("C60".to_string(), Decimal::percent(60))]);
+
+    // not enough games
+    let best = from_binary::<Vec<(String, Decimal)>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::BestOpeningsForColor {
+          color: CwChessColor::Black,
+          min_games: 11,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert!(best.is_empty());
+  }
+
+  // create an env for a specific block height
+  fn block_env(height: u64) -> Env {
+    let mut env = mock_env();
+    env.block.height = height;
+    env
+  }
+
+  #[test]
+  fn test_timeout() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game with timeout
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        // 300 blocks/per person @ ~10 blocks/minute => ~30 minutes/person
+        block_limit: Some(300),
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    // game created at block 100
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // first move, time limit starts
+    execute(
+      deps.as_mut(),
+      block_env(300),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("d4"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(310),
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("d5".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // not a timeout yet (time starts after first move)
+    let response = execute(
+      deps.as_mut(),
+      block_env(500),
+      mock_info("black", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotTimedOut { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      block_env(600),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("c4"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(610),
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("dxc4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    // white timed out
+    let result = execute(
+      deps.as_mut(),
+      block_env(631),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("e3"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(result.attributes[2].key, "status");
+    assert_eq!(result.attributes[2].value.contains("WhiteTimeout"), true);
+  }
+
+  #[test]
+  fn test_migrate_keeps_clock() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: Some(300),
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("white", &[]),
       ExecuteMsg::AcceptChallenge { challenge_id: 1 },
     )
     .unwrap();
+    // black has used 10 blocks and is on move from block 600
+    for (height, player, move_str) in [(300, "white", "d4"), (310, "black", "d5"), (600, "white", "c4")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let get_game = |deps: Deps| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+    let before = get_game(deps.as_ref());
+    let response = migrate(deps.as_mut(), block_env(700), MigrateMsg {}).unwrap();
+    assert_eq!(response.attributes[0].value, "migrate");
+    assert_eq!(get_game(deps.as_ref()), before);
+
+    // black's clock carries on from block 600, 10 + 290 blocks is not a timeout
+    let response = execute(
+      deps.as_mut(),
+      block_env(890),
+      mock_info("white", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotTimedOut { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    execute(
+      deps.as_mut(),
+      block_env(891),
+      mock_info("white", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(get_game(deps.as_ref()).status, Some(CwChessGameOver::BlackTimeout));
 
-    // check game status
-    let game = from_binary::<CwChessGame>(
-      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    // only this contract's state can be migrated
+    cw2::set_contract_version(deps.as_mut().storage, "other-contract", "1.0.0").unwrap();
+    match migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err() {
+      ContractError::WrongContract { contract } => assert_eq!(contract, "other-contract"),
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_migrate_reindexes_games() {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
     )
     .unwrap();
-    assert_eq!(game.player1, "white");
-    assert_eq!(game.player2, "black");
-    assert_eq!(game.status, None);
-    assert_eq!(game.turn_color(), Some(CwChessColor::White));
-
-    // white resigns
     execute(
       deps.as_mut(),
       mock_env(),
-      mock_info("white", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::Resign {},
-        game_id: 1,
+      mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
       },
     )
     .unwrap();
-
-    // game status updated
-    let game = from_binary::<CwChessGame>(
-      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
-    )
-    .unwrap();
-    assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
-
-    // cannot move after game over
-    let response = execute(
+    execute(
       deps.as_mut(),
       mock_env(),
       mock_info("white", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d4".to_string()),
-        game_id: 1,
-      },
-    );
-    match response.unwrap_err() {
-      ContractError::GameAlreadyOver { .. } => {}
-      e => panic!("unexpected error: {:?}", e),
-    }
-  }
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    // a game saved before the indexes existed
+    let mut game = get_games_map().load(&deps.storage, 1).unwrap();
+    game.game_id = 2;
+    Map::<u64, CwChessGame>::new("games").save(&mut deps.storage, 2, &game).unwrap();
 
-  // create an env for a specific block height
-  fn block_env(height: u64) -> Env {
-    let mut env = mock_env();
-    env.block.height = height;
-    env
+    let in_progress = |deps: Deps| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps,
+          mock_env(),
+          QueryMsg::GamesByStatus {
+            status: GameStatus::InProgress,
+            start_after: None,
+            limit: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .into_iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(in_progress(deps.as_ref()), [1]);
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(in_progress(deps.as_ref()), [1, 2]);
+    let active = from_binary::<Option<u64>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ActiveGameBetween {
+          player_a: "white".to_string(),
+          player_b: "black".to_string(),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(active, Some(2));
   }
 
   #[test]
-  fn test_timeout() {
+  fn test_scheduled_game() {
     let mut deps = mock_dependencies();
 
     // initialize
@@ -571,21 +4234,26 @@ mod tests {
       InstantiateMsg {},
     )
     .unwrap();
-    // create game with timeout
+    // round starts at block 1000
     execute(
       deps.as_mut(),
-      mock_env(),
+      block_env(50),
       mock_info("black", &[]),
       ExecuteMsg::CreateChallenge {
-        // 300 blocks/per person @ ~10 blocks/minute => ~30 minutes/person
+        adjudication: false,
+        armageddon: false,
         block_limit: Some(300),
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
         opponent: None,
         // creator is black
         play_as: Some(CwChessColor::Black),
+        start_at: Some(1000),
+        start_fen: None,
       },
     )
     .unwrap();
-    // game created at block 100
     execute(
       deps.as_mut(),
       block_env(100),
@@ -594,10 +4262,35 @@ mod tests {
     )
     .unwrap();
 
-    // first move, time limit starts
+    // no moves or draws before the start
+    let responses = [
+      execute(
+        deps.as_mut(),
+        block_env(999),
+        mock_info("white", &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from("d4"),
+          game_id: 1,
+        },
+      ),
+      execute(
+        deps.as_mut(),
+        block_env(999),
+        mock_info("black", &[]),
+        ExecuteMsg::AgreeDraw { game_id: 1 },
+      ),
+    ];
+    for response in responses {
+      match response.unwrap_err() {
+        ContractError::GameNotStarted { start_at } => assert_eq!(start_at, 1000),
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    // the clock starts with the first move, not when the game was accepted
     execute(
       deps.as_mut(),
-      block_env(300),
+      block_env(1000),
       mock_info("white", &[]),
       ExecuteMsg::Turn {
         action: CwChessAction::from("d4"),
@@ -605,61 +4298,347 @@ mod tests {
       },
     )
     .unwrap();
+    let response = execute(
+      deps.as_mut(),
+      block_env(1300),
+      mock_info("white", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotTimedOut { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
     execute(
       deps.as_mut(),
-      block_env(310),
+      block_env(1301),
+      mock_info("white", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.start_at, Some(1000));
+    assert_eq!(game.status, Some(CwChessGameOver::BlackTimeout));
+  }
+
+  #[test]
+  fn test_armageddon() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    let create_game = |deps: DepsMut, block_limit: Option<u64>| {
+      execute(
+        deps,
+        block_env(10),
+        mock_info("white", &[]),
+        ExecuteMsg::CreateChallenge {
+          adjudication: false,
+          armageddon: true,
+          block_limit,
+          confirm_moves: false,
+          confirm_resign: false,
+          max_plies: None,
+          opponent: None,
+          play_as: Some(CwChessColor::White),
+          start_at: None,
+          start_fen: None,
+        },
+      )
+      .unwrap();
+    };
+    let get_game = |deps: Deps, game_id: u64| {
+      from_binary::<CwChessGame>(&query(deps, mock_env(), QueryMsg::GetGame { game_id }).unwrap())
+        .unwrap()
+    };
+
+    // black has less time
+    create_game(deps.as_mut(), Some(100));
+    execute(
+      deps.as_mut(),
+      block_env(10),
       mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(get_game(deps.as_ref(), 1).block_limits(), Some((100, 80)));
+    execute(
+      deps.as_mut(),
+      block_env(20),
+      mock_info("white", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("d5".to_string()),
+        action: CwChessAction::from("d4"),
         game_id: 1,
       },
     )
     .unwrap();
-
-    // not a timeout yet (time starts after first move)
     let response = execute(
       deps.as_mut(),
-      block_env(500),
-      mock_info("black", &[]),
+      block_env(100),
+      mock_info("white", &[]),
       ExecuteMsg::DeclareTimeout { game_id: 1 },
     );
     match response.unwrap_err() {
       ContractError::GameNotTimedOut { .. } => {}
       e => panic!("unexpected error: {:?}", e),
     }
-
     execute(
       deps.as_mut(),
-      block_env(600),
+      block_env(101),
       mock_info("white", &[]),
-      ExecuteMsg::Turn {
-        action: CwChessAction::from("c4"),
-        game_id: 1,
-      },
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
     )
     .unwrap();
+    assert_eq!(get_game(deps.as_ref(), 1).status, Some(CwChessGameOver::BlackTimeout));
+
+    // a draw is a black win
+    create_game(deps.as_mut(), None);
     execute(
       deps.as_mut(),
-      block_env(610),
+      block_env(200),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    for (player, action) in [("white", "e4"), ("black", "e5")] {
+      execute(
+        deps.as_mut(),
+        block_env(210),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 2,
+        },
+      )
+      .unwrap();
+    }
+    for player in ["white", "black"] {
+      execute(
+        deps.as_mut(),
+        block_env(220),
+        mock_info(player, &[]),
+        ExecuteMsg::AgreeDraw { game_id: 2 },
+      )
+      .unwrap();
+    }
+    assert_eq!(get_game(deps.as_ref(), 2).status, Some(CwChessGameOver::DrawAgreed));
+    let record = from_binary::<GameRecord>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::CompletedGame { game_id: 2 }).unwrap(),
+    )
+    .unwrap();
+    assert!(record.armageddon);
+    assert!(record.pgn.ends_with(" 0-1"));
+    assert!(record.black_elo_after > record.black_elo_before);
+    assert!(record.white_elo_after < record.white_elo_before);
+    let pgn = from_binary::<String>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::ArchivedPgn { game_id: 2 }).unwrap(),
+    )
+    .unwrap();
+    assert!(pgn.contains("[Result \"0-1\"]"));
+  }
+
+  // the notification a subscriber contract receives
+  fn subscriber_update(response: &Response, subscriber: &str) -> SubscriberMsg {
+    let sub_msg = response
+      .messages
+      .iter()
+      .find(|sub_msg| {
+        matches!(&sub_msg.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })
+          if contract_addr == subscriber)
+      })
+      .expect("subscriber not notified");
+    // a failing subscriber must not revert the move
+    assert_eq!(sub_msg.reply_on, ReplyOn::Error);
+    assert_eq!(sub_msg.id, u64::from(ReplyId::SubscriberNotification));
+    match &sub_msg.msg {
+      CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) => {
+        assert!(funds.is_empty());
+        from_binary(msg).unwrap()
+      }
+      msg => panic!("unexpected message: {:?}", msg),
+    }
+  }
+
+  #[test]
+  fn test_subscribe() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {},
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
       mock_info("black", &[]),
+      ExecuteMsg::CreateChallenge {
+        adjudication: false,
+        armageddon: false,
+        block_limit: None,
+        confirm_moves: false,
+        confirm_resign: false,
+        max_plies: None,
+        opponent: None,
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        start_at: None,
+        start_fen: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // every message after this one is sent a block later than the last, the
+    // subscriptions take more messages than the rate limit allows in one block
+    let height = Cell::new(mock_env().block.height);
+    let next_block = || {
+      height.set(height.get() + 1);
+      block_env(height.get())
+    };
+
+    let subscribe = |deps: DepsMut, callback: &str| {
+      execute(
+        deps,
+        next_block(),
+        mock_info("white", &[]),
+        ExecuteMsg::Subscribe {
+          game_id: 1,
+          callback: callback.to_string(),
+        },
+      )
+    };
+
+    // no game
+    let response = execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("white", &[]),
+      ExecuteMsg::Subscribe {
+        game_id: 2,
+        callback: "bot".to_string(),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotFound { game_id: 2 } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // only players subscribe
+    let response = execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("watcher", &[]),
+      ExecuteMsg::Subscribe {
+        game_id: 1,
+        callback: "bot".to_string(),
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    subscribe(deps.as_mut(), "bot").unwrap();
+    match subscribe(deps.as_mut(), "bot").unwrap_err() {
+      ContractError::AlreadySubscribed {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the bot is told about each move
+    let response = execute(
+      deps.as_mut(),
+      next_block(),
+      mock_info("white", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::MakeMove("dxc4".to_string()),
+        action: CwChessAction::MakeMove("e4".to_string()),
         game_id: 1,
       },
     )
     .unwrap();
-    // white timed out
-    let result = execute(
+    assert_eq!(response.messages.len(), 1);
+    assert_eq!(
+      subscriber_update(&response, "bot"),
+      SubscriberMsg::GameUpdate {
+        fen: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 0".to_string(),
+        game_id: 1,
+        last_move_uci: Some("e2e4".to_string()),
+        status: None,
+      }
+    );
+
+    // subscribers are bounded
+    for i in 1..MAX_SUBSCRIBERS {
+      subscribe(deps.as_mut(), &format!("bot{}", i)).unwrap();
+    }
+    match subscribe(deps.as_mut(), "one_too_many").unwrap_err() {
+      ContractError::TooManySubscribers { max } => assert_eq!(max, MAX_SUBSCRIBERS as u64),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // either player removes a subscriber, others cannot
+    let unsubscribe = |deps: DepsMut, sender: &str, callback: &str| {
+      execute(
+        deps,
+        next_block(),
+        mock_info(sender, &[]),
+        ExecuteMsg::Unsubscribe {
+          game_id: 1,
+          callback: callback.to_string(),
+        },
+      )
+    };
+    match unsubscribe(deps.as_mut(), "watcher", "bot1").unwrap_err() {
+      ContractError::Unauthorized {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    unsubscribe(deps.as_mut(), "black", "bot1").unwrap();
+    match unsubscribe(deps.as_mut(), "black", "bot1").unwrap_err() {
+      ContractError::NotSubscribed {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    subscribe(deps.as_mut(), "bot1").unwrap();
+
+    // and told the result
+    let response = execute(
       deps.as_mut(),
-      block_env(631),
-      mock_info("white", &[]),
+      next_block(),
+      mock_info("black", &[]),
       ExecuteMsg::Turn {
-        action: CwChessAction::from("e3"),
+        action: CwChessAction::Resign {},
         game_id: 1,
       },
     )
     .unwrap();
-    assert_eq!(result.attributes[2].key, "status");
-    assert_eq!(result.attributes[2].value.contains("WhiteTimeout"), true);
+    assert_eq!(response.messages.len(), MAX_SUBSCRIBERS);
+    match subscriber_update(&response, "bot3") {
+      SubscriberMsg::GameUpdate { status, .. } => {
+        assert_eq!(status, Some(CwChessGameOver::BlackResigns))
+      }
+    }
+
+    // subscriptions end with the game
+    match subscribe(deps.as_mut(), "late_bot").unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
   }
 }
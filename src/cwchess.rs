@@ -1,6 +1,15 @@
 use crate::error::ContractError;
-use crate::engine::Color;
-use crate::game::{Game, GameAction, GameOver};
+use crate::board::{Board, PieceCounts};
+use crate::engine::{
+  classify_mate, is_forced_mate_in, only_king_can_move, pinned_pieces, Color, Evaluate, MatePattern, Move, Phase,
+};
+use crate::util::rng::Rng;
+use crate::game::adjudication::{auto_adjudicate, AdjudicationConfig};
+use crate::game::notation::{long_algebraic_move_list, numbered_san_list};
+use crate::game::opening::classify;
+use crate::game::{diagnose_illegal_move, Game, GameAction, GameError, GameOver, IllegalMoveReason, TopMove};
+use crate::position::Position;
+use crate::util::{fnv1a_hash, format_fen, format_san_move, parse_fen};
 use cosmwasm_std::Addr;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -21,6 +30,17 @@ impl From<&str> for CwChessAction {
   }
 }
 
+impl CwChessAction {
+  // move in san notation, or the action name when there is no move
+  pub fn notation(&self) -> String {
+    match self {
+      CwChessAction::AcceptDraw => "accept_draw".to_string(),
+      CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) => move_str.clone(),
+      CwChessAction::Resign => "resign".to_string(),
+    }
+  }
+}
+
 impl From<&CwChessAction> for GameAction {
   fn from(action: &CwChessAction) -> GameAction {
     match action {
@@ -63,16 +83,76 @@ pub enum CwChessGameOver {
   // chess_engine_game results
   BlackCheckmates,
   BlackResigns,
+  BlackWinsByAdjudication,
   DrawAccepted,
   DrawDeclared,
   Stalemate,
   WhiteCheckmates,
   WhiteResigns,
+  WhiteWinsByAdjudication,
   // custom results
+  // ruled by the owner, see ExecuteMsg::Adjudicate, no winner is a draw
+  Adjudicated { winner: Option<CwChessColor> },
+  // too many illegal moves in a row, see SetMaxRejectedMoves
+  BlackForfeits,
   BlackTimeout,
+  DrawAgreed,
+  PlyLimit,
+  WhiteForfeits,
   WhiteTimeout,
 }
 
+impl CwChessGameOver {
+  // game termination marker, e.g. "1-0"
+  pub fn pgn_result(&self) -> &'static str {
+    match self {
+      CwChessGameOver::WhiteCheckmates
+      | CwChessGameOver::WhiteWinsByAdjudication
+      | CwChessGameOver::Adjudicated {
+        winner: Some(CwChessColor::White),
+      }
+      | CwChessGameOver::BlackResigns
+      | CwChessGameOver::BlackForfeits
+      | CwChessGameOver::BlackTimeout => "1-0",
+      CwChessGameOver::BlackCheckmates
+      | CwChessGameOver::BlackWinsByAdjudication
+      | CwChessGameOver::Adjudicated {
+        winner: Some(CwChessColor::Black),
+      }
+      | CwChessGameOver::WhiteResigns
+      | CwChessGameOver::WhiteForfeits
+      | CwChessGameOver::WhiteTimeout => "0-1",
+      CwChessGameOver::Adjudicated { winner: None }
+      | CwChessGameOver::DrawAccepted
+      | CwChessGameOver::DrawAgreed
+      | CwChessGameOver::DrawDeclared
+      | CwChessGameOver::PlyLimit
+      | CwChessGameOver::Stalemate => "1/2-1/2",
+    }
+  }
+
+  pub fn is_draw(&self) -> bool {
+    matches!(
+      self,
+      CwChessGameOver::Adjudicated { winner: None }
+        | CwChessGameOver::DrawAccepted
+        | CwChessGameOver::DrawAgreed
+        | CwChessGameOver::DrawDeclared
+        | CwChessGameOver::PlyLimit
+        | CwChessGameOver::Stalemate
+    )
+  }
+
+  // result of the game, in an armageddon game a draw is a black win
+  pub fn game_result(&self, armageddon: bool) -> &'static str {
+    if armageddon && self.is_draw() {
+      "0-1"
+    } else {
+      self.pgn_result()
+    }
+  }
+}
+
 impl From<&GameOver> for CwChessGameOver {
   fn from(status: &GameOver) -> CwChessGameOver {
     match status {
@@ -82,36 +162,613 @@ impl From<&GameOver> for CwChessGameOver {
       GameOver::Stalemate => CwChessGameOver::Stalemate,
       GameOver::WhiteCheckmates => CwChessGameOver::WhiteCheckmates,
       GameOver::WhiteResigns => CwChessGameOver::WhiteResigns,
+      GameOver::WhiteWinsByAdjudication => CwChessGameOver::WhiteWinsByAdjudication,
+      GameOver::BlackWinsByAdjudication => CwChessGameOver::BlackWinsByAdjudication,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessIllegalMove {
+  // custom reasons
+  WrongTurn,
+  // notation reasons
+  AmbiguousMove,
+  GameAlreadyOver,
+  NotAMove,
+  // game reasons
+  NoPiece,
+  NotYourPiece,
+  OwnPieceOnTarget,
+  PieceCannotReach,
+  PathBlocked,
+  LeavesKingInCheck,
+  CastleRightsLost,
+  CastleOutOfCheck,
+  CastleThroughCheck,
+  EnPassantNotAvailable,
+  InvalidPromotion,
+}
+
+impl From<&IllegalMoveReason> for CwChessIllegalMove {
+  fn from(reason: &IllegalMoveReason) -> CwChessIllegalMove {
+    match reason {
+      IllegalMoveReason::NoPiece => CwChessIllegalMove::NoPiece,
+      IllegalMoveReason::NotYourPiece => CwChessIllegalMove::NotYourPiece,
+      IllegalMoveReason::OwnPieceOnTarget => CwChessIllegalMove::OwnPieceOnTarget,
+      IllegalMoveReason::PieceCannotReach => CwChessIllegalMove::PieceCannotReach,
+      IllegalMoveReason::PathBlocked => CwChessIllegalMove::PathBlocked,
+      IllegalMoveReason::LeavesKingInCheck => CwChessIllegalMove::LeavesKingInCheck,
+      IllegalMoveReason::CastleRightsLost => CwChessIllegalMove::CastleRightsLost,
+      IllegalMoveReason::CastleOutOfCheck => CwChessIllegalMove::CastleOutOfCheck,
+      IllegalMoveReason::CastleThroughCheck => CwChessIllegalMove::CastleThroughCheck,
+      IllegalMoveReason::EnPassantNotAvailable => CwChessIllegalMove::EnPassantNotAvailable,
+      IllegalMoveReason::InvalidPromotion => CwChessIllegalMove::InvalidPromotion,
+    }
+  }
+}
+
+impl core::fmt::Display for CwChessIllegalMove {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+    let reason = match self {
+      CwChessIllegalMove::WrongTurn => "not your turn",
+      CwChessIllegalMove::AmbiguousMove => "ambiguous move",
+      CwChessIllegalMove::GameAlreadyOver => "game already over",
+      CwChessIllegalMove::NotAMove => "not a legal move",
+      CwChessIllegalMove::NoPiece => "no piece on that square",
+      CwChessIllegalMove::NotYourPiece => "not your piece",
+      CwChessIllegalMove::OwnPieceOnTarget => "own piece on target square",
+      CwChessIllegalMove::PieceCannotReach => "piece cannot reach that square",
+      CwChessIllegalMove::PathBlocked => "path is blocked",
+      CwChessIllegalMove::LeavesKingInCheck => "king would be in check",
+      CwChessIllegalMove::CastleRightsLost => "king or rook has already moved",
+      CwChessIllegalMove::CastleOutOfCheck => "cannot castle out of check",
+      CwChessIllegalMove::CastleThroughCheck => "cannot castle through check",
+      CwChessIllegalMove::EnPassantNotAvailable => "en passant not available",
+      CwChessIllegalMove::InvalidPromotion => "invalid promotion",
+    };
+    write!(f, "{}", reason)
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessMatePattern {
+  Anastasia,
+  BackRank,
+  Smothered,
+}
+
+impl From<&MatePattern> for CwChessMatePattern {
+  fn from(pattern: &MatePattern) -> CwChessMatePattern {
+    match pattern {
+      MatePattern::Anastasia => CwChessMatePattern::Anastasia,
+      MatePattern::BackRank => CwChessMatePattern::BackRank,
+      MatePattern::Smothered => CwChessMatePattern::Smothered,
+    }
+  }
+}
+
+// legal move with a label for move pickers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessLegalMove {
+  // from/to squares, with the promotion piece appended, e.g. "e7e8n"
+  pub coordinates: String,
+  // san to submit as the move, e.g. "Nbd2" or "e8N+"
+  pub san: String,
+}
+
+// side to move, check and legal moves of any position, no game needed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessFenAnalysis {
+  pub in_check: bool,
+  pub legal_moves: Vec<CwChessLegalMove>,
+  // true in double check or when every other piece is pinned or blocked
+  pub only_king_can_move: bool,
+  // the forced move, for highlighting or auto-playing it
+  pub only_move: Option<CwChessLegalMove>,
+  // (pinned piece, pinning piece) squares of the side to move, e.g. ("c3", "b4")
+  pub pins: Vec<(String, String)>,
+  pub to_move: CwChessColor,
+}
+
+impl CwChessFenAnalysis {
+  pub fn from_fen(fen: &str) -> Result<CwChessFenAnalysis, ContractError> {
+    let board = parse_fen(fen).map_err(|reason| ContractError::InvalidFen { reason })?;
+    validate_position(&board)?;
+    let game = Game::from_fen(fen, None, None).map_err(|_| ContractError::InvalidPosition {})?;
+    let color = board.get_turn_color();
+    Ok(CwChessFenAnalysis {
+      in_check: board.is_in_check(color),
+      legal_moves: game
+        .legal_moves_san()
+        .into_iter()
+        .map(|(coordinates, san)| CwChessLegalMove { coordinates, san })
+        .collect(),
+      only_king_can_move: only_king_can_move(&board, color),
+      only_move: game
+        .only_move_san()
+        .map(|(coordinates, san)| CwChessLegalMove { coordinates, san }),
+      pins: pinned_pieces(&board, color)
+        .into_iter()
+        .map(|(pinned, pinner)| (pinned.to_string(), pinner.to_string()))
+        .collect(),
+      to_move: CwChessColor::from(&color),
+    })
+  }
+}
+
+// can the side to move of any position force mate within `moves` of its
+// own moves, see engine::is_forced_mate_in
+pub fn forced_mate(fen: &str, moves: u8) -> Result<bool, ContractError> {
+  let board = parse_fen(fen).map_err(|reason| ContractError::InvalidFen { reason })?;
+  validate_position(&board)?;
+  Ok(is_forced_mate_in(&board, moves as u32))
+}
+
+// moves of a single piece of either color, as from/to squares
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessPieceMoves {
+  // moves that don't leave the piece's own king in check
+  pub legal: Vec<String>,
+  // moves ignoring the piece's own king's safety
+  pub pseudo_legal: Vec<String>,
+  // false when it isn't the piece's side to move, the moves are then the
+  // ones it would have on its own turn
+  pub to_move: bool,
+}
+
+// early end for games that are clearly won, set by the owner and copied
+// into every game whose challenge opts in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessAdjudication {
+  // plies of lookahead for the losing side, 1 to MAX_ADJUDICATION_DEPTH
+  pub depth: u8,
+  // full moves before adjudication starts
+  pub min_moves: u64,
+  // evaluation lead in centipawns, 1 to MAX_ADJUDICATION_CENTIPAWNS
+  pub threshold_centipawns: u32,
+}
+
+impl Default for CwChessAdjudication {
+  fn default() -> Self {
+    let default = AdjudicationConfig::default();
+    CwChessAdjudication {
+      depth: default.depth as u8,
+      min_moves: default.min_moves,
+      threshold_centipawns: default.threshold_centipawns as u32,
+    }
+  }
+}
+
+impl From<&CwChessAdjudication> for AdjudicationConfig {
+  fn from(adjudication: &CwChessAdjudication) -> AdjudicationConfig {
+    AdjudicationConfig {
+      threshold_centipawns: adjudication.threshold_centipawns.min(MAX_ADJUDICATION_CENTIPAWNS) as i32,
+      depth: adjudication.depth.min(MAX_ADJUDICATION_DEPTH) as i32,
+      min_moves: adjudication.min_moves,
+    }
+  }
+}
+
+// static evaluation in centipawns from white's point of view
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessEvaluation {
+  pub king_safety: i32,
+  pub material: i32,
+  pub mobility: i32,
+  pub pawn_structure: i32,
+  // game phase, from 24 with every piece on the board down to 0, not a score
+  pub phase: u8,
+  pub piece_square: i32,
+  // sum of the centipawn components above, phase is not one of them
+  pub total_centipawns: i32,
+}
+
+impl CwChessEvaluation {
+  pub fn from_fen(fen: &str) -> Result<CwChessEvaluation, ContractError> {
+    let board = parse_fen(fen).map_err(|reason| ContractError::InvalidFen { reason })?;
+    let evaluation = board.evaluate_detailed();
+    Ok(CwChessEvaluation {
+      king_safety: evaluation.king_safety,
+      material: evaluation.material,
+      mobility: evaluation.mobility,
+      pawn_structure: evaluation.pawn_structure,
+      phase: board.get_phase_value(),
+      piece_square: evaluation.piece_square,
+      total_centipawns: evaluation.total(),
+    })
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessPhase {
+  Opening,
+  Middlegame,
+  Endgame,
+}
+
+impl From<&Phase> for CwChessPhase {
+  fn from(phase: &Phase) -> CwChessPhase {
+    match phase {
+      Phase::Opening => CwChessPhase::Opening,
+      Phase::Middlegame => CwChessPhase::Middlegame,
+      Phase::Endgame => CwChessPhase::Endgame,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessPieceCounts {
+  pub pawns: u8,
+  pub knights: u8,
+  pub bishops: u8,
+  pub rooks: u8,
+  pub queens: u8,
+}
+
+impl From<&PieceCounts> for CwChessPieceCounts {
+  fn from(counts: &PieceCounts) -> CwChessPieceCounts {
+    CwChessPieceCounts {
+      pawns: counts.pawns,
+      knights: counts.knights,
+      bishops: counts.bishops,
+      rooks: counts.rooks,
+      queens: counts.queens,
     }
   }
 }
 
+// white's count minus black's for each piece type
+// e.g. knights 1 and bishops -1 when white has a knight for a bishop
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessPieceDifference {
+  pub pawns: i8,
+  pub knights: i8,
+  pub bishops: i8,
+  pub rooks: i8,
+  pub queens: i8,
+}
+
+impl CwChessPieceDifference {
+  pub fn between(white: &CwChessPieceCounts, black: &CwChessPieceCounts) -> CwChessPieceDifference {
+    let difference = |white: u8, black: u8| white as i8 - black as i8;
+    CwChessPieceDifference {
+      pawns: difference(white.pawns, black.pawns),
+      knights: difference(white.knights, black.knights),
+      bishops: difference(white.bishops, black.bishops),
+      rooks: difference(white.rooks, black.rooks),
+      queens: difference(white.queens, black.queens),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessMaterialImbalance {
+  pub black: CwChessPieceCounts,
+  pub net: CwChessPieceDifference,
+  pub white: CwChessPieceCounts,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessGamePhase {
+  pub black: CwChessPieceCounts,
+  pub phase: CwChessPhase,
+  // 24 with all non-pawn material down to 0 with kings and pawns only
+  pub phase_value: u8,
+  pub white: CwChessPieceCounts,
+}
+
+// what a move would do, worked out before it is played
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CwChessMovePreview {
+  // e.g. "promotion e7 e8 queen"
+  pub events: Vec<String>,
+  // the move would stalemate the opponent, e.g. in a won endgame
+  pub stalemate_risk: bool,
+}
+
 pub type CwChessMove = (u64, CwChessAction);
 
+// static evaluation a side must be behind by before resigning is suggested
+// a queen is 900, so a little less allows for piece placement
+pub const RESIGN_THRESHOLD_CENTIPAWNS: i32 = 800;
+
+// standard starting position
+pub const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// a variant this build can play
+pub struct CwChessVariant {
+  // clients use the id to tell variants apart
+  pub id: &'static str,
+  pub name: &'static str,
+  pub start_fen: &'static str,
+}
+
+// armageddon starts from the standard position, see Challenge::armageddon
+pub const VARIANTS: [CwChessVariant; 2] = [
+  CwChessVariant {
+    id: "standard",
+    name: "Standard",
+    start_fen: DEFAULT_FEN,
+  },
+  CwChessVariant {
+    id: "armageddon",
+    name: "Armageddon",
+    start_fen: DEFAULT_FEN,
+  },
+];
+
+// check a custom start position and rewrite it with reset move counters
+// None for the standard position
+// white must move first, turns are counted from the moves list
+pub fn normalize_start_fen(fen: &str) -> Result<Option<String>, ContractError> {
+  let board = parse_fen(fen).map_err(|reason| ContractError::InvalidFen { reason })?;
+  if board.get_turn_color() != Color::White {
+    return Err(ContractError::InvalidFen {
+      reason: "white must move first".to_string(),
+    });
+  }
+  validate_position(&board)?;
+  let fen = format_fen(&board, 0, 1).map_err(|reason| ContractError::InvalidFen { reason })?;
+  Ok(Some(fen).filter(|fen| fen != DEFAULT_FEN))
+}
+
+// checks a start position can be played from
+fn validate_position(board: &Board) -> Result<(), ContractError> {
+  let invalid = |reason: &str| {
+    Err(ContractError::InvalidFen {
+      reason: reason.to_string(),
+    })
+  };
+  for color in [Color::White, Color::Black] {
+    let kings = (0..64)
+      .filter_map(|i| board.get_piece(Position::new(i / 8, i % 8)))
+      .filter(|piece| piece.is_king() && piece.get_color() == color)
+      .count();
+    if kings != 1 {
+      return invalid("each side needs one king");
+    }
+  }
+  for col in 0..8 {
+    for row in [0, 7] {
+      if matches!(board.get_piece(Position::new(row, col)), Some(piece) if piece.is_pawn()) {
+        return invalid("pawn on first or last rank");
+      }
+    }
+  }
+  if board.is_in_check(!board.get_turn_color()) {
+    return invalid("side not to move is in check");
+  }
+  Ok(())
+}
+
+// replay actions from start_fen, e.g. to check a game before importing it
+// fails with the index of the first action that can't be played
+// an unusable start position is reported at index 0
+pub fn validate_game(start_fen: &str, actions: &[CwChessAction]) -> Result<(), (usize, ContractError)> {
+  let board = parse_fen(start_fen).map_err(|reason| (0, ContractError::InvalidFen { reason }))?;
+  validate_position(&board).map_err(|error| (0, error))?;
+  let mut game = Game::from_fen(start_fen, None, None).map_err(|_| (0, ContractError::InvalidPosition {}))?;
+  for (i, action) in actions.iter().enumerate() {
+    if let Err(error) = game.make_move(&GameAction::from(action)) {
+      return Err((i, illegal_move(&game, &action.notation(), error)));
+    }
+  }
+  Ok(())
+}
+
+// a draft challenge starts from an empty board
+pub const EMPTY_FEN: &str = "8/8/8/8/8/8/8/8 w - - 0 1";
+
+// fen with `square` set to `piece`, a FEN letter, or emptied when None
+// only the placement is changed, the board need not be playable yet
+pub fn set_square(fen: &str, square: &str, piece: Option<char>) -> Result<String, ContractError> {
+  let pos = Position::pgn(square).map_err(|_| ContractError::InvalidPosition {})?;
+  if let Some(piece) = piece {
+    if !"KQRBNPkqrbnp".contains(piece) {
+      return Err(ContractError::InvalidFen {
+        reason: "unexpected piece".to_string(),
+      });
+    }
+  }
+  let (placement, rest) = fen.split_once(' ').unwrap_or((fen, ""));
+  // ranks from 8 down to 1, files a to h
+  let mut squares: Vec<Vec<Option<char>>> = placement
+    .split('/')
+    .map(|rank| {
+      rank
+        .chars()
+        .flat_map(|c| match c.to_digit(10) {
+          Some(empty) => vec![None; empty as usize],
+          None => vec![Some(c)],
+        })
+        .collect()
+    })
+    .collect();
+  let (rank, file) = ((7 - pos.get_row()) as usize, pos.get_col() as usize);
+  match squares.get_mut(rank).and_then(|rank| rank.get_mut(file)) {
+    Some(square) => *square = piece,
+    None => {
+      return Err(ContractError::InvalidFen {
+        reason: "incomplete position".to_string(),
+      })
+    }
+  }
+
+  let placement = squares
+    .iter()
+    .map(|rank| {
+      let mut fen_rank = String::new();
+      let mut empty = 0;
+      for square in rank {
+        match square {
+          Some(piece) => {
+            if empty > 0 {
+              fen_rank.push_str(&empty.to_string());
+              empty = 0;
+            }
+            fen_rank.push(*piece);
+          }
+          None => empty += 1,
+        }
+      }
+      if empty > 0 {
+        fen_rank.push_str(&empty.to_string());
+      }
+      fen_rank
+    })
+    .collect::<Vec<_>>()
+    .join("/");
+  Ok(format!("{} {}", placement, rest))
+}
+
+// hash of a normalized start fen, None is the standard position
+pub fn start_position_hash(start_fen: Option<&str>) -> u64 {
+  fnv1a_hash(start_fen.unwrap_or(DEFAULT_FEN).as_bytes())
+}
+
+// deepest search the adjudication gate runs after a move
+pub const MAX_ADJUDICATION_DEPTH: u8 = 3;
+
+// largest adjudication threshold, well past any lead in material alone
+pub const MAX_ADJUDICATION_CENTIPAWNS: u32 = 5000;
+
+// ply cap for games created without max_plies, bounds the moves list
+pub const DEFAULT_MAX_PLIES: u64 = 600;
+
+// blocks a player has to confirm a proposed move, see CwChessGame::confirm_moves
+pub const MOVE_CONFIRM_BLOCKS: u64 = 10;
+
+// blocks a player has to confirm a resignation, see CwChessGame::confirm_resign
+pub const RESIGN_CONFIRM_BLOCKS: u64 = 10;
+
+// black's share of the block limit in an armageddon game, white gets all of it
+pub const ARMAGEDDON_BLACK_TIME_PERCENT: u64 = 80;
+
+// plies the engine searches past the phantom's move in a practice game
+pub const PHANTOM_SEARCH_DEPTH: i32 = 1;
+
+// fixed-rating opponent of a practice game, it has no address
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessPhantom {
+  pub color: CwChessColor,
+  pub rating: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct CwChessGame {
+  // game is adjudicated when clearly won, disabled when None
+  pub adjudication: Option<CwChessAdjudication>,
+  // white must win, a draw counts as a black win
+  // black's block limit is ARMAGEDDON_BLACK_TIME_PERCENT of block_limit
+  #[serde(default)]
+  pub armageddon: bool,
+  // black agreed to draw, cleared when black moves
+  #[serde(default)]
+  pub black_agrees_draw: bool,
+  // illegal moves black sent in a row, reset by a legal move
+  #[serde(default)]
+  pub black_rejected_moves: u64,
   // per player block limit for all moves
   // starts at first move (not game start_height)
   pub block_limit: Option<u64>,
   // when game was created
   pub block_start: u64,
+  // a move only goes into pending_move, the player confirms it with
+  // ConfirmMove within MOVE_CONFIRM_BLOCKS
+  #[serde(default)]
+  pub confirm_moves: bool,
+  // a Resign only marks the resignation pending, a second Resign within
+  // RESIGN_CONFIRM_BLOCKS makes it final
+  #[serde(default)]
+  pub confirm_resign: bool,
   // board position in FEN
   // cheaper to load board than executing moves
   pub fen: String,
   // game id
   pub game_id: u64,
+  // from/to squares of the last move played on the board, e.g. "e2e4"
+  pub last_move_uci: Option<String>,
+  // known checkmate pattern of the final move, e.g. back rank
+  pub mate_pattern: Option<CwChessMatePattern>,
+  // game is drawn once this many moves are played
+  // DEFAULT_MAX_PLIES when None
+  pub max_plies: Option<u64>,
   // list of moves
   pub moves: Vec<CwChessMove>,
+  // practice game opponent, the engine moves for it
+  // and only the player's rating changes
+  pub phantom: Option<CwChessPhantom>,
   // player1 is white
   pub player1: Addr,
   // player2 is black
   pub player2: Addr,
+  // move proposed in a confirm_moves game, as (player, action, expires_at)
+  // ignored after expires_at, cleared when a move is played
+  #[serde(default)]
+  pub pending_move: Option<(Addr, CwChessAction, u64)>,
+  // block height of an unconfirmed Resign, cleared when the player moves
+  #[serde(default)]
+  pub resign_pending_at: Option<u64>,
+  // no moves before this block height, the clock starts at the first move
+  pub start_at: Option<u64>,
+  // position the game started from, DEFAULT_FEN when None
+  pub start_fen: Option<String>,
   // status is None while game is being played
   pub status: Option<CwChessGameOver>,
+  // white agreed to draw, cleared when white moves
+  #[serde(default)]
+  pub white_agrees_draw: bool,
+  // illegal moves white sent in a row, reset by a legal move
+  #[serde(default)]
+  pub white_rejected_moves: u64,
+}
+
+// error for a move the game rejected, with the reason it is not legal
+pub(crate) fn illegal_move(game: &Game, notation: &str, error: GameError) -> ContractError {
+  let reason = match error {
+    GameError::AmbiguousMove => CwChessIllegalMove::AmbiguousMove,
+    GameError::GameAlreadyOver => CwChessIllegalMove::GameAlreadyOver,
+    GameError::InvalidMove | GameError::InvalidPosition => diagnose_illegal_move(game, notation)
+      .as_ref()
+      .map(CwChessIllegalMove::from)
+      .unwrap_or(CwChessIllegalMove::NotAMove),
+  };
+  ContractError::IllegalMove {
+    notation: notation.to_string(),
+    reason,
+  }
 }
 
 impl CwChessGame {
+  pub fn already_over(&self) -> ContractError {
+    ContractError::GameAlreadyOver {
+      game_id: self.game_id,
+      status: self.status.as_ref().map(|status| format!("{:?}", status)).unwrap_or_default(),
+    }
+  }
+
+  fn not_your_turn(&self, player: &Addr) -> ContractError {
+    ContractError::NotYourTurn {
+      game_id: self.game_id,
+      player: player.to_string(),
+    }
+  }
+
   // check if game timed out based on block_time_limit
   pub fn check_timeout(
     &mut self,
@@ -119,15 +776,15 @@ impl CwChessGame {
   ) -> Result<&Option<CwChessGameOver>, ContractError> {
     // check if game already over
     if self.status.is_some() {
-      return Err(ContractError::GameAlreadyOver {});
+      return Err(self.already_over());
     }
-    self.status = match self.block_limit {
+    self.status = match self.block_limits() {
       None => None,
-      Some(block_time_limit) => {
+      Some((white_limit, black_limit)) => {
         let block_times = self.get_block_times(current_block);
-        if block_times.0 > block_time_limit {
+        if block_times.0 > white_limit {
           Some(CwChessGameOver::WhiteTimeout {})
-        } else if block_times.1 > block_time_limit {
+        } else if block_times.1 > black_limit {
           Some(CwChessGameOver::BlackTimeout {})
         } else {
           None
@@ -137,17 +794,80 @@ impl CwChessGame {
     Ok(&self.status)
   }
 
+  // (white, black) block limits, black has less time in armageddon
+  pub fn block_limits(&self) -> Option<(u64, u64)> {
+    self.block_limit.map(|block_limit| {
+      if self.armageddon {
+        (block_limit, block_limit * ARMAGEDDON_BLACK_TIME_PERCENT / 100)
+      } else {
+        (block_limit, block_limit)
+      }
+    })
+  }
+
+  // count an illegal move from the player to move, who forfeits once they
+  // send more than max_rejected_moves in a row
+  pub fn reject_move(&mut self, max_rejected_moves: u64) -> &Option<CwChessGameOver> {
+    let (rejected, forfeit) = match self.turn_color() {
+      Some(CwChessColor::White) => (&mut self.white_rejected_moves, CwChessGameOver::WhiteForfeits),
+      Some(CwChessColor::Black) => (&mut self.black_rejected_moves, CwChessGameOver::BlackForfeits),
+      None => return &self.status,
+    };
+    *rejected += 1;
+    if *rejected > max_rejected_moves {
+      self.status = Some(forfeit);
+    }
+    &self.status
+  }
+
+  // scheduled games cannot be played before start_at
+  pub fn check_started(&self, current_block: u64) -> Result<(), ContractError> {
+    match self.start_at {
+      Some(start_at) if current_block < start_at => Err(ContractError::GameNotStarted { start_at }),
+      _ => Ok(()),
+    }
+  }
+
+  // record player's agreement to draw, the game is drawn once both agree
+  // in a practice game the player agrees for both sides
+  pub fn agree_draw(
+    &mut self,
+    player: &Addr,
+    current_block: u64,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    if self.phantom.is_some() {
+      return Err(ContractError::PhantomDraw {});
+    }
+    if *player != self.player1 && *player != self.player2 {
+      return Err(ContractError::Unauthorized {});
+    }
+    self.check_started(current_block)?;
+    if self.check_timeout(current_block)?.is_some() {
+      return Ok(&self.status);
+    }
+    if *player == self.player1 {
+      self.white_agrees_draw = true;
+    }
+    if *player == self.player2 {
+      self.black_agrees_draw = true;
+    }
+    if self.white_agrees_draw && self.black_agrees_draw {
+      self.status = Some(CwChessGameOver::DrawAgreed);
+    }
+    Ok(&self.status)
+  }
+
   pub fn get_player_order(
     player1: Addr,
     player2: Addr,
     play_as: Option<CwChessColor>,
-    height: u64,
+    seed: u64,
   ) -> (Addr, Addr) {
     match play_as {
       Some(CwChessColor::White) => (player1, player2),
       Some(CwChessColor::Black) => (player2, player1),
       None => {
-        if height % 2 == 0 {
+        if Rng::new(seed).next_range(2) == 0 {
           (player1, player2)
         } else {
           (player2, player1)
@@ -157,7 +877,8 @@ impl CwChessGame {
   }
 
   pub fn load_game(&self) -> Result<Game, ContractError> {
-    match Game::from_fen(
+    match Game::from_starting_fen(
+      self.start_fen.as_deref(),
       &self.fen,
       self.draw_offered().as_ref().map(Color::from),
       None,
@@ -175,7 +896,7 @@ impl CwChessGame {
   ) -> Result<bool, ContractError> {
     // check if game already over
     if self.status.is_some() {
-      return Err(ContractError::GameAlreadyOver {});
+      return Err(self.already_over());
     }
 
     // validate the move
@@ -186,13 +907,171 @@ impl CwChessGame {
       Color::Black => &self.player2,
     };
     if player_to_move != player {
-      return Err(ContractError::NotYourTurn {});
+      return Err(self.not_your_turn(player));
     }
 
     match game.valid_move(move_str) {
       Ok(valid) => Ok(valid),
-      Err(_) => Err(ContractError::InvalidMove {}),
+      Err(error) => Err(illegal_move(&game, move_str, error)),
+    }
+  }
+
+  /// legal moves for the side to move as from/to squares, e.g. "e2e4"
+  /// squares are relative to `perspective` when given
+  /// only moves of the piece on `square` are listed when given
+  pub fn legal_moves(
+    &self,
+    perspective: Option<CwChessColor>,
+    square: Option<String>,
+  ) -> Result<Vec<String>, ContractError> {
+    if self.status.is_some() {
+      return Ok(vec![]);
     }
+    let game = self.load_game()?;
+    let color = perspective.as_ref().map(Color::from).unwrap_or(Color::White);
+    let moves = match square {
+      Some(square) => match Position::pgn(&square) {
+        Ok(pos) => game.legal_moves_from(pos.relative_to(color)),
+        Err(_) => return Err(ContractError::InvalidPosition {}),
+      },
+      None => game.legal_moves(),
+    };
+
+    Ok(
+      moves
+        .iter()
+        .map(|(from, to)| format!("{}{}", from.relative_to(color), to.relative_to(color)))
+        .collect(),
+    )
+  }
+
+  /// pseudo-legal and legal moves for the piece on `square`, whichever
+  /// side it belongs to, for analysis
+  pub fn piece_moves(&self, square: &str) -> Result<CwChessPieceMoves, ContractError> {
+    let pos = Position::pgn(square).map_err(|_| ContractError::InvalidPosition {})?;
+    let game = self.load_game()?;
+    let to_move = game.board.get_piece(pos).map(|piece| piece.get_color()) == Some(game.get_turn_color());
+    let (pseudo_legal, legal) = game.piece_moves(pos);
+    let squares = |moves: Vec<(Position, Position)>| -> Vec<String> {
+      moves.iter().map(|(from, to)| format!("{}{}", from, to)).collect()
+    };
+
+    Ok(CwChessPieceMoves {
+      legal: squares(legal),
+      pseudo_legal: squares(pseudo_legal),
+      to_move,
+    })
+  }
+
+  /// legal moves for the side to move with their san
+  pub fn legal_moves_san(&self) -> Result<Vec<CwChessLegalMove>, ContractError> {
+    if self.status.is_some() {
+      return Ok(vec![]);
+    }
+    let game = self.load_game()?;
+
+    Ok(
+      game
+        .legal_moves_san()
+        .into_iter()
+        .map(|(coordinates, san)| CwChessLegalMove { coordinates, san })
+        .collect(),
+    )
+  }
+
+  /// explain why a from/to move is illegal for the player
+  /// returns None when the move is legal
+  pub fn explain_move(
+    &self,
+    player: &Addr,
+    from: &str,
+    to: &str,
+  ) -> Result<Option<CwChessIllegalMove>, ContractError> {
+    // check if game already over
+    if self.status.is_some() {
+      return Err(self.already_over());
+    }
+
+    let game = self.load_game()?;
+
+    let player_to_move = match game.get_turn_color() {
+      Color::White => &self.player1,
+      Color::Black => &self.player2,
+    };
+    if player_to_move != player {
+      return Ok(Some(CwChessIllegalMove::WrongTurn));
+    }
+
+    match game.explain_move(from, to) {
+      Ok(reason) => Ok(reason.as_ref().map(CwChessIllegalMove::from)),
+      Err(error) => Err(illegal_move(&game, &format!("{}{}", from, to), error)),
+    }
+  }
+
+  /// events and stalemate risk of the action's move, from one load of the game
+  /// empty for actions without a move
+  pub fn preview_move(&self, action: &CwChessAction) -> Result<CwChessMovePreview, ContractError> {
+    let move_str = match action {
+      CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) => move_str,
+      _ => return Ok(CwChessMovePreview::default()),
+    };
+    let game = self.load_game()?;
+    let events = game
+      .move_events(move_str)
+      .map_err(|error| illegal_move(&game, move_str, error))?;
+    let stalemate_risk = game
+      .stalemate_risk(move_str)
+      .map_err(|error| illegal_move(&game, move_str, error))?;
+    Ok(CwChessMovePreview {
+      events: events.iter().map(|event| event.to_string()).collect(),
+      stalemate_risk,
+    })
+  }
+
+  /// piece counts and stage of the game for the current position
+  pub fn game_phase(&self) -> Result<CwChessGamePhase, ContractError> {
+    let board = self.load_game()?.board;
+
+    Ok(CwChessGamePhase {
+      black: CwChessPieceCounts::from(&board.get_piece_counts(Color::Black)),
+      phase: CwChessPhase::from(&board.get_phase()),
+      phase_value: board.get_phase_value(),
+      white: CwChessPieceCounts::from(&board.get_piece_counts(Color::White)),
+    })
+  }
+
+  /// pieces of each type on the board for both sides, and the difference
+  pub fn material_imbalance(&self) -> Result<CwChessMaterialImbalance, ContractError> {
+    let board = self.load_game()?.board;
+    let white = CwChessPieceCounts::from(&board.get_piece_counts(Color::White));
+    let black = CwChessPieceCounts::from(&board.get_piece_counts(Color::Black));
+
+    Ok(CwChessMaterialImbalance {
+      net: CwChessPieceDifference::between(&white, &black),
+      black,
+      white,
+    })
+  }
+
+  /// n best moves for the side to move with depth moves of lookahead
+  pub fn top_moves(&self, depth: u8, n: u8) -> Result<Vec<TopMove>, ContractError> {
+    if self.status.is_some() {
+      return Ok(vec![]);
+    }
+    Ok(self.load_game()?.top_moves(depth, n))
+  }
+
+  /// advisory only: color is hopelessly lost by static evaluation,
+  /// e.g. down a queen with nothing for it
+  pub fn should_resign(&self, color: &CwChessColor) -> Result<bool, ContractError> {
+    if self.status.is_some() {
+      return Ok(false);
+    }
+    let color = match color {
+      CwChessColor::White => Color::White,
+      CwChessColor::Black => Color::Black,
+    };
+    Ok(self.load_game()?.board.value_for(color) <= -RESIGN_THRESHOLD_CENTIPAWNS)
   }
 
   // get dirty state
@@ -221,32 +1100,151 @@ impl CwChessGame {
     player: &Addr,
     chess_move: CwChessMove,
   ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let mut game = match self.load_turn(player, chess_move.0)? {
+      Some(game) => game,
+      // check_timeout updates and returns status
+      None => return Ok(&self.status),
+    };
+    let color = game.get_turn_color();
+    // first Resign, or one after the window closed, only asks for confirmation
+    if self.confirm_resign && chess_move.1 == CwChessAction::Resign {
+      match self.resign_pending_at {
+        Some(pending_at) if chess_move.0 <= pending_at + RESIGN_CONFIRM_BLOCKS => {}
+        _ => {
+          self.resign_pending_at = Some(chess_move.0);
+          return Ok(&self.status);
+        }
+      }
+    }
+    match game.make_move(&GameAction::from(&chess_move.1)) {
+      Err(error) => Err(illegal_move(&game, &chess_move.1.notation(), error)),
+      Ok(status) => {
+        self.pending_move = None;
+        self.resign_pending_at = None;
+        // moving withdraws the mover's agreement to draw
+        match color {
+          Color::White => {
+            self.white_agrees_draw = false;
+            self.white_rejected_moves = 0;
+          }
+          Color::Black => {
+            self.black_agrees_draw = false;
+            self.black_rejected_moves = 0;
+          }
+        }
+        self.moves.push(chess_move);
+        self.status = status.as_ref().map(CwChessGameOver::from);
+        if let Some(last_move_uci) = game.last_move_uci() {
+          self.last_move_uci = Some(last_move_uci);
+        }
+        if let Some(CwChessGameOver::WhiteCheckmates | CwChessGameOver::BlackCheckmates) = self.status {
+          self.mate_pattern = game
+            .history
+            .last()
+            .and_then(|(board, mating_move)| classify_mate(board, *mating_move))
+            .as_ref()
+            .map(CwChessMatePattern::from);
+        }
+        if self.status.is_none()
+          && self.ply_count() as u64 >= self.max_plies.unwrap_or(DEFAULT_MAX_PLIES)
+        {
+          self.status = Some(CwChessGameOver::PlyLimit);
+        }
+        if let (None, Some(adjudication)) = (&self.status, &self.adjudication) {
+          let moves = (self.ply_count() / 2) as u64;
+          self.status = auto_adjudicate(&game, &AdjudicationConfig::from(adjudication), moves)
+            .as_ref()
+            .map(CwChessGameOver::from);
+        }
+        self.fen = game.to_fen(0, (self.moves.len() / 2) as u8).unwrap();
+        Ok(&self.status)
+      }
+    }
+  }
+
+  // the game for player's move at current_block, after the checks shared by
+  // make_move and propose_move, or None once check_timeout has ended the game
+  fn load_turn(&mut self, player: &Addr, current_block: u64) -> Result<Option<Game>, ContractError> {
     // check if game already over
     if self.status.is_some() {
-      return Err(ContractError::GameAlreadyOver {});
+      return Err(self.already_over());
     }
+    self.check_started(current_block)?;
     // check if game timed out
-    if self.check_timeout(chess_move.0)?.is_some() {
-      // check_timeout updates and returns status
-      return Ok(&self.status);
+    if self.check_timeout(current_block)?.is_some() {
+      return Ok(None);
     }
-    let mut game = self.load_game()?;
+    let game = self.load_game()?;
     let player_to_move = match game.get_turn_color() {
       Color::White => &self.player1,
       Color::Black => &self.player2,
     };
     if player_to_move != player {
-      return Err(ContractError::NotYourTurn {});
+      return Err(self.not_your_turn(player));
     }
-    match game.make_move(&GameAction::from(&chess_move.1)) {
-      Err(_) => Err(ContractError::InvalidMove {}),
-      Ok(status) => {
-        self.moves.push(chess_move);
-        self.status = status.as_ref().map(CwChessGameOver::from);
-        self.fen = game.to_fen(0, (self.moves.len() / 2) as u8).unwrap();
-        Ok(&self.status)
-      }
+    Ok(Some(game))
+  }
+
+  // check chess_move as make_move would and hold it in pending_move for
+  // the player to confirm, replacing any earlier proposal
+  // only a timeout changes the game
+  pub fn propose_move(
+    &mut self,
+    player: &Addr,
+    chess_move: CwChessMove,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let (height, action) = chess_move;
+    let mut game = match self.load_turn(player, height)? {
+      Some(game) => game,
+      None => return Ok(&self.status),
+    };
+    // played on the loaded game only, to check it is legal
+    if let Err(error) = game.make_move(&GameAction::from(&action)) {
+      return Err(illegal_move(&game, &action.notation(), error));
+    }
+    self.pending_move = Some((player.clone(), action, height + MOVE_CONFIRM_BLOCKS));
+    Ok(&self.status)
+  }
+
+  // the player of a practice game only moves their own side, and the
+  // phantom never agrees to a draw
+  pub fn check_practice_action(
+    &self,
+    player: &Addr,
+    action: &CwChessAction,
+  ) -> Result<(), ContractError> {
+    let phantom = match (&self.phantom, &self.status) {
+      (Some(phantom), None) => phantom,
+      _ => return Ok(()),
+    };
+    if matches!(action, CwChessAction::AcceptDraw | CwChessAction::OfferDraw(_)) {
+      return Err(ContractError::PhantomDraw {});
+    }
+    if self.turn_color().as_ref() == Some(&phantom.color) {
+      return Err(self.not_your_turn(player));
+    }
+    Ok(())
+  }
+
+  // play the engine's move for the phantom when it is to move, searched
+  // PHANTOM_SEARCH_DEPTH plies past the move
+  pub fn play_phantom(&mut self, current_block: u64) -> Result<Option<CwChessAction>, ContractError> {
+    let phantom_to_move = match (&self.phantom, &self.status) {
+      (Some(phantom), None) => self.turn_color().as_ref() == Some(&phantom.color),
+      _ => false,
+    };
+    if !phantom_to_move {
+      return Ok(None);
     }
+    let board = self.load_game()?.board;
+    let action = match board.get_best_next_move(PHANTOM_SEARCH_DEPTH) {
+      (Move::Resign, _, _) => return Ok(None),
+      (best, _, _) => CwChessAction::MakeMove(format_san_move(&board, best)),
+    };
+    // the player is on both sides, see execute_create_practice
+    let player = self.player1.clone();
+    self.make_move(&player, (current_block, action.clone()))?;
+    Ok(Some(action))
   }
 
   pub fn turn_color(&self) -> Option<CwChessColor> {
@@ -261,6 +1259,70 @@ impl CwChessGame {
     }
   }
 
+  // key for finding games that started from the same position
+  pub fn start_position_hash(&self) -> u64 {
+    start_position_hash(self.start_fen.as_deref())
+  }
+
+  // number of moves played on the board
+  pub fn ply_count(&self) -> usize {
+    self.board_moves().count()
+  }
+
+  // eco code of the opening the game started with, if it is a known line
+  // None for games from a custom start position
+  pub fn opening_eco_code(&self) -> Option<String> {
+    if self.start_fen.is_some() {
+      return None;
+    }
+    classify(self.board_moves().map(|move_str| move_str.as_str())).map(|opening| opening.eco.to_string())
+  }
+
+  // movetext in PGN style, the moves in san with move numbers
+  pub fn pgn(&self) -> String {
+    // the moves were legal when played, so replaying them doesn't fail
+    let mut pgn = self.replayed_game().map(|game| numbered_san_list(&game)).unwrap_or_default();
+    if !pgn.is_empty() {
+      pgn.push(' ');
+    }
+    pgn.push_str(
+      self.status.as_ref().map(|status| status.game_result(self.armageddon)).unwrap_or("*"),
+    );
+    pgn
+  }
+
+  // every move played on the board as from/to squares, e.g. "e7e8n"
+  pub fn uci_moves(&self) -> Result<Vec<String>, ContractError> {
+    Ok(long_algebraic_move_list(&self.replayed_game()?))
+  }
+
+  // start position and every move played on the board, packed
+  // see game::pack_move
+  pub fn replay(&self) -> Result<(String, Vec<u16>), ContractError> {
+    let start_fen = self.start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string());
+    Ok((start_fen, self.replayed_game()?.packed_moves()))
+  }
+
+  // game from the start position with its history, unlike load_game
+  fn replayed_game(&self) -> Result<Game, ContractError> {
+    let start_fen = self.start_fen.as_deref().unwrap_or(DEFAULT_FEN);
+    let mut game = Game::from_fen(start_fen, None, None).map_err(|_| ContractError::InvalidPosition {})?;
+    for move_str in self.board_moves() {
+      if let Err(error) = game.make_move(&GameAction::from(move_str.as_str())) {
+        return Err(illegal_move(&game, move_str, error));
+      }
+    }
+    Ok(game)
+  }
+
+  // moves that changed the board, skipping resigns and draw accepts
+  fn board_moves(&self) -> impl Iterator<Item = &String> {
+    self.moves.iter().filter_map(|(_, action)| match action {
+      CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) => Some(move_str),
+      _ => None,
+    })
+  }
+
   // check whether draw was offered on previous turn
   // return color that offered draw
   fn draw_offered(&self) -> Option<CwChessColor> {
@@ -301,3 +1363,61 @@ impl CwChessGame {
     block_times
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn actions(moves: &[&str]) -> Vec<CwChessAction> {
+    moves.iter().map(|move_str| CwChessAction::from(*move_str)).collect()
+  }
+
+  #[test]
+  fn test_validate_game() {
+    // scholar's mate
+    let moves = actions(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7"]);
+    assert!(validate_game(DEFAULT_FEN, &moves).is_ok());
+    assert!(validate_game(DEFAULT_FEN, &[]).is_ok());
+
+    // black's knight can't reach e5
+    let moves = actions(&["e4", "e5", "Nf3", "Ne5", "Bc4"]);
+    match validate_game(DEFAULT_FEN, &moves).unwrap_err() {
+      (3, ContractError::IllegalMove { notation, .. }) => assert_eq!(notation, "Ne5"),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // nothing can follow mate
+    let moves = actions(&["f3", "e5", "g4", "Qh4", "a3"]);
+    match validate_game(DEFAULT_FEN, &moves).unwrap_err() {
+      (4, ContractError::IllegalMove { .. }) => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    match validate_game("8/8/8/8/8/8/8/8 w - - 0 1", &moves).unwrap_err() {
+      (0, ContractError::InvalidFen { .. }) => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_normalize_start_fen_check() {
+    let rejected = [
+      // black is in check, so white's last move was illegal
+      "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",
+      // both kings in check
+      "4k3/8/8/8/8/8/8/4R1Kr w - - 0 1",
+    ];
+    for fen in rejected {
+      match normalize_start_fen(fen).unwrap_err() {
+        ContractError::InvalidFen { reason } => assert_eq!(reason, "side not to move is in check"),
+        e => panic!("unexpected error: {:?}", e),
+      }
+      assert!(matches!(validate_game(fen, &[]), Err((0, ContractError::InvalidFen { .. }))));
+    }
+
+    // white to move and in check
+    let fen = "4k3/8/8/8/8/8/8/4r1K1 w - - 0 1";
+    assert_eq!(normalize_start_fen(fen).unwrap(), Some(fen.to_string()));
+    assert!(validate_game(fen, &actions(&["Kf2"])).is_ok());
+  }
+}
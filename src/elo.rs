@@ -28,7 +28,7 @@
 //! // The config allows you to specify certain values in the Elo calculation.
 //! // Here we modify the k-value to be 20.0, instead of the usual 32.0.
 //! // To simplify massively: This means the ratings will not change as much.
-//! let config = EloConfig { k: 20 };
+//! let config = EloConfig { k: 20, ..EloConfig::new() };
 //!
 //! // The elo function will calculate the new ratings for both players and return them.
 //! let (new_player_one, new_player_two) = elo(&player_one, &player_two, &outcome, &config);
@@ -40,51 +40,17 @@
 //! - [Elo Calculator](https://www.omnicalculator.com/sports/elo)
 //! - [FIDE Ratings](https://ratings.fide.com/)
 
-/// Constants
-const LN10: u64 = 2358; //ln(10)
-const E: u64 = 2784;    //e
-const PREC: u64 = 10;   //precision
-
-/// Calculates the exponential function e^x
-fn fp_exp(x: u64) -> u64 {
-  let mut result = 1 << PREC; // Start with 1 in fixed-point
-  let mut term = 1 << PREC;   // The current term, starts with x^0 / 0! = 1
-
-  for i in 1..=10 {
-    // Calculate x^i / i!
-    term = ((term * x) >> PREC) / (i as u64);
-    // Add the term to the result
-    result += term;
-
-    // Break early if the term is too small to affect the result
-    if term == 0 {
-      break;
-    }
-  }
+use crate::error::ContractError;
+use crate::util::fp_math::{fp_div, fp_pow10, HALF, ONE, PREC};
 
-  result
-}
-
-/// Calculates the exponential function e^x for integer
-fn fp_exp_int(x: u64) -> u64 {
-  let mut s: u64 = 1 << PREC;
-  for _ in 1..=x {
-    s = (s * E) >> PREC;
-  }
-  s
-}
+/// The largest k-value accepted by [`validate_k_schedule`], larger ones can overflow [`elo`].
+pub const MAX_K: u64 = 100;
 
-/// Calculates 10^x using fixed-point arithmetic
-fn fp_pow10(x: u64) -> u64 {
-  // multiply x by ln(10) to convert to e^x form
-  let exponent = (x * LN10) >> PREC;
-  
-  // compute e^(x * ln(10))
-  let e1 = exponent >> PREC;
-  let e2 = exponent - (e1 << PREC);
+/// The lowest expected score from [`expected_score_clamped`], about 0.1% in fixed-point.
+pub const MIN_EXPECTED_SCORE: u64 = 1;
 
-  (fp_exp_int(e1) * fp_exp(e2)) >> PREC
-}
+/// The highest expected score from [`expected_score_clamped`], just under 1.0 in fixed-point.
+pub const MAX_EXPECTED_SCORE: u64 = (1 << PREC) - 1;
 
 /// The possible outcomes for a match: Win, Draw, Loss.
 ///
@@ -108,9 +74,9 @@ impl Outcomes {
   pub const fn to_chess_points(self) -> u64 {
     // Could set the visibility to crate level, but maybe someone has a use for it, who knows.
     match self {
-      Self::WIN => 1 << PREC,         //1.0
-      Self::DRAW => 1 << (PREC - 1),  //0.5
-      Self::LOSS => 0,                //0.0
+      Self::WIN => ONE,
+      Self::DRAW => HALF,
+      Self::LOSS => 0,
     }
   }
 }
@@ -158,13 +124,30 @@ pub struct EloConfig {
   /// The higher the number, the more volatile the ranking.  
   /// Here the default is 32.
   pub k: u64,
+  /// The largest difference in size between two teams accepted by [`validate_team_balance`].
+  /// Here the default is 2.
+  pub max_team_size_difference: u64,
 }
 
 impl EloConfig {
   #[must_use]
   /// Initialise a new `EloConfig` with a k value of `32.0`.
   pub const fn new() -> Self {
-    Self { k: 32 }
+    Self {
+      k: 32,
+      max_team_size_difference: 2,
+    }
+  }
+
+  /// The `EloConfig` for a game between `player_one` and `player_two` under a K-factor schedule.
+  ///
+  /// Both players use the k-value [`effective_k`] picks for their average rating,
+  /// so a game still moves both ratings by the same amount and the pool is conserved.
+  /// An empty schedule gives the default config.
+  #[must_use]
+  pub fn scheduled(schedule: &[(u64, u64)], player_one: &EloRating, player_two: &EloRating) -> Self {
+    let average = (player_one.rating + player_two.rating) / 2;
+    effective_k(schedule, average).map_or_else(Self::new, |k| Self { k, ..Self::new() })
   }
 }
 
@@ -174,6 +157,61 @@ impl Default for EloConfig {
   }
 }
 
+/// Picks the k-value for `rating` from a K-factor schedule.
+///
+/// The schedule is a list of `(rating_threshold, k)` pairs sorted by threshold, see
+/// [`validate_k_schedule`]. The k-value of the highest threshold not above `rating` is used,
+/// and ratings below every threshold use the first k-value.
+/// Returns `None` for an empty schedule.
+///
+/// # Examples
+///
+/// ```
+/// use cosmos_chess::elo::effective_k;
+///
+/// let schedule = [(0, 40), (2000, 20)];
+///
+/// assert!(effective_k(&schedule, 1999) == Some(40));
+/// assert!(effective_k(&schedule, 2000) == Some(20));
+/// ```
+#[must_use]
+pub fn effective_k(schedule: &[(u64, u64)], rating: u64) -> Option<u64> {
+  let first = schedule.first()?;
+  Some(
+    schedule
+      .iter()
+      .take_while(|(threshold, _)| *threshold <= rating)
+      .last()
+      .unwrap_or(first)
+      .1,
+  )
+}
+
+/// Checks that a K-factor schedule is usable by [`effective_k`].
+///
+/// The schedule must not be empty, its thresholds must be strictly increasing
+/// and every k-value must be above 0 and at most [`MAX_K`].
+pub fn validate_k_schedule(schedule: &[(u64, u64)]) -> Result<(), ContractError> {
+  let invalid = |reason: &str| {
+    Err(ContractError::InvalidKSchedule {
+      reason: reason.to_string(),
+    })
+  };
+  if schedule.is_empty() {
+    return invalid("empty");
+  }
+  if schedule.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+    return invalid("thresholds not sorted");
+  }
+  if schedule.iter().any(|(_, k)| *k == 0) {
+    return invalid("k must be above 0");
+  }
+  if schedule.iter().any(|(_, k)| *k > MAX_K) {
+    return invalid(&format!("k must be at most {}", MAX_K));
+  }
+  Ok(())
+}
+
 /// Calculates the [`EloRating`]s of two players based on their old ratings and the outcome of the game.
 ///
 /// Takes in two players as [`EloRating`]s, an [`Outcome`](Outcomes) and an [`EloConfig`].
@@ -210,14 +248,128 @@ pub fn elo(
   let one_new_elo = ((player_one.rating << PREC) + config.k * outcome - config.k * expected) >> PREC;
   let two_new_elo = player_one.rating + player_two.rating - one_new_elo;
 
-  (
+  let new_ratings = (
     EloRating {
       rating: one_new_elo,
     },
     EloRating {
       rating: two_new_elo,
     },
-  )
+  );
+  #[cfg(test)]
+  rating_pool_conservation_check(&[*player_one, *player_two], &[new_ratings.0, new_ratings.1])
+    .expect("rating pool not conserved");
+  new_ratings
+}
+
+/// Checks that the total rating pool is unchanged.
+///
+/// Elo is zero-sum, so the points one player gains are the points the other
+/// loses, and the ratings in `before` and `after` sum to the same total.
+/// Returns the difference between the two totals when they do not.
+pub fn rating_pool_conservation_check(before: &[EloRating], after: &[EloRating]) -> Result<(), u64> {
+  let before: u64 = before.iter().map(|player| player.rating).sum();
+  let after: u64 = after.iter().map(|player| player.rating).sum();
+  if before == after {
+    Ok(())
+  } else {
+    Err(before.abs_diff(after))
+  }
+}
+
+/// The k-value for each player of a team of `team_size` players.
+///
+/// `base_k` is split evenly between the players, so a whole team moves about
+/// as much as a single player would in a one on one game. Never less than 1.
+#[must_use]
+pub fn team_k_factor(team_size: u64, base_k: u64) -> u64 {
+  (base_k / team_size.max(1)).max(1)
+}
+
+/// Calculates the new [`EloRating`]s of the players of two teams.
+///
+/// The teams are rated as two players with the average rating of their members.
+/// Each player of `team_one` gains or loses the same amount, using the k-value from
+/// [`team_k_factor`]. The total `team_one` gains is then taken from `team_two`, split
+/// as evenly as whole rating points allow, so the rating pool is conserved whatever
+/// the team sizes. For 1v1 this is the same as [`elo`]. The one exception is a
+/// rating that would drop below 0, which stays at 0.
+///
+/// The outcome of the match is in the perspective of `team_one`.
+#[must_use]
+pub fn team_elo(
+  team_one: &[EloRating],
+  team_two: &[EloRating],
+  outcome: &Outcomes,
+  config: &EloConfig,
+) -> (Vec<EloRating>, Vec<EloRating>) {
+  if team_one.is_empty() || team_two.is_empty() {
+    return (team_one.to_vec(), team_two.to_vec());
+  }
+  let expected = expected_score(&team_average(team_one), &team_average(team_two));
+  let outcome = outcome.to_chess_points();
+  let k = team_k_factor(team_one.len() as u64, config.k);
+
+  let new_one: Vec<EloRating> = team_one
+    .iter()
+    .map(|player| EloRating {
+      rating: ((player.rating << PREC) + k * outcome - k * expected) >> PREC,
+    })
+    .collect();
+  let gained = new_one.iter().map(|player| player.rating as i64).sum::<i64>()
+    - team_one.iter().map(|player| player.rating as i64).sum::<i64>();
+
+  // the first players of team_two take the remainder
+  let size = team_two.len() as i64;
+  let mut floored = false;
+  let new_two: Vec<EloRating> = team_two
+    .iter()
+    .enumerate()
+    .map(|(i, player)| {
+      let extra = if (i as i64) < gained.abs() % size { gained.signum() } else { 0 };
+      let rating = player.rating as i64 - gained / size - extra;
+      floored |= rating < 0;
+      EloRating {
+        rating: rating.max(0) as u64,
+      }
+    })
+    .collect();
+
+  let new_ratings = (new_one, new_two);
+  #[cfg(test)]
+  if !floored {
+    rating_pool_conservation_check(
+    &[team_one, team_two].concat(),
+      &[new_ratings.0.as_slice(), new_ratings.1.as_slice()].concat(),
+    )
+    .expect("rating pool not conserved");
+  }
+  new_ratings
+}
+
+/// Checks that two teams are close enough in size to be rated against each other.
+///
+/// Returns [`ContractError::TeamSizeImbalance`] when the sizes differ by more
+/// than the config's `max_team_size_difference`.
+pub fn validate_team_balance(
+  team_one: &[EloRating],
+  team_two: &[EloRating],
+  config: &EloConfig,
+) -> Result<(), ContractError> {
+  if team_one.len().abs_diff(team_two.len()) as u64 > config.max_team_size_difference {
+    return Err(ContractError::TeamSizeImbalance {
+      max_difference: config.max_team_size_difference,
+    });
+  }
+  Ok(())
+}
+
+// rating of a player as strong as the team on average
+fn team_average(team: &[EloRating]) -> EloRating {
+  let total: u64 = team.iter().map(|player| player.rating).sum();
+  EloRating {
+    rating: total / team.len() as u64,
+  }
 }
 
 /// Calculates the expected score of two players based on their elo rating.
@@ -245,18 +397,67 @@ pub fn expected_score(player_one: &EloRating, player_two: &EloRating) -> u64 {
   } else {
     player_two.rating - player_one.rating
   };
-  let exp_one = (1 << (PREC + PREC)) / ((1 << PREC) + fp_pow10((diff << PREC) / 400));
+  let exp_one = fp_div(ONE, ONE + fp_pow10((diff << PREC) / 400));
 
   if player_two.rating >= player_one.rating {
     exp_one
   } else {
-    (1 << PREC) - exp_one
+    ONE - exp_one
   }
 }
 
+/// Calculates the expected scores of both players, never exactly 0 or 1.
+///
+/// [`expected_score`] rounds to 0 or 1.0 once the ratings are far enough
+/// apart. Anything dividing by an expected score, or by one minus it, needs
+/// the value kept between [`MIN_EXPECTED_SCORE`] and [`MAX_EXPECTED_SCORE`].
+/// The two scores still add up to 1.0.
+///
+/// # Examples
+///
+/// ```
+/// use cosmos_chess::elo::{expected_score_clamped, EloRating, MAX_EXPECTED_SCORE, MIN_EXPECTED_SCORE};
+///
+/// let weaker = EloRating { rating: 0 };
+/// let stronger = EloRating { rating: 10000 };
+///
+/// let (exp1, exp2) = expected_score_clamped(&weaker, &stronger);
+///
+/// assert!(exp1 == MIN_EXPECTED_SCORE);
+/// assert!(exp2 == MAX_EXPECTED_SCORE);
+/// assert!(exp1 + exp2 == MIN_EXPECTED_SCORE + MAX_EXPECTED_SCORE);
+/// ```
+#[must_use]
+pub fn expected_score_clamped(player_one: &EloRating, player_two: &EloRating) -> (u64, u64) {
+  let expected = expected_score(player_one, player_two).clamp(MIN_EXPECTED_SCORE, MAX_EXPECTED_SCORE);
+  (expected, ONE - expected)
+}
+
+/// Calculates the expected score of `player_one` in basis points.
+///
+/// The same probability as [`expected_score`], scaled to 0 through 10000
+/// and rounded to the nearest basis point, for display.
+///
+/// # Examples
+///
+/// ```
+/// use cosmos_chess::elo::{expected_score_bps, EloRating};
+///
+/// let player_one = EloRating { rating: 1000 };
+/// let player_two = EloRating { rating: 1000 };
+///
+/// assert!(expected_score_bps(&player_one, &player_two) == 5000);
+/// ```
+#[must_use]
+pub fn expected_score_bps(player_one: &EloRating, player_two: &EloRating) -> u16 {
+  let expected = expected_score(player_one, player_two);
+  ((expected * 10_000 + HALF) >> PREC) as u16
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::util::rng::Rng;
 
   #[test]
   fn test_elo() {
@@ -316,6 +517,234 @@ mod tests {
     // loser: 14%
   }
 
+  #[test]
+  fn test_expected_score_bps() {
+    let player_one = EloRating::new();
+    let player_two = EloRating::default();
+
+    assert_eq!(expected_score_bps(&player_one, &player_two), 5000);
+
+    let player_one = EloRating { rating: 2251 };
+    let player_two = EloRating { rating: 1934 };
+
+    // 86% up to fixed-point precision
+    let winner = expected_score_bps(&player_one, &player_two);
+    assert!((8600..8700).contains(&winner), "{}", winner);
+    let loser = expected_score_bps(&player_two, &player_one);
+    assert!((1300..1400).contains(&loser), "{}", loser);
+    assert!((9999..=10001).contains(&(winner + loser)));
+  }
+
+  #[test]
+  fn test_expected_score_clamped() {
+    let weaker = EloRating { rating: 0 };
+    let stronger = EloRating { rating: 10000 };
+
+    // unclamped, the weaker player has no chance at all
+    assert_eq!(expected_score(&weaker, &stronger), 0);
+    assert_eq!(
+      expected_score_clamped(&weaker, &stronger),
+      (MIN_EXPECTED_SCORE, MAX_EXPECTED_SCORE)
+    );
+    assert_eq!(
+      expected_score_clamped(&stronger, &weaker),
+      (MAX_EXPECTED_SCORE, MIN_EXPECTED_SCORE)
+    );
+
+    // close ratings are not changed
+    let player_one = EloRating { rating: 2251 };
+    let player_two = EloRating { rating: 1934 };
+    let expected = expected_score(&player_one, &player_two);
+    assert_eq!(
+      expected_score_clamped(&player_one, &player_two),
+      (expected, ONE - expected)
+    );
+  }
+
+  #[test]
+  fn test_elo_conservation() {
+    let mut rng = Rng::new(178);
+    let mut players = [EloRating::new(); 10];
+    let config = EloConfig::default();
+
+    for _ in 0..1000 {
+      let one = rng.next_range(10) as usize;
+      let two = (one + 1 + rng.next_range(9) as usize) % 10;
+      let outcome = match rng.next_range(3) {
+        0 => Outcomes::WIN,
+        1 => Outcomes::LOSS,
+        _ => Outcomes::DRAW,
+      };
+      let before = players;
+      let (new_one, new_two) = elo(&players[one], &players[two], &outcome, &config);
+      players[one] = new_one;
+      players[two] = new_two;
+      assert_eq!(rating_pool_conservation_check(&before, &players), Ok(()));
+    }
+    assert_eq!(
+      rating_pool_conservation_check(&[EloRating::new(); 10], &players),
+      Ok(())
+    );
+    assert_ne!(players, [EloRating::new(); 10]);
+
+    // a discrepancy is reported either way
+    let before = [EloRating::from(1000), EloRating::from(1000)];
+    assert_eq!(
+      rating_pool_conservation_check(&before, &[EloRating::from(1010), EloRating::from(1000)]),
+      Err(10)
+    );
+    assert_eq!(
+      rating_pool_conservation_check(&before, &[EloRating::from(990), EloRating::from(1000)]),
+      Err(10)
+    );
+  }
+
+  #[test]
+  fn test_effective_k() {
+    let schedule = [(0, 40), (2000, 20)];
+    assert_eq!(effective_k(&schedule, 0), Some(40));
+    assert_eq!(effective_k(&schedule, 1999), Some(40));
+    assert_eq!(effective_k(&schedule, 2000), Some(20));
+    assert_eq!(effective_k(&schedule, 2800), Some(20));
+    // below the first threshold
+    assert_eq!(effective_k(&[(1000, 24), (2400, 10)], 800), Some(24));
+    assert_eq!(effective_k(&[], 1000), None);
+
+    // the average rating picks k for both players
+    let config = EloConfig::scheduled(&schedule, &EloRating::from(1950), &EloRating::from(2100));
+    assert_eq!(config.k, 20);
+    let config = EloConfig::scheduled(&schedule, &EloRating::from(1900), &EloRating::from(2000));
+    assert_eq!(config.k, 40);
+    assert_eq!(EloConfig::scheduled(&[], &EloRating::new(), &EloRating::new()).k, 32);
+
+    let (winner, loser) = elo(
+      &EloRating::from(1000),
+      &EloRating::from(1000),
+      &Outcomes::WIN,
+      &EloConfig::scheduled(&schedule, &EloRating::from(1000), &EloRating::from(1000)),
+    );
+    assert_eq!((winner.rating, loser.rating), (1020, 980));
+    let (winner, loser) = elo(
+      &EloRating::from(2100),
+      &EloRating::from(2100),
+      &Outcomes::WIN,
+      &EloConfig::scheduled(&schedule, &EloRating::from(2100), &EloRating::from(2100)),
+    );
+    assert_eq!((winner.rating, loser.rating), (2110, 2090));
+  }
+
+  #[test]
+  fn test_validate_k_schedule() {
+    assert!(validate_k_schedule(&[(0, 32)]).is_ok());
+    assert!(validate_k_schedule(&[(0, 40), (2000, 20), (2400, 10)]).is_ok());
+    assert!(validate_k_schedule(&[(0, MAX_K)]).is_ok());
+    match validate_k_schedule(&[(0, 40), (1000, 3000)]).unwrap_err() {
+      ContractError::InvalidKSchedule { reason } => assert_eq!(reason, "k must be at most 100"),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    for schedule in [vec![], vec![(2000, 20), (0, 40)], vec![(0, 40), (0, 20)], vec![(0, 0)]] {
+      match validate_k_schedule(&schedule).unwrap_err() {
+        ContractError::InvalidKSchedule { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+  }
+
+  #[test]
+  fn test_team_k_factor() {
+    assert_eq!(team_k_factor(1, 32), 32);
+    assert_eq!(team_k_factor(2, 32), 16);
+    assert_eq!(team_k_factor(3, 32), 10);
+    assert_eq!(team_k_factor(64, 32), 1);
+    assert_eq!(team_k_factor(0, 32), 32);
+  }
+
+  #[test]
+  fn test_team_elo() {
+    let config = EloConfig::new();
+    let ratings = |ratings: &[u64]| -> Vec<EloRating> {
+      ratings.iter().map(|&r| EloRating::from(r)).collect()
+    };
+    let total = |team: &[EloRating]| -> u64 { team.iter().map(|player| player.rating).sum() };
+
+    // 1v1 is the same as a regular game
+    for (one, two, outcome) in [
+      (1000, 1000, Outcomes::WIN),
+      (500, 1500, Outcomes::WIN),
+      (1320, 1217, Outcomes::DRAW),
+    ] {
+      let (new_one, new_two) = elo(&EloRating::from(one), &EloRating::from(two), &outcome, &config);
+      let (team_one, team_two) = team_elo(&ratings(&[one]), &ratings(&[two]), &outcome, &config);
+      assert_eq!((team_one, team_two), (vec![new_one], vec![new_two]));
+    }
+
+    // 2v2, each player moves by half as much
+    let (team_one, team_two) =
+      team_elo(&ratings(&[900, 1100]), &ratings(&[1000, 1000]), &Outcomes::WIN, &config);
+    assert_eq!(team_one, ratings(&[908, 1108]));
+    assert_eq!(team_two, ratings(&[992, 992]));
+
+    // 3v3
+    let before = [ratings(&[1200, 1000, 800]), ratings(&[1000, 1000, 1000])];
+    let (team_one, team_two) = team_elo(&before[0], &before[1], &Outcomes::LOSS, &config);
+    assert_eq!(team_one, ratings(&[1195, 995, 795]));
+    assert_eq!(team_two, ratings(&[1005, 1005, 1005]));
+    assert_eq!(total(&team_one) + total(&team_two), total(&before[0]) + total(&before[1]));
+
+    // 2v3, team_two splits what team_one gains
+    let (team_one, team_two) =
+      team_elo(&ratings(&[1000, 1000]), &ratings(&[1000, 1000, 1000]), &Outcomes::WIN, &config);
+    assert_eq!(team_one, ratings(&[1008, 1008]));
+    assert_eq!(team_two, ratings(&[994, 995, 995]));
+
+    // a rating never drops below 0
+    let (_, team_two) = team_elo(&ratings(&[10]), &ratings(&[5, 5]), &Outcomes::WIN, &config);
+    assert_eq!(team_two, ratings(&[0, 0]));
+  }
+
+  #[test]
+  fn test_team_elo_conservation() {
+    let mut rng = Rng::new(185);
+    let config = EloConfig::default();
+    for _ in 0..200 {
+      let team = |rng: &mut Rng| -> Vec<EloRating> {
+        (0..1 + rng.next_range(4)).map(|_| EloRating::from(600 + rng.next_range(1200))).collect()
+      };
+      let (before_one, before_two) = (team(&mut rng), team(&mut rng));
+      let outcome = match rng.next_range(3) {
+        0 => Outcomes::WIN,
+        1 => Outcomes::LOSS,
+        _ => Outcomes::DRAW,
+      };
+      let (after_one, after_two) = team_elo(&before_one, &before_two, &outcome, &config);
+      assert_eq!(
+        rating_pool_conservation_check(
+          &[before_one, before_two].concat(),
+          &[after_one, after_two].concat()
+        ),
+        Ok(())
+      );
+    }
+  }
+
+  #[test]
+  fn test_validate_team_balance() {
+    let team = |size: usize| vec![EloRating::new(); size];
+    let config = EloConfig::new();
+    assert!(validate_team_balance(&team(1), &team(1), &config).is_ok());
+    assert!(validate_team_balance(&team(2), &team(3), &config).is_ok());
+    assert!(validate_team_balance(&team(1), &team(3), &config).is_ok());
+    match validate_team_balance(&team(4), &team(1), &config).unwrap_err() {
+      ContractError::TeamSizeImbalance { max_difference } => assert_eq!(max_difference, 2),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let config = EloConfig {
+      max_team_size_difference: 3,
+      ..EloConfig::new()
+    };
+    assert!(validate_team_balance(&team(4), &team(1), &config).is_ok());
+  }
+
   #[test]
   #[allow(clippy::clone_on_copy)]
   fn test_misc_stuff() {
@@ -1,7 +1,15 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+pub mod draw_detection;
+
 use crate::board::Board;
+use draw_detection::is_draw_by_repetition_in_search;
+use crate::game::Game;
 use crate::position::Position;
 use crate::piece::Piece;
 use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
 
 pub const WHITE: Color = Color::White;
 pub const BLACK: Color = Color::Black;
@@ -40,7 +48,7 @@ pub enum GameResult {
 }
 
 /// The color of a piece.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Color {
   White,
   Black,
@@ -204,12 +212,178 @@ impl core::fmt::Display for Move {
   }
 }
 
+/// Something a move does that a frontend may want to show, such as a
+/// promotion or a check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveEvent {
+  /// A piece moved, possibly capturing.
+  Normal { from: Position, to: Position },
+  /// A pawn reached the last rank and became `promoted_to`.
+  Promotion {
+    from: Position,
+    to: Position,
+    promoted_to: Piece,
+  },
+  /// The king castled.
+  Castle { kingside: bool },
+  /// A pawn captured en passant, removing the pawn on `captured_pawn`.
+  EnPassant { captured_pawn: Position },
+  /// The move gives check.
+  Check,
+  /// The move gives checkmate.
+  Checkmate,
+}
+
+impl core::fmt::Display for MoveEvent {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+    match self {
+      MoveEvent::Normal { from, to } => write!(f, "move {} {}", from, to),
+      MoveEvent::Promotion {
+        from,
+        to,
+        promoted_to,
+      } => write!(f, "promotion {} {} {}", from, to, promoted_to.get_name()),
+      MoveEvent::Castle { kingside: true } => write!(f, "castle kingside"),
+      MoveEvent::Castle { kingside: false } => write!(f, "castle queenside"),
+      MoveEvent::EnPassant { captured_pawn } => write!(f, "en_passant {}", captured_pawn),
+      MoveEvent::Check => write!(f, "check"),
+      MoveEvent::Checkmate => write!(f, "checkmate"),
+    }
+  }
+}
+
+impl Move {
+  /// Get the events this move triggers when played on `board_before`.
+  ///
+  /// The first event describes the move itself, followed by `Check` or
+  /// `Checkmate` when the move attacks the opponent's king. Resigning
+  /// triggers no events.
+  pub fn events(&self, board_before: &Board) -> Vec<MoveEvent> {
+    let mut events = match *self {
+      Move::Resign => return vec![],
+      Move::KingSideCastle => vec![MoveEvent::Castle { kingside: true }],
+      Move::QueenSideCastle => vec![MoveEvent::Castle { kingside: false }],
+      Move::Promotion(from, to, piece) => vec![MoveEvent::Promotion {
+        from,
+        to,
+        promoted_to: piece,
+      }],
+      Move::Piece(from, to) => match board_before.get_piece(from) {
+        // pawns reaching the last rank become queens by default
+        Some(Piece::Pawn(color, _)) if to.get_row() == 0 || to.get_row() == 7 => {
+          vec![MoveEvent::Promotion {
+            from,
+            to,
+            promoted_to: Piece::Queen(color, to),
+          }]
+        }
+        Some(Piece::Pawn(color, _))
+          if board_before.get_en_passant() == Some(to) && from.get_col() != to.get_col() =>
+        {
+          vec![MoveEvent::EnPassant {
+            captured_pawn: to.pawn_back(color),
+          }]
+        }
+        _ => vec![MoveEvent::Normal { from, to }],
+      },
+    };
+
+    let board_after = board_before.apply_eval_move(*self);
+    if board_after.is_checkmate() {
+      events.push(MoveEvent::Checkmate);
+    } else if board_after.is_in_check(board_after.get_turn_color()) {
+      events.push(MoveEvent::Check);
+    }
+    events
+  }
+}
+
+/// The stage of the game, judged by how much non-pawn material is left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+  Opening,
+  Middlegame,
+  Endgame,
+}
+
+impl Phase {
+  /// The phase value at the start of the game, with all non-pawn
+  /// material on the board.
+  pub const MAX_VALUE: u8 = 24;
+
+  /// Classify a phase value from `Board::get_phase_value`.
+  pub fn from_value(value: u8) -> Self {
+    match value {
+      0..=6 => Self::Endgame,
+      7..=21 => Self::Middlegame,
+      _ => Self::Opening,
+    }
+  }
+}
+
+/// The static evaluation of a position split into its parts, in centipawns
+/// from white's point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Evaluation {
+  /// `PAWN_SHIELD_CENTIPAWNS` for every pawn more in front of white's king
+  /// than black's, scaled down by the game phase to nothing in a bare
+  /// endgame.
+  pub king_safety: i32,
+  /// Material balance, not counting kings.
+  pub material: i32,
+  /// `MOBILITY_CENTIPAWNS` for every legal move white has more than black,
+  /// scaled down by the game phase to nothing with all the pieces on the
+  /// board.
+  pub mobility: i32,
+  /// Penalties for doubled and isolated pawns.
+  pub pawn_structure: i32,
+  /// Bonuses and penalties from the piece position weights.
+  pub piece_square: i32,
+}
+
+impl Evaluation {
+  /// The full evaluation, the sum of every part. This is what
+  /// `Evaluate::value_for` returns for white.
+  pub fn total(&self) -> i32 {
+    self.king_safety + self.material + self.mobility + self.pawn_structure + self.piece_square
+  }
+}
+
+/// Bound for the alpha-beta window, past any evaluation.
+pub const SEARCH_BOUND: i32 = 10_000_000;
+
+/// Score of a side left without legal moves, inside `SEARCH_BOUND`.
+const NO_MOVES_SCORE: i32 = 9_999_990;
+
+/// What one more legal move is worth in the evaluation, once the game
+/// reaches the endgame.
+pub const MOBILITY_CENTIPAWNS: i32 = 2;
+
+/// What a pawn costs for each other pawn of its color on its file.
+pub const DOUBLED_PAWN_CENTIPAWNS: i32 = 10;
+
+/// What a pawn costs with no pawn of its color on a neighbouring file.
+pub const ISOLATED_PAWN_CENTIPAWNS: i32 = 10;
+
+/// What a pawn in front of its king is worth, before the endgame.
+pub const PAWN_SHIELD_CENTIPAWNS: i32 = 10;
+
+/// Hash a position for repetition detection.
+///
+/// Two boards hash the same when their pieces, side to move, castling
+/// rights and en passant square are the same.
+pub fn position_hash<T: Hash>(position: &T) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  position.hash(&mut hasher);
+  hasher.finish()
+}
+
 /// Evaluate a board and extract information, such as the best and worst moves.
-pub trait Evaluate: Sized {
-  /// Get the value of the board for a given color.
+pub trait Evaluate: Sized + Hash {
+  /// Get the value of the board for a given color, in centipawns.
   /// This subtracts the opponents value, and accounts for piece positions
   /// and material value.
-  fn value_for(&self, color: Color) -> f64;
+  fn value_for(&self, color: Color) -> i32;
 
   /// Get the current player's color.
   fn get_current_player_color(&self) -> Color;
@@ -220,6 +394,15 @@ pub trait Evaluate: Sized {
   /// Apply a move to the board for evaluation.
   fn apply_eval_move(&self, m: Move) -> Self;
 
+  /// Get the only legal move for the current player, if there is exactly
+  /// one. This is useful for highlighting or auto-playing forced moves.
+  fn get_only_move(&self) -> Option<Move> {
+    match self.get_legal_moves().as_slice() {
+      [m] => Some(*m),
+      _ => None,
+    }
+  }
+
   /// Get the best move for the current player with `depth` number of moves
   /// of lookahead.
   ///
@@ -230,9 +413,33 @@ pub trait Evaluate: Sized {
   ///
   /// It's best not to use the rating value by itself for anything, as it
   /// is relative to the other player's move ratings as well.
-  fn get_best_next_move(&self, depth: i32) -> (Move, u64, f64) {
-    let legal_moves = self.get_legal_moves();
-    let mut best_move_value = -999999.0;
+  fn get_best_next_move(&self, depth: i32) -> (Move, u64, i32) {
+    self.get_best_next_move_with_history(depth, &[])
+  }
+
+  /// Get the best move like `get_best_next_move`, scoring positions that
+  /// were already reached as draws.
+  ///
+  /// `history` holds the `position_hash` of every position reached in the
+  /// game so far. Returning to one of them, or repeating a position within
+  /// the search, scores 0 regardless of the evaluation, so a side that is
+  /// ahead prefers any move that keeps its advantage over a repetition.
+  fn get_best_next_move_with_history(&self, depth: i32, history: &[u64]) -> (Move, u64, i32) {
+    self.get_best_next_move_excluding(depth, history, &[])
+  }
+
+  /// Get the best move like `get_best_next_move_with_history`, leaving
+  /// out the moves in `excluded`.
+  ///
+  /// `Move::Resign` is returned when every legal move is excluded.
+  fn get_best_next_move_excluding(&self, depth: i32, history: &[u64], excluded: &[Move]) -> (Move, u64, i32) {
+    let legal_moves: Vec<Move> = self
+      .get_legal_moves()
+      .into_iter()
+      .filter(|m| !excluded.contains(m))
+      .collect();
+    let mut history = history.to_vec();
+    let mut best_move_value = -NO_MOVES_SCORE;
     let mut best_move = Move::Resign;
 
     let color = self.get_current_player_color();
@@ -241,11 +448,12 @@ pub trait Evaluate: Sized {
     for m in &legal_moves {
       let child_board_value = self.apply_eval_move(*m).minimax(
         depth,
-        -1000000.0,
-        1000000.0,
+        -SEARCH_BOUND,
+        SEARCH_BOUND,
         false,
         color,
         &mut board_count,
+        &mut history,
       );
       if child_board_value >= best_move_value {
         best_move = *m;
@@ -266,9 +474,9 @@ pub trait Evaluate: Sized {
   ///
   /// It's best not to use the rating value by itself for anything, as it
   /// is relative to the other player's move ratings as well.
-  fn get_worst_next_move(&self, depth: i32) -> (Move, u64, f64) {
+  fn get_worst_next_move(&self, depth: i32) -> (Move, u64, i32) {
     let legal_moves = self.get_legal_moves();
-    let mut best_move_value = -999999.0;
+    let mut best_move_value = -NO_MOVES_SCORE;
     let mut best_move = Move::Resign;
 
     let color = self.get_current_player_color();
@@ -277,11 +485,12 @@ pub trait Evaluate: Sized {
     for m in &legal_moves {
       let child_board_value = self.apply_eval_move(*m).minimax(
         depth,
-        -1000000.0,
-        1000000.0,
+        -SEARCH_BOUND,
+        SEARCH_BOUND,
         true,
         !color,
         &mut board_count,
+        &mut vec![],
       );
 
       if child_board_value >= best_move_value {
@@ -300,26 +509,40 @@ pub trait Evaluate: Sized {
   /// In other words, choose moves with the assumption that your opponent will make the
   /// best possible replies to your moves. Moves that are seemingly good, but are easily countered,
   /// are categorically eliminated by this algorithm.
+  ///
+  /// Positions in `history`, the positions leading up to this one, score
+  /// as draws.
+  #[allow(clippy::too_many_arguments)]
   fn minimax(
     &self,
     depth: i32,
-    mut alpha: f64,
-    mut beta: f64,
+    mut alpha: i32,
+    mut beta: i32,
     is_maximizing: bool,
     getting_move_for: Color,
     board_count: &mut u64,
-  ) -> f64 {
+    history: &mut Vec<u64>,
+  ) -> i32 {
     *board_count += 1;
 
+    // a repeated position is a draw, history starts with the game's
+    // positions so all of it is in reach
+    let hash = position_hash(self);
+    let plies = u8::try_from(history.len()).unwrap_or(u8::MAX);
+    if is_draw_by_repetition_in_search(hash, history, plies) {
+      return 0;
+    }
+
     if depth == 0 {
       return self.value_for(getting_move_for);
     }
 
     let legal_moves = self.get_legal_moves();
     let mut best_move_value;
+    history.push(hash);
 
     if is_maximizing {
-      best_move_value = -999999.0;
+      best_move_value = -NO_MOVES_SCORE;
 
       for m in &legal_moves {
         let child_board_value = self.apply_eval_move(*m).minimax(
@@ -329,6 +552,7 @@ pub trait Evaluate: Sized {
           !is_maximizing,
           getting_move_for,
           board_count,
+          history,
         );
 
         if child_board_value > best_move_value {
@@ -340,11 +564,12 @@ pub trait Evaluate: Sized {
         }
 
         if beta <= alpha {
+          history.pop();
           return best_move_value;
         }
       }
     } else {
-      best_move_value = 999999.0;
+      best_move_value = NO_MOVES_SCORE;
 
       for m in &legal_moves {
         let child_board_value = self.apply_eval_move(*m).minimax(
@@ -354,6 +579,7 @@ pub trait Evaluate: Sized {
           !is_maximizing,
           getting_move_for,
           board_count,
+          history,
         );
         if child_board_value < best_move_value {
           best_move_value = child_board_value;
@@ -364,11 +590,660 @@ pub trait Evaluate: Sized {
         }
 
         if beta <= alpha {
+          history.pop();
           return best_move_value;
         }
       }
     }
 
+    history.pop();
     best_move_value
   }
 }
+
+/// The most moves `multi_pv_search` returns.
+pub const MAX_PV: u8 = 5;
+
+/// The deepest search `multi_pv_search` runs.
+pub const MAX_PV_DEPTH: u8 = 3;
+
+/// One of the moves found by `multi_pv_search`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+  /// The move searched.
+  pub best_move: Move,
+  /// The rating of the move in centipawns, as returned by
+  /// `get_best_next_move`.
+  pub value: i32,
+  /// The expected line, starting with `best_move`.
+  pub pv: Vec<Move>,
+}
+
+/// Get the `n` best moves for the side to move, best first, with
+/// `depth` moves of lookahead as in `get_best_next_move`.
+///
+/// The position is searched once for each move, leaving out the moves
+/// already found, so the first result is the move `get_best_next_move`
+/// picks. This costs more than a true multi-PV search but reuses the
+/// single search as is. `n` is capped at `MAX_PV` and `depth` at
+/// `MAX_PV_DEPTH`, and fewer results are returned when there are fewer
+/// legal moves. Positions already reached in `game` score as draws.
+pub fn multi_pv_search(game: &Game, depth: u8, n: u8) -> Vec<SearchResult> {
+  let depth = depth.min(MAX_PV_DEPTH);
+  let history: Vec<u64> = game.history.iter().map(|(board, _)| position_hash(board)).collect();
+  let mut results: Vec<SearchResult> = vec![];
+  for _ in 0..n.min(MAX_PV) {
+    let excluded: Vec<Move> = results.iter().map(|result| result.best_move).collect();
+    let (best_move, _, value) = game
+      .board
+      .get_best_next_move_excluding(depth as i32, &history, &excluded);
+    if best_move == Move::Resign {
+      break;
+    }
+    results.push(SearchResult {
+      best_move,
+      value,
+      pv: principal_variation(&game.board, best_move, depth as i32, &history),
+    });
+  }
+  results
+}
+
+// `first` followed by the best reply to each move, searching one ply less
+// each time, so the line is as long as the search that found `first`
+fn principal_variation(board: &Board, first: Move, depth: i32, history: &[u64]) -> Vec<Move> {
+  let mut history = history.to_vec();
+  history.push(position_hash(board));
+  let mut board = board.apply_eval_move(first);
+  let mut pv = vec![first];
+  for depth in (0..depth).rev() {
+    let (m, _, _) = board.get_best_next_move_with_history(depth, &history);
+    if m == Move::Resign {
+      break;
+    }
+    history.push(position_hash(&board));
+    board = board.apply_eval_move(m);
+    pv.push(m);
+  }
+  pv
+}
+
+/// Get the square of `color`'s king, if it is on the board.
+pub fn king_square(board: &Board, color: Color) -> Option<Position> {
+  board.get_king_pos(color)
+}
+
+/// Get the pieces of `color` pinned to their king, as pairs of the pinned
+/// piece's square and the square of the enemy slider pinning it.
+///
+/// A pinned piece is the only piece between its king and an enemy rook or
+/// queen on the same rank or file, or an enemy bishop or queen on the same
+/// diagonal.
+pub fn pinned_pieces(board: &Board, color: Color) -> Vec<(Position, Position)> {
+  let king = match king_square(board, color) {
+    Some(king) => king,
+    None => return vec![],
+  };
+
+  let mut result = vec![];
+  for (row_step, col_step) in [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+  ] {
+    let diagonal = row_step != 0 && col_step != 0;
+    let mut pinned = None;
+    let mut pos = king;
+    loop {
+      pos = Position::new(pos.get_row() + row_step, pos.get_col() + col_step);
+      if pos.is_off_board() {
+        break;
+      }
+      let piece = match board.get_piece(pos) {
+        Some(piece) => piece,
+        None => continue,
+      };
+      match pinned {
+        // first piece along the ray must be our own
+        None if piece.get_color() == color => pinned = Some(pos),
+        None => break,
+        // second piece must be an enemy slider moving along this ray
+        Some(pinned_pos) => {
+          let slides = piece.is_queen() || (diagonal && piece.is_bishop()) || (!diagonal && piece.is_rook());
+          if piece.get_color() != color && slides {
+            result.push((pinned_pos, pos));
+          }
+          break;
+        }
+      }
+    }
+  }
+  result
+}
+
+/// Is every legal move of `color` a king move?
+///
+/// True in double check, or when every other piece is pinned or blocked.
+/// Stops at the first other piece with a legal move, so it is cheaper than
+/// listing every move. Also true when `color` has no legal moves at all.
+pub fn only_king_can_move(board: &Board, color: Color) -> bool {
+  !(0..8)
+    .flat_map(|row| (0..8).map(move |col| Position::new(row, col)))
+    .filter_map(|pos| board.get_piece(pos))
+    .filter(|piece| piece.get_color() == color && !piece.is_king())
+    .any(|piece| !piece.get_legal_moves(board).is_empty())
+}
+
+/// A well known checkmate pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatePattern {
+  /// A rook or queen mates along the king's back rank, the squares in
+  /// front of the king taken by its own pieces.
+  BackRank,
+  /// A knight mates a king surrounded by its own pieces.
+  Smothered,
+  /// A rook or queen mates along the edge file while a knight three files
+  /// away on the king's rank covers the escape squares.
+  Anastasia,
+}
+
+/// Name the pattern of the checkmate `mating_move` gives when played on
+/// `board`, if it matches a known motif.
+///
+/// Only the mating piece and the squares around the king are looked at, so
+/// this is a fixed amount of work rather than a search. Returns `None` when
+/// the move does not checkmate, or the mate matches none of the patterns.
+pub fn classify_mate(board: &Board, mating_move: Move) -> Option<MatePattern> {
+  let to = match mating_move {
+    Move::Piece(_, to) | Move::Promotion(_, to, _) => to,
+    _ => return None,
+  };
+  let attacker = board.get_turn_color();
+  let defender = !attacker;
+  let after = board.apply_move(mating_move).change_turn();
+  if !after.is_checkmate() {
+    return None;
+  }
+  let king = after.get_king_pos(defender)?;
+  let piece = after.get_piece(to)?;
+
+  let mut neighbours = vec![];
+  for row_step in -1..=1 {
+    for col_step in -1..=1 {
+      let pos = Position::new(king.get_row() + row_step, king.get_col() + col_step);
+      if pos != king && pos.is_on_board() {
+        neighbours.push(pos);
+      }
+    }
+  }
+  // the mating piece checks along an open rank or file
+  let slider_check = (piece.is_rook() || piece.is_queen())
+    && to.is_orthogonal_to(king)
+    && to
+      .orthogonals_to(king)
+      .iter()
+      .all(|&pos| pos == king || after.has_no_piece(pos));
+
+  if piece.is_knight()
+    && to.is_knight_move(king)
+    && neighbours.iter().all(|&pos| after.has_ally_piece(pos, defender))
+  {
+    return Some(MatePattern::Smothered);
+  }
+
+  let back_rank = match defender {
+    Color::White => 0,
+    Color::Black => 7,
+  };
+  if slider_check
+    && king.get_row() == back_rank
+    && to.get_row() == back_rank
+    && neighbours
+      .iter()
+      .filter(|pos| pos.get_row() != back_rank)
+      .all(|&pos| after.has_ally_piece(pos, defender))
+  {
+    return Some(MatePattern::BackRank);
+  }
+
+  let knight_col = match king.get_col() {
+    0 => 3,
+    7 => 4,
+    _ => return None,
+  };
+  let knight = after.get_piece(Position::new(king.get_row(), knight_col));
+  if slider_check
+    && to.get_col() == king.get_col()
+    && matches!(knight, Some(knight) if knight.is_knight() && knight.get_color() == attacker)
+  {
+    return Some(MatePattern::Anastasia);
+  }
+
+  None
+}
+
+/// Moves of checks and captures `is_forced_mate_in` searches past `n`.
+pub const QUIESCENCE_DEPTH: u32 = 2;
+
+/// The deepest `n` `is_forced_mate_in` searches, anything more is too
+/// costly for a query.
+pub const MAX_MATE_MOVES: u32 = 2;
+
+/// Can the side to move force checkmate within `n` of its own moves?
+///
+/// Every move is tried for the first `n` moves, then `quiescence_mate`
+/// takes over, so a mate a few moves longer is still found when those
+/// extra moves are all checks or captures. This keeps a capture that wins
+/// an interposed piece, for instance, from falling past the horizon.
+/// `n` is capped at `MAX_MATE_MOVES`.
+pub fn is_forced_mate_in(board: &Board, n: u32) -> bool {
+  let n = n.min(MAX_MATE_MOVES);
+  if n == 0 {
+    return quiescence_mate(board, QUIESCENCE_DEPTH);
+  }
+  board
+    .get_legal_moves()
+    .into_iter()
+    .any(|m| mates_against_every_reply(&board.apply_eval_move(m), |board| is_forced_mate_in(board, n - 1)))
+}
+
+/// Can the side to move force checkmate within `depth` of its own moves,
+/// playing only checks and captures?
+///
+/// The defender may answer with any legal move. Strictly bounded by
+/// `depth`, so it is cheap enough to run at the leaves of a search.
+pub fn quiescence_mate(board: &Board, depth: u32) -> bool {
+  if depth == 0 {
+    return false;
+  }
+  board
+    .get_legal_moves()
+    .into_iter()
+    .filter(|m| is_forcing(board, *m))
+    .any(|m| mates_against_every_reply(&board.apply_eval_move(m), |board| quiescence_mate(board, depth - 1)))
+}
+
+// `board` is checkmate, or every defender reply leads to a position
+// where `mates` holds. Stalemate is not a mate.
+fn mates_against_every_reply(board: &Board, mates: impl Fn(&Board) -> bool) -> bool {
+  if board.is_checkmate() {
+    return true;
+  }
+  let replies = board.get_legal_moves();
+  !replies.is_empty() && replies.into_iter().all(|reply| mates(&board.apply_eval_move(reply)))
+}
+
+// does `m` give check or capture a piece
+fn is_forcing(board: &Board, m: Move) -> bool {
+  let color = board.get_turn_color();
+  let capture = match m {
+    Move::Piece(from, to) | Move::Promotion(from, to, _) => {
+      board.has_enemy_piece(to, color)
+        || (board.get_en_passant() == Some(to)
+          && matches!(board.get_piece(from), Some(piece) if piece.is_pawn()))
+    }
+    _ => false,
+  };
+  capture || board.apply_eval_move(m).is_in_check(!color)
+}
+
+/// Count the legal moves `color` would have on its turn.
+///
+/// Fully legal: pseudo-legal moves that leave the king in check are not
+/// counted. The side not to move gets the moves it would have if it were
+/// its turn, without en passant, so the count depends only on the pieces
+/// and castling rights. Each promotion piece counts as a move.
+pub fn mobility(board: &Board, color: Color) -> u32 {
+  board.count_legal_moves(color)
+}
+
+/// The penalties for `color`'s doubled and isolated pawns, zero or less.
+///
+/// A file of two pawns costs `DOUBLED_PAWN_CENTIPAWNS` for the second one,
+/// and each pawn without a pawn of its color on a neighbouring file costs
+/// `ISOLATED_PAWN_CENTIPAWNS`.
+pub fn pawn_structure(board: &Board, color: Color) -> i32 {
+  let mut files = [0; 8];
+  for (row, col) in (0..8).flat_map(|row| (0..8).map(move |col| (row, col))) {
+    if matches!(board.get_piece(Position::new(row, col)), Some(Piece::Pawn(c, _)) if c == color) {
+      files[col as usize] += 1;
+    }
+  }
+
+  (0..8)
+    .filter(|file| files[*file] > 0)
+    .map(|file| {
+      let pawns = files[file];
+      let isolated = (file == 0 || files[file - 1] == 0) && (file == 7 || files[file + 1] == 0);
+      -(pawns - 1) * DOUBLED_PAWN_CENTIPAWNS - if isolated { pawns * ISOLATED_PAWN_CENTIPAWNS } else { 0 }
+    })
+    .sum()
+}
+
+/// Count `color`'s pawns on the three squares right in front of its king.
+pub fn pawn_shield(board: &Board, color: Color) -> u32 {
+  let front = match board.get_king_pos(color) {
+    Some(king) => king.pawn_up(color),
+    None => return 0,
+  };
+  [front.next_left(), front, front.next_right()]
+    .iter()
+    .filter(|pos| pos.is_on_board())
+    .filter(|pos| matches!(board.get_piece(**pos), Some(Piece::Pawn(c, _)) if c == color))
+    .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::parse_fen;
+
+  #[test]
+  fn test_repetition_avoidance() {
+    // white is two pawns up
+    let board = parse_fen("4k3/8/8/8/8/8/PP6/R3K3 w - - 0 1").unwrap();
+    let (best, _, value) = board.get_best_next_move(1);
+    assert!(value > 0);
+
+    // pretend the position after the best move was already reached,
+    // repeating it would throw the advantage away as a draw
+    let repeated = position_hash(&board.apply_eval_move(best));
+    let (other, _, other_value) = board.get_best_next_move_with_history(1, &[repeated]);
+    assert_ne!(other, best);
+    assert!(other_value > 0);
+
+    // the losing side is happy to repeat
+    let board = board.change_turn();
+    let (best, _, value) = board.get_best_next_move(1);
+    assert!(value < 0);
+    let repeated = position_hash(&board.apply_eval_move(best));
+    let (_, _, repeat_value) = board.get_best_next_move_with_history(1, &[repeated]);
+    assert_eq!(repeat_value, 0);
+  }
+
+  #[test]
+  fn test_search_alternating_positions() {
+    // locked pawns, only the kings can move
+    let board = parse_fen("7k/8/8/p1p1p1p1/P1P1P1P1/8/8/K7 w - - 0 1").unwrap();
+    let shuffle = |board: &Board, from: &str, to: &str| {
+      board.apply_eval_move(Move::Piece(Position::pgn(from).unwrap(), Position::pgn(to).unwrap()))
+    };
+    let b1 = shuffle(&board, "a1", "b1");
+    let g8 = shuffle(&b1, "h8", "g8");
+    let a1 = shuffle(&g8, "b1", "a1");
+    let h8 = shuffle(&a1, "g8", "h8");
+    assert_eq!(position_hash(&h8), position_hash(&board));
+
+    let search = |board: &Board, history: &mut Vec<u64>| {
+      let mut board_count = 0;
+      let value = board.minimax(6, -SEARCH_BOUND, SEARCH_BOUND, true, WHITE, &mut board_count, history);
+      (value, board_count)
+    };
+    // back at the root, the shuffle scores as a draw without searching on
+    let mut history = vec![position_hash(&board), position_hash(&b1), position_hash(&g8), position_hash(&a1)];
+    assert_eq!(search(&h8, &mut history), (0, 1));
+    assert_eq!(history.len(), 4);
+
+    // kings shuffling back and forth are cut off at each repeat
+    let (_, fresh) = search(&board, &mut vec![]);
+    let (_, repeated) = search(&board, &mut vec![position_hash(&b1), position_hash(&g8)]);
+    assert!(repeated < fresh);
+  }
+
+  #[test]
+  fn test_multi_pv_search() {
+    // white can take the queen, a pawn or neither
+    let fen = "4k3/8/8/3q4/8/2p5/3R4/4K3 w - - 0 1";
+    let game = Game::from_fen(fen, None, None).unwrap();
+    let results = multi_pv_search(&game, 1, 3);
+    assert_eq!(results.len(), 3);
+
+    let (best, _, value) = parse_fen(fen).unwrap().get_best_next_move(1);
+    assert_eq!(results[0].best_move, best);
+    assert_eq!(results[0].value, value);
+    assert_eq!(best, Move::Piece(Position::pgn("d2").unwrap(), Position::pgn("d5").unwrap()));
+    for pair in results.windows(2) {
+      assert!(pair[0].value >= pair[1].value, "{:?}", results);
+      assert_ne!(pair[0].best_move, pair[1].best_move);
+    }
+    for result in &results {
+      assert_eq!(result.pv[0], result.best_move);
+      assert_eq!(result.pv.len(), 2);
+    }
+
+    // n is capped, and limited by the legal moves
+    assert_eq!(multi_pv_search(&Game::default(), 0, 10).len(), MAX_PV as usize);
+    // in check with Kg8 and Kh7
+    let game = Game::from_fen("7k/8/5Q2/8/8/8/8/K7 b - - 0 1", None, None).unwrap();
+    assert_eq!(multi_pv_search(&game, 1, 3).len(), 2);
+
+    // so is depth
+    assert_eq!(multi_pv_search(&game, u8::MAX, 1), multi_pv_search(&game, MAX_PV_DEPTH, 1));
+  }
+
+  #[test]
+  fn test_pinned_pieces() {
+    // the bishop on b4 pins the knight on c3 to the king on e1
+    let board = parse_fen("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(king_square(&board, Color::White), Some(Position::new(0, 4)));
+    assert_eq!(king_square(&board, Color::Black), Some(Position::new(7, 4)));
+    assert_eq!(
+      pinned_pieces(&board, Color::White),
+      vec![(Position::new(2, 2), Position::new(3, 1))]
+    );
+    assert!(pinned_pieces(&board, Color::Black).is_empty());
+
+    // a second piece in between breaks the pin, a rook cannot pin diagonally
+    let board = parse_fen("4k3/8/8/8/1b6/2N5/3P4/4K3 w - - 0 1").unwrap();
+    assert!(pinned_pieces(&board, Color::White).is_empty());
+    let board = parse_fen("4k3/8/8/8/1r6/2N5/8/4K3 w - - 0 1").unwrap();
+    assert!(pinned_pieces(&board, Color::White).is_empty());
+  }
+
+  #[test]
+  fn test_get_only_move() {
+    // the rook covers the g-file, so the black king can only go to h7
+    let board = parse_fen("7k/8/8/8/8/8/8/K5R1 b - - 0 1").unwrap();
+    assert_eq!(
+      board.get_only_move(),
+      Some(Move::Piece(Position::pgn("h8").unwrap(), Position::pgn("h7").unwrap()))
+    );
+
+    assert_eq!(Board::default().get_only_move(), None);
+  }
+
+  #[test]
+  fn test_move_events() {
+    let pos = |s: &str| Position::pgn(s).unwrap();
+
+    let board = Board::default();
+    assert_eq!(
+      Move::Piece(pos("e2"), pos("e4")).events(&board),
+      vec![MoveEvent::Normal {
+        from: pos("e2"),
+        to: pos("e4"),
+      }]
+    );
+    assert_eq!(Move::Resign.events(&board), vec![]);
+
+    // both sides can castle either way
+    let board = parse_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+    assert_eq!(
+      Move::KingSideCastle.events(&board),
+      vec![MoveEvent::Castle { kingside: true }]
+    );
+    assert_eq!(
+      Move::QueenSideCastle.events(&board),
+      vec![MoveEvent::Castle { kingside: false }]
+    );
+
+    // white pawn about to promote, black king out of the way
+    let board = parse_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    assert_eq!(
+      Move::Promotion(pos("a7"), pos("a8"), Piece::Knight(Color::White, pos("a8"))).events(&board),
+      vec![MoveEvent::Promotion {
+        from: pos("a7"),
+        to: pos("a8"),
+        promoted_to: Piece::Knight(Color::White, pos("a8")),
+      }]
+    );
+    // queen by default, and it checks along the back rank
+    assert_eq!(
+      Move::Piece(pos("a7"), pos("a8")).events(&board),
+      vec![
+        MoveEvent::Promotion {
+          from: pos("a7"),
+          to: pos("a8"),
+          promoted_to: Piece::Queen(Color::White, pos("a8")),
+        },
+        MoveEvent::Check,
+      ]
+    );
+
+    // black just played d7d5 next to the e5 pawn
+    let board = parse_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+    assert_eq!(
+      Move::Piece(pos("e5"), pos("d6")).events(&board),
+      vec![MoveEvent::EnPassant {
+        captured_pawn: pos("d5"),
+      }]
+    );
+
+    // fool's mate
+    let board = parse_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2").unwrap();
+    assert_eq!(
+      Move::Piece(pos("d8"), pos("h4")).events(&board),
+      vec![
+        MoveEvent::Normal {
+          from: pos("d8"),
+          to: pos("h4"),
+        },
+        MoveEvent::Checkmate,
+      ]
+    );
+  }
+
+  fn mate_pattern(fen: &str, from: &str, to: &str) -> Option<MatePattern> {
+    let board = parse_fen(fen).unwrap();
+    let mating_move = Move::Piece(Position::pgn(from).unwrap(), Position::pgn(to).unwrap());
+    classify_mate(&board, mating_move)
+  }
+
+  #[test]
+  fn test_classify_mate() {
+    assert_eq!(
+      mate_pattern("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1", "a8"),
+      Some(MatePattern::BackRank)
+    );
+    assert_eq!(
+      mate_pattern("6rk/6pp/8/6N1/8/8/8/6K1 w - - 0 1", "g5", "f7"),
+      Some(MatePattern::Smothered)
+    );
+    assert_eq!(
+      mate_pattern("8/4N1pk/8/8/8/R7/8/6K1 w - - 0 1", "a3", "h3"),
+      Some(MatePattern::Anastasia)
+    );
+    // scholar's mate is none of these
+    assert_eq!(
+      mate_pattern(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        "h5",
+        "f7"
+      ),
+      None
+    );
+    // the king escapes through h7
+    assert_eq!(mate_pattern("6k1/5pp1/8/8/8/8/8/R5K1 w - - 0 1", "a1", "a8"), None);
+  }
+
+  #[test]
+  fn test_only_king_can_move() {
+    // double check from the rook and the bishop, Rxb4 still leaves the rook's check
+    let board = parse_fen("4r1k1/8/8/8/Rb6/8/8/4K3 w - - 0 1").unwrap();
+    assert!(only_king_can_move(&board, WHITE));
+    assert!(!only_king_can_move(&board, BLACK));
+
+    // single check, the rook can block on e4
+    let board = parse_fen("4r1k1/8/8/8/R7/8/8/4K3 w - - 0 1").unwrap();
+    assert!(!only_king_can_move(&board, WHITE));
+
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert!(!only_king_can_move(&board, WHITE));
+    assert!(!only_king_can_move(&board, BLACK));
+
+    // the knight is pinned, so only the pawn can move besides the king
+    let board = parse_fen("4k3/4r3/8/8/8/P7/4N3/4K3 w - - 0 1").unwrap();
+    assert!(!only_king_can_move(&board, WHITE));
+    // until the pawn is blocked
+    let board = parse_fen("4k3/4r3/8/8/p7/P7/4N3/4K3 w - - 0 1").unwrap();
+    assert!(only_king_can_move(&board, WHITE));
+  }
+
+  #[test]
+  fn test_mobility() {
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert_eq!(mobility(&board, WHITE), 20);
+    // not black's turn, counted the same
+    assert_eq!(mobility(&board, BLACK), 20);
+
+    // 1. e4 opens the bishop and queen, black is no freer for it
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+    assert_eq!(mobility(&board, WHITE), 30);
+    assert_eq!(mobility(&board, BLACK), 20);
+
+    // cramped: the pawn chain shuts in white's bishops, the black rook
+    // and bishop on open lines have more moves with fewer pieces
+    let cramped = parse_fen("4k3/8/8/r7/2p1p1p1/1pPpPpPp/1P1P1P1P/2B1KB2 w - - 0 1").unwrap();
+    let open = parse_fen("4k3/8/8/8/8/8/8/R2BK3 w - - 0 1").unwrap();
+    assert!(mobility(&cramped, WHITE) < mobility(&open, WHITE));
+    assert!(mobility(&cramped, WHITE) < mobility(&cramped, BLACK));
+
+    // the pinned knight has no legal moves
+    let board = parse_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+    assert_eq!(mobility(&board, WHITE), 4);
+  }
+
+  #[test]
+  fn test_pawn_structure() {
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert_eq!(pawn_structure(&board, WHITE), 0);
+    assert_eq!(pawn_structure(&board, BLACK), 0);
+
+    // white's a-pawns are doubled and isolated, black's h-pawn is isolated
+    let board = parse_fen("4k3/5p1p/8/8/8/P7/P7/4K3 w - - 0 1").unwrap();
+    assert_eq!(pawn_structure(&board, WHITE), -DOUBLED_PAWN_CENTIPAWNS - 2 * ISOLATED_PAWN_CENTIPAWNS);
+    assert_eq!(pawn_structure(&board, BLACK), -2 * ISOLATED_PAWN_CENTIPAWNS);
+  }
+
+  #[test]
+  fn test_pawn_shield() {
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert_eq!(pawn_shield(&board, WHITE), 3);
+    assert_eq!(pawn_shield(&board, BLACK), 3);
+
+    // castled behind f2 and h2, the g-pawn pushed; black's king on the edge
+    let board = parse_fen("7k/6pp/8/8/8/6P1/5P1P/6K1 w - - 0 1").unwrap();
+    assert_eq!(pawn_shield(&board, WHITE), 2);
+    assert_eq!(pawn_shield(&board, BLACK), 2);
+  }
+
+  #[test]
+  fn test_is_forced_mate_in() {
+    // 1. Ra8+ has to be blocked on d8 or f8, and the rook takes the blocker with mate
+    let board = parse_fen("7k/4bppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    assert!(is_forced_mate_in(&board, 2));
+    // found past the horizon, the second move is a capture
+    assert!(is_forced_mate_in(&board, 1));
+    assert!(is_forced_mate_in(&board, u32::MAX));
+    assert!(!quiescence_mate(&board, 1));
+    assert!(quiescence_mate(&board, 2));
+
+    // the knight guards both blocking squares
+    let board = parse_fen("7k/4bppp/4n3/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    assert!(!quiescence_mate(&board, QUIESCENCE_DEPTH));
+    assert!(!quiescence_mate(&Board::default(), QUIESCENCE_DEPTH));
+
+    // stalemate is not mate
+    let board = parse_fen("7k/5KR1/8/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(!mates_against_every_reply(&board, |_| true));
+  }
+}
@@ -0,0 +1,125 @@
+//! Fixed search benchmark for measuring engine speed.
+//!
+//! The positions are the first ten of the Win at Chess (WAC) test suite.
+//! Searching them to the same depth visits the same nodes every run, so
+//! the node count only changes with the search itself and nodes per
+//! second can be compared between builds.
+//!
+//! `bench_has_legal_moves` times `Board::has_legal_moves` against listing
+//! every legal move on positions where there are none.
+#![allow(dead_code)]
+
+use std::time::Instant;
+
+use super::Evaluate;
+use crate::board::Board;
+use crate::util::parse_fen;
+
+pub const WAC_001: &str = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1";
+pub const WAC_002: &str = "8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - 0 1";
+pub const WAC_003: &str = "5rk1/1ppb3p/p1pb4/6q1/3P1p1r/2P1R2P/PP1BQ1P1/5RKN w - - 0 1";
+pub const WAC_004: &str = "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1";
+pub const WAC_005: &str = "5k2/6pp/p1qN4/1p1p4/3P4/2PKP2Q/PP3r2/3R4 b - - 0 1";
+pub const WAC_006: &str = "7k/p7/1R5K/6r1/6p1/6P1/8/8 w - - 0 1";
+pub const WAC_007: &str = "rnbqkb1r/pppp1ppp/8/4P3/6n1/7P/PPPNPPP1/R1BQKBNR b KQkq - 0 1";
+pub const WAC_008: &str = "r4q1k/p2bR1rp/2p2Q1N/5p2/5p2/2P5/PP3PPP/R5K1 w - - 0 1";
+pub const WAC_009: &str = "3q1rk1/p4pp1/2pb3p/3p4/6Pr/1PNQ4/P1PB1PP1/4RRK1 b - - 0 1";
+pub const WAC_010: &str = "2br2k1/2q3rn/p2NppQ1/2p1P3/Pp5R/4P3/1P3PPP/3R2K1 w - - 0 1";
+
+pub const POSITIONS: [&str; 10] = [
+  WAC_001, WAC_002, WAC_003, WAC_004, WAC_005, WAC_006, WAC_007, WAC_008, WAC_009, WAC_010,
+];
+
+/// Plies searched from each position.
+pub const DEPTH: i32 = 4;
+
+/// Totals over every position in the suite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+  /// Nodes searched per second.
+  pub nps: u64,
+  /// Nodes searched at `DEPTH`.
+  pub nodes_at_depth_4: u64,
+  /// Wall time of the searches.
+  pub duration_ns: u64,
+}
+
+/// Search every suite position to `DEPTH` and total the work done.
+pub fn bench_suite() -> BenchResult {
+  let start = Instant::now();
+  let mut nodes = 0;
+  for fen in POSITIONS {
+    let board = parse_fen(fen).unwrap();
+    // the root move is the first ply
+    let (_, board_count, _) = board.get_best_next_move(DEPTH - 1);
+    nodes += board_count;
+  }
+  let duration_ns = (start.elapsed().as_nanos() as u64).max(1);
+
+  BenchResult {
+    nps: (nodes as u128 * 1_000_000_000 / duration_ns as u128) as u64,
+    nodes_at_depth_4: nodes,
+    duration_ns,
+  }
+}
+
+/// Checkmates: fool's mate, scholar's mate and a back rank mate.
+pub const MATES: [&str; 3] = [
+  "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+  "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4",
+  "6k1/5ppp/8/8/8/8/5PPP/3r2K1 w - - 0 1",
+];
+
+/// Times each mate is checked, enough for the totals to be measurable.
+pub const REPEATS: u32 = 500;
+
+/// Wall time of finding out there is no legal move in every mate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LegalMovesBench {
+  /// Checking with `!generate_legal_moves().is_empty()`.
+  pub generate_ns: u64,
+  /// Checking with `has_legal_moves()`.
+  pub has_legal_moves_ns: u64,
+}
+
+/// Time both ways of asking whether the side to move has a legal move.
+pub fn bench_has_legal_moves() -> LegalMovesBench {
+  let boards = MATES.map(|fen| parse_fen(fen).unwrap());
+  let time = |has_moves: &dyn Fn(&Board) -> bool| {
+    let start = Instant::now();
+    for _ in 0..REPEATS {
+      for board in &boards {
+        assert!(!has_moves(board));
+      }
+    }
+    start.elapsed().as_nanos() as u64
+  };
+
+  LegalMovesBench {
+    generate_ns: time(&|board| !board.generate_legal_moves().is_empty()),
+    has_legal_moves_ns: time(&|board| board.has_legal_moves()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // the bar is for optimized builds, run with `cargo test --release`
+  // unoptimized the suite takes close to a minute at well under the bar
+  #[test]
+  #[cfg_attr(debug_assertions, ignore)]
+  fn engine_benchmark() {
+    let result = bench_suite();
+    assert!(result.nps > 100_000, "{:?}", result);
+  }
+
+  // wall time depends on the machine and its load, so this only runs when
+  // asked for, with `cargo test --release -- --ignored`
+  #[test]
+  #[ignore]
+  fn has_legal_moves_benchmark() {
+    let result = bench_has_legal_moves();
+    assert!(result.has_legal_moves_ns < result.generate_ns, "{:?}", result);
+  }
+}
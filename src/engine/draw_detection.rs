@@ -0,0 +1,41 @@
+//! Draw checks made inside the search.
+//!
+//! These are cheaper than the game's own draw rules. A position seen once
+//! before on the way to the current one scores as a draw, since the side
+//! that can force a repetition could repeat it again and claim.
+
+/// Plies a repetition can reach back. A hundred plies without a capture or
+/// pawn move is already a draw by the fifty-move rule.
+pub const MAX_REPETITION_PLIES: u8 = 100;
+
+/// Has `current_hash` appeared in the last `plies_from_root` entries of
+/// `hash_history`, looking back at most `MAX_REPETITION_PLIES`?
+///
+/// Two occurrences are enough, unlike the threefold repetition a player
+/// has to reach to claim a draw over the board.
+pub fn is_draw_by_repetition_in_search(current_hash: u64, hash_history: &[u64], plies_from_root: u8) -> bool {
+  let window = plies_from_root.min(MAX_REPETITION_PLIES) as usize;
+  hash_history.iter().rev().take(window).any(|hash| *hash == current_hash)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_draw_by_repetition_in_search() {
+    let history = [1, 2, 3, 4];
+    assert!(is_draw_by_repetition_in_search(1, &history, 4));
+    assert!(is_draw_by_repetition_in_search(4, &history, 1));
+    // outside the window
+    assert!(!is_draw_by_repetition_in_search(1, &history, 3));
+    assert!(!is_draw_by_repetition_in_search(4, &history, 0));
+    assert!(!is_draw_by_repetition_in_search(5, &history, u8::MAX));
+
+    // never further back than the fifty-move rule allows
+    let mut history = vec![7];
+    history.extend(8..8 + MAX_REPETITION_PLIES as u64);
+    assert!(!is_draw_by_repetition_in_search(7, &history, u8::MAX));
+    assert!(is_draw_by_repetition_in_search(8, &history, u8::MAX));
+  }
+}
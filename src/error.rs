@@ -1,3 +1,4 @@
+use crate::cwchess::CwChessIllegalMove;
 use cosmwasm_std::StdError;
 use thiserror::Error;
 
@@ -10,24 +11,110 @@ pub enum ContractError {
 
   // Add any other custom errors you like here.
   // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+  #[error("already subscribed")]
+  AlreadySubscribed {},
+  #[error("block limit too short")]
+  BlockLimitTooShort {},
   #[error("cannot play self")]
   CannotPlaySelf {},
   #[error("challenge not found")]
   ChallengeNotFound {},
-  #[error("game already over")]
-  GameAlreadyOver {},
-  #[error("game not found")]
-  GameNotFound {},
+  #[error("draw not allowed before ply {min_plies}")]
+  DrawTooEarly { min_plies: u64 },
+  #[error("game {game_id} already over ({status})")]
+  GameAlreadyOver { game_id: u64, status: String },
+  #[error("game {game_id} not found")]
+  GameNotFound { game_id: u64 },
+  #[error("game starts at block {start_at}")]
+  GameNotStarted { start_at: u64 },
   #[error("game not timed out")]
   GameNotTimedOut {},
-  #[error("invalid move")]
-  InvalidMove {},
+  #[error("illegal move {notation}: {reason}")]
+  IllegalMove { notation: String, reason: CwChessIllegalMove },
+  #[error("invalid adjudication settings: {reason}")]
+  InvalidAdjudicationSettings { reason: String },
+  #[error("invalid fen: {reason}")]
+  InvalidFen { reason: String },
+  #[error("invalid k schedule: {reason}")]
+  InvalidKSchedule { reason: String },
+  #[error("invalid move notation {notation}")]
+  InvalidMoveNotation { notation: String },
   #[error("invalid position")]
   InvalidPosition {},
+  #[error("invalid rate limit, at least 1 message per block")]
+  InvalidRateLimit {},
+  #[error("invalid rating")]
+  InvalidRating {},
+  #[error("move confirmation expired at block {expired_at}")]
+  MoveConfirmationExpired { expired_at: u64 },
+  #[error("no pending move")]
+  NoPendingMove {},
+  #[error("note already set")]
+  NoteAlreadySet {},
+  #[error("note too long, at most {max} characters")]
+  NoteTooLong { max: u64 },
+  #[error("not subscribed")]
+  NotSubscribed {},
+  #[error("notes not expired")]
+  NotesNotExpired {},
   #[error("not your challenge")]
   NotYourChallenge {},
-  #[error("not your turn")]
-  NotYourTurn {},
+  #[error("not {player}'s turn in game {game_id}")]
+  NotYourTurn { game_id: u64, player: String },
+  #[error("pgn too long ({length} bytes)")]
+  PgnTooLong { length: u64 },
+  #[error("the phantom does not agree to draws")]
+  PhantomDraw {},
+  #[error("player already has games")]
+  PlayerHasGames {},
+  #[error("position already locked")]
+  PositionLocked {},
+  #[error("position not locked")]
+  PositionNotLocked {},
+  #[error("rate limit exceeded, at most {max} messages per block")]
+  RateLimitExceeded { max: u8 },
+  #[error("rating already set")]
+  RatingAlreadySet {},
+  #[error("team sizes differ by more than {max_difference}")]
+  TeamSizeImbalance { max_difference: u64 },
+  #[error("too many subscribers, at most {max}")]
+  TooManySubscribers { max: u64 },
+  #[error("cannot migrate from contract {contract}")]
+  WrongContract { contract: String },
   #[error("game timed out")]
   GameTimedOut {},
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_error_messages() {
+    let error = ContractError::GameAlreadyOver {
+      game_id: 3,
+      status: "WhiteCheckmates".to_string(),
+    };
+    assert_eq!(error.to_string(), "game 3 already over (WhiteCheckmates)");
+
+    let error = ContractError::GameNotFound { game_id: 7 };
+    assert_eq!(error.to_string(), "game 7 not found");
+
+    let error = ContractError::IllegalMove {
+      notation: "Nc4".to_string(),
+      reason: CwChessIllegalMove::AmbiguousMove,
+    };
+    assert_eq!(error.to_string(), "illegal move Nc4: ambiguous move");
+
+    let error = ContractError::InvalidFen {
+      reason: "wrong number of spaces".to_string(),
+    };
+    assert_eq!(error.to_string(), "invalid fen: wrong number of spaces");
+
+    let error = ContractError::NotYourTurn {
+      game_id: 1,
+      player: "white".to_string(),
+    };
+    assert_eq!(error.to_string(), "not white's turn in game 1");
+  }
+}
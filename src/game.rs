@@ -1,6 +1,19 @@
-use crate::board::Board;
-use crate::util::{format_fen, parse_fen, parse_san_move};
-use crate::engine::{Color, GameResult};
+pub mod adjudication;
+pub mod notation;
+pub mod opening;
+pub mod stalemate_trap;
+
+use crate::board::{Board, BoardBuilder};
+use crate::util::{format_fen, format_san_move, parse_fen, parse_san_move};
+use crate::engine::{multi_pv_search, Color, Evaluate, GameResult, Move, MoveEvent};
+use crate::piece::Piece;
+use crate::position::Position;
+#[cfg(any(test, feature = "json-debug"))]
+use crate::cwchess::illegal_move;
+#[cfg(any(test, feature = "json-debug"))]
+use crate::error::ContractError;
+#[cfg(any(test, feature = "json-debug"))]
+use cosmwasm_std::StdError;
 
 pub enum GameAction {
   // accept draw if previous action was OfferDraw
@@ -32,6 +45,52 @@ pub enum GameError {
   InvalidPosition,
 }
 
+impl core::fmt::Display for GameError {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+    match self {
+      GameError::AmbiguousMove => write!(f, "ambiguous move"),
+      GameError::GameAlreadyOver => write!(f, "game already over"),
+      GameError::InvalidMove => write!(f, "not a legal move"),
+      GameError::InvalidPosition => write!(f, "invalid position"),
+    }
+  }
+}
+
+// map a parse_san_move error, keeping ambiguity distinct
+fn san_error(error: String) -> GameError {
+  match error.as_str() {
+    "ambiguous move" => GameError::AmbiguousMove,
+    _ => GameError::InvalidMove,
+  }
+}
+
+// why a from/to move is not legal for the current turn
+#[derive(Debug, PartialEq)]
+pub enum IllegalMoveReason {
+  // no piece on the from square
+  NoPiece,
+  // piece on the from square belongs to the opponent
+  NotYourPiece,
+  // to square holds one of the mover's own pieces
+  OwnPieceOnTarget,
+  // piece does not move that way, even on an empty board
+  PieceCannotReach,
+  // another piece is in the way
+  PathBlocked,
+  // move would leave the mover's king in check
+  LeavesKingInCheck,
+  // king or rook has already moved (or rook is missing)
+  CastleRightsLost,
+  // cannot castle while in check
+  CastleOutOfCheck,
+  // king would pass through or land on an attacked square
+  CastleThroughCheck,
+  // pawn captures onto an empty square beside an enemy pawn that did not just advance two
+  EnPassantNotAvailable,
+  // promotion piece given for a move that does not promote, or a king
+  InvalidPromotion,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum GameOver {
   WhiteCheckmates,
@@ -40,19 +99,58 @@ pub enum GameOver {
   BlackResigns,
   Stalemate,
   DrawAccepted,
+  WhiteWinsByAdjudication,
+  BlackWinsByAdjudication,
+}
+
+// move found by Game::top_moves
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopMove {
+  // san, e.g. "Nf3"
+  pub algebraic: String,
+  // centipawns for the side to move
+  pub evaluation: i32,
+  // expected line in san, starting with the move
+  pub pv: Vec<String>,
+  // from/to squares, e.g. "g1f3"
+  pub uci: String,
 }
 
+// moves as (from, to) squares, see Game::legal_moves
+pub type MoveSquares = Vec<(Position, Position)>;
+
 // wrapper around chess_engine::Board
 //
 // abstractions for two player games, like offering/accepting a draw.
 // status is Some when the game is over.
-#[derive(Default)]
+#[derive(Debug, PartialEq)]
 pub struct Game {
   pub board: Board,
   pub draw_offered: Option<Color>,
+  // moves played since the game was created or loaded, with the board
+  // each was played on
+  pub history: Vec<(Board, Move)>,
+  // board before the first move, standard unless the game started from a custom fen
+  pub starting_board: Board,
+  // non-king material of starting_board, see remaining_material_ratio
+  pub starting_material: u32,
   pub status: Option<GameOver>,
 }
 
+impl Default for Game {
+  fn default() -> Self {
+    let board = Board::starting_position();
+    Game {
+      board,
+      draw_offered: None,
+      history: vec![],
+      starting_board: board,
+      starting_material: board.get_non_king_material(),
+      status: None,
+    }
+  }
+}
+
 impl Game {
   pub fn from_fen(
     fen: &str,
@@ -68,10 +166,52 @@ impl Game {
     Ok(Game {
       board,
       draw_offered,
+      history: vec![],
+      starting_board: board,
+      starting_material: board.get_non_king_material(),
       status,
     })
   }
 
+  // game at fen that started from start_fen, or the standard position when
+  // None, history is not replayed
+  pub fn from_starting_fen(
+    start_fen: Option<&str>,
+    fen: &str,
+    draw_offered: Option<Color>,
+    status: Option<GameOver>,
+  ) -> Result<Self, GameError> {
+    let starting_board = match start_fen {
+      Some(start_fen) => parse_fen(start_fen).map_err(|_| GameError::InvalidPosition)?,
+      None => Board::starting_position(),
+    };
+    let game = Game::from_fen(fen, draw_offered, status)?;
+    Ok(Game {
+      starting_board,
+      starting_material: starting_board.get_non_king_material(),
+      ..game
+    })
+  }
+
+  // board at move 0, e.g. for SetUp and FEN tags or replaying the game
+  pub fn starting_position(&self) -> &Board {
+    &self.starting_board
+  }
+
+  // did the game start from the standard position?
+  pub fn is_standard_start(&self) -> bool {
+    self.starting_board == Board::starting_position()
+  }
+
+  // non-king material left as a percentage of the start, 100 at the
+  // start and 0 with only kings, capped at 100 after promotions
+  pub fn remaining_material_ratio(&self) -> u8 {
+    if self.starting_material == 0 {
+      return 0;
+    }
+    (self.board.get_non_king_material() * 100 / self.starting_material).min(100) as u8
+  }
+
   pub fn to_fen(&self, halfmove_clock: u8, fullmove_number: u8) -> Result<String, String> {
     format_fen(&self.board, halfmove_clock, fullmove_number)
   }
@@ -92,14 +232,186 @@ impl Game {
     }
     let chess_move = match parse_san_move(&self.board, move_str) {
       Ok(chess_move) => chess_move,
-      Err(_) => {
-        return Err(GameError::InvalidMove {});
+      Err(error) => {
+        return Err(san_error(error));
       }
     };
     
     Ok(self.board.is_legal_move(chess_move, self.get_turn_color()))
   }
 
+  // events triggered by a san move for current turn, without playing it
+  pub fn move_events(&self, move_str: &str) -> Result<Vec<MoveEvent>, GameError> {
+    match parse_san_move(&self.board, move_str) {
+      Ok(chess_move) => Ok(chess_move.events(&self.board)),
+      Err(error) => Err(san_error(error)),
+    }
+  }
+
+  // would the move stalemate the opponent
+  pub fn stalemate_risk(&self, move_str: &str) -> Result<bool, GameError> {
+    match parse_san_move(&self.board, move_str) {
+      Ok(chess_move) => Ok(stalemate_trap::check(self, chess_move)),
+      Err(error) => Err(san_error(error)),
+    }
+  }
+
+  // legal moves for current turn as (from, to) squares
+  // castling is listed as the king's move
+  pub fn legal_moves(&self) -> Vec<(Position, Position)> {
+    self.move_squares(self.board.get_legal_moves(), self.get_turn_color())
+  }
+
+  // legal moves for the piece on `pos`, listed like legal_moves
+  pub fn legal_moves_from(&self, pos: Position) -> Vec<(Position, Position)> {
+    self.move_squares(self.board.generate_legal_moves_from(pos), self.get_turn_color())
+  }
+
+  // (pseudo-legal, legal) moves for the piece on `pos` of either color,
+  // listed like legal_moves, see Board::generate_moves_for_piece
+  pub fn piece_moves(&self, pos: Position) -> (MoveSquares, MoveSquares) {
+    let color = match self.board.get_piece(pos) {
+      Some(piece) => piece.get_color(),
+      None => return (vec![], vec![]),
+    };
+    let (pseudo_legal, legal) = self.board.generate_moves_for_piece(pos);
+    (self.move_squares(pseudo_legal, color), self.move_squares(legal, color))
+  }
+
+  fn move_squares(&self, moves: Vec<Move>, color: Color) -> Vec<(Position, Position)> {
+    if self.status.is_some() {
+      return vec![];
+    }
+    let king = Position::king_pos(color);
+    moves
+      .into_iter()
+      .filter_map(|legal_move| match legal_move {
+        Move::Piece(from, to) => Some((from, to)),
+        // promotions are listed once per piece, keep one
+        Move::Promotion(from, to, piece) if piece.is_queen() => Some((from, to)),
+        Move::Promotion(..) => None,
+        Move::KingSideCastle => Some((king, king.next_right().next_right())),
+        Move::QueenSideCastle => Some((king, king.next_left().next_left())),
+        Move::Resign => None,
+      })
+      .collect()
+  }
+
+  // legal moves for current turn as (coordinates, san), e.g. ("g1f3", "Nf3")
+  // castling uses the king's move, promotions are listed once per piece
+  // with the piece appended to the coordinates, e.g. ("e7e8n", "e8N")
+  pub fn legal_moves_san(&self) -> Vec<(String, String)> {
+    if self.status.is_some() {
+      return vec![];
+    }
+    self.board
+      .get_legal_moves()
+      .into_iter()
+      .filter_map(|legal_move| {
+        uci_move(&self.board, legal_move)
+          .map(|coordinates| (coordinates, format_san_move(&self.board, legal_move)))
+      })
+      .collect()
+  }
+
+  // (coordinates, san) of the side to move's only legal move, if it has
+  // exactly one
+  pub fn only_move_san(&self) -> Option<(String, String)> {
+    if self.status.is_some() {
+      return None;
+    }
+    let only_move = self.board.get_only_move()?;
+    uci_move(&self.board, only_move).map(|coordinates| (coordinates, format_san_move(&self.board, only_move)))
+  }
+
+  // the n best moves for the side to move, see engine::multi_pv_search
+  pub fn top_moves(&self, depth: u8, n: u8) -> Vec<TopMove> {
+    if self.status.is_some() {
+      return vec![];
+    }
+    multi_pv_search(self, depth, n)
+      .into_iter()
+      .map(|result| {
+        let mut board = self.board;
+        let pv = result
+          .pv
+          .iter()
+          .map(|pv_move| {
+            let san = format_san_move(&board, *pv_move);
+            board = board.apply_eval_move(*pv_move);
+            san
+          })
+          .collect();
+        TopMove {
+          algebraic: format_san_move(&self.board, result.best_move),
+          evaluation: result.value,
+          pv,
+          uci: uci_move(&self.board, result.best_move).unwrap_or_default(),
+        }
+      })
+      .collect()
+  }
+
+  // most recent move played on this game, None before the first move
+  pub fn last_move(&self) -> Option<Move> {
+    self.history.last().map(|(_, chess_move)| *chess_move)
+  }
+
+  // last_move in san, e.g. "Nf3"
+  pub fn last_move_algebraic(&self) -> Option<String> {
+    self.history.last().map(|(board, chess_move)| format_san_move(board, *chess_move))
+  }
+
+  // every move played since the game was created or loaded, see pack_move
+  pub fn packed_moves(&self) -> Vec<u16> {
+    self
+      .history
+      .iter()
+      .filter_map(|(board, chess_move)| pack_move(board, *chess_move))
+      .collect()
+  }
+
+  // last_move as from/to squares, e.g. "g1f3"
+  pub fn last_move_uci(&self) -> Option<String> {
+    self.history.last().and_then(|(board, chess_move)| uci_move(board, *chess_move))
+  }
+
+  // piece taken by last_move, including pawns taken en passant
+  pub fn last_captured_piece(&self) -> Option<Piece> {
+    let (board, chess_move) = self.history.last()?;
+    let (from, to) = match *chess_move {
+      Move::Piece(from, to) | Move::Promotion(from, to, _) => (from, to),
+      _ => return None,
+    };
+    match (board.get_piece(from), board.get_piece(to)) {
+      (_, Some(captured)) => Some(captured),
+      (Some(Piece::Pawn(color, _)), None) if from.get_col() != to.get_col() => {
+        board.get_piece(to.pawn_back(color))
+      }
+      _ => None,
+    }
+  }
+
+  // explain why moving the piece on `from` to `to` is illegal for current turn
+  // returns None when the move is legal
+  pub fn explain_move(
+    &self,
+    from: &str,
+    to: &str,
+  ) -> Result<Option<IllegalMoveReason>, GameError> {
+    if self.status.is_some() {
+      return Err(GameError::GameAlreadyOver {});
+    }
+    let (from, to) = match (Position::pgn(from), Position::pgn(to)) {
+      (Ok(from), Ok(to)) if from.is_on_board() && to.is_on_board() => (from, to),
+      _ => {
+        return Err(GameError::InvalidMove {});
+      }
+    };
+
+    Ok(move_reason(&self.board, from, to))
+  }
+
   // make a move for current turn
   pub fn make_move(&mut self, action: &GameAction) -> Result<&Option<GameOver>, GameError> {
     if self.status.is_some() {
@@ -132,8 +444,8 @@ impl Game {
   ) -> Result<&Option<GameOver>, GameError> {
     let chess_move = match parse_san_move(&self.board, movestr) {
       Ok(chess_move) => chess_move,
-      Err(_) => {
-        return Err(GameError::InvalidMove {});
+      Err(error) => {
+        return Err(san_error(error));
       }
     };
 
@@ -141,6 +453,7 @@ impl Game {
       true => Some(self.get_turn_color()),
       false => None,
     };
+    let board_before = self.board;
     self.status = match self.board.play_move(chess_move) {
       GameResult::Continuing(board) => {
         self.board = board;
@@ -155,6 +468,7 @@ impl Game {
         Color::White => Some(GameOver::WhiteCheckmates),
       },
     };
+    self.history.push((board_before, chess_move));
     Ok(&self.status)
   }
 
@@ -168,9 +482,324 @@ impl Game {
   }
 }
 
+// readable form of a game for state inspection tools
+// the game is rebuilt by replaying moves from start_fen, and fen checks
+// the replay reached the same board
+#[cfg(any(test, feature = "json-debug"))]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameJson {
+  current_player: String,
+  draw_offered: Option<String>,
+  fen: String,
+  moves: Vec<String>,
+  start_fen: String,
+  status: Option<String>,
+}
+
+#[cfg(any(test, feature = "json-debug"))]
+impl Game {
+  // debug serialization, move counters are not tracked and written as 0 1
+  pub fn to_json(&self) -> String {
+    let game = GameJson {
+      current_player: self.get_turn_color().to_string(),
+      draw_offered: self.draw_offered.map(|color| color.to_string()),
+      fen: format_fen(&self.board, 0, 1).unwrap_or_default(),
+      moves: self
+        .history
+        .iter()
+        .map(|(board, chess_move)| format_san_move(board, *chess_move))
+        .collect(),
+      start_fen: format_fen(&self.starting_board, 0, 1).unwrap_or_default(),
+      status: self.status.as_ref().map(|status| format!("{:?}", status)),
+    };
+    serde_json_wasm::to_string(&game).unwrap_or_default()
+  }
+
+  pub fn from_json(s: &str) -> Result<Game, ContractError> {
+    let json: GameJson =
+      serde_json_wasm::from_str(s).map_err(|e| StdError::parse_err("Game", e))?;
+    let mut game = Game::from_fen(&json.start_fen, None, None).map_err(|e| {
+      ContractError::InvalidFen {
+        reason: e.to_string(),
+      }
+    })?;
+    for move_str in &json.moves {
+      if let Err(e) = game.make_move(&GameAction::MakeMove(move_str.clone())) {
+        return Err(illegal_move(&game, move_str, e));
+      }
+    }
+
+    let invalid = |reason: &str| ContractError::InvalidFen {
+      reason: reason.to_string(),
+    };
+    if format_fen(&game.board, 0, 1).ok().as_ref() != Some(&json.fen) {
+      return Err(invalid("moves do not reach fen"));
+    }
+    if game.get_turn_color().to_string() != json.current_player {
+      return Err(invalid("wrong current player"));
+    }
+    let color = |name: &str| match name {
+      "White" => Ok(Color::White),
+      "Black" => Ok(Color::Black),
+      _ => Err(invalid("invalid color")),
+    };
+    game.draw_offered = json.draw_offered.as_deref().map(color).transpose()?;
+    let status = match json.status.as_deref() {
+      None => None,
+      Some("WhiteCheckmates") => Some(GameOver::WhiteCheckmates),
+      Some("WhiteResigns") => Some(GameOver::WhiteResigns),
+      Some("BlackCheckmates") => Some(GameOver::BlackCheckmates),
+      Some("BlackResigns") => Some(GameOver::BlackResigns),
+      Some("Stalemate") => Some(GameOver::Stalemate),
+      Some("DrawAccepted") => Some(GameOver::DrawAccepted),
+      Some("WhiteWinsByAdjudication") => Some(GameOver::WhiteWinsByAdjudication),
+      Some("BlackWinsByAdjudication") => Some(GameOver::BlackWinsByAdjudication),
+      Some(_) => return Err(invalid("invalid status")),
+    };
+    // checkmate and stalemate come from the moves, other results are kept
+    if game.status.is_some() && game.status != status {
+      return Err(invalid("status does not match moves"));
+    }
+    game.status = status;
+    Ok(game)
+  }
+}
+
+// a move played on `board` in 16 bits: the from square (row * 8 + col) in
+// bits 0-5, the to square in bits 6-11 and the promotion piece in bits 12-14,
+// 0 for none, then knight, bishop, rook and queen
+// castling is the king's move, as in uci_move
+pub fn pack_move(board: &Board, chess_move: Move) -> Option<u16> {
+  let square = |pos: Position| (pos.get_row() * 8 + pos.get_col()) as u16;
+  let king = Position::king_pos(board.get_turn_color());
+  let (from, to, promotion) = match chess_move {
+    Move::Piece(from, to) => match board.get_piece(from) {
+      // pawns reaching the last rank become queens by default
+      Some(piece) if piece.is_promoting_pawn() => (from, to, 4),
+      _ => (from, to, 0),
+    },
+    Move::Promotion(from, to, piece) => (
+      from,
+      to,
+      match piece {
+        Piece::Knight(_, _) => 1,
+        Piece::Bishop(_, _) => 2,
+        Piece::Rook(_, _) => 3,
+        _ => 4,
+      },
+    ),
+    Move::KingSideCastle => (king, king.next_right().next_right(), 0),
+    Move::QueenSideCastle => (king, king.next_left().next_left(), 0),
+    Move::Resign => return None,
+  };
+  Some(square(from) | square(to) << 6 | promotion << 12)
+}
+
+// the legal move on `board` that pack_move packs to `packed`
+// clients decode replays themselves, this checks the packing in tests
+#[cfg(test)]
+pub fn unpack_move(board: &Board, packed: u16) -> Option<Move> {
+  board
+    .get_legal_moves()
+    .into_iter()
+    .find(|legal_move| pack_move(board, *legal_move) == Some(packed))
+}
+
+// from/to squares of a move played on `board`, e.g. "e2e4"
+// castling is the king's move and promotions add the piece, e.g. "e7e8n"
+fn uci_move(board: &Board, chess_move: Move) -> Option<String> {
+  let king = Position::king_pos(board.get_turn_color());
+  match chess_move {
+    Move::Piece(from, to) => match board.get_piece(from) {
+      // pawns reaching the last rank become queens by default
+      Some(piece) if piece.is_promoting_pawn() => Some(format!("{}{}q", from, to)),
+      _ => Some(format!("{}{}", from, to)),
+    },
+    Move::Promotion(from, to, piece) => Some(format!(
+      "{}{}{}",
+      from,
+      to,
+      match piece {
+        Piece::Rook(_, _) => 'r',
+        Piece::Bishop(_, _) => 'b',
+        Piece::Knight(_, _) => 'n',
+        _ => 'q',
+      }
+    )),
+    Move::KingSideCastle => Some(format!("{}{}", king, king.next_right().next_right())),
+    Move::QueenSideCastle => Some(format!("{}{}", king, king.next_left().next_left())),
+    Move::Resign => None,
+  }
+}
+
+// why a from/to move is not legal on `board` for the side to move
+// None when it is legal
+fn move_reason(board: &Board, from: Position, to: Position) -> Option<IllegalMoveReason> {
+  let color = board.get_turn_color();
+  let piece = match board.get_piece(from) {
+    None => return Some(IllegalMoveReason::NoPiece),
+    Some(piece) if piece.get_color() != color => {
+      return Some(IllegalMoveReason::NotYourPiece);
+    }
+    Some(piece) => piece,
+  };
+
+  // king moving two files along its home rank is a castle
+  if piece.is_king()
+    && from == Position::king_pos(color)
+    && to.get_row() == from.get_row()
+    && (to.get_col() - from.get_col()).abs() == 2
+  {
+    let kingside = to.get_col() > from.get_col();
+    let castle = match kingside {
+      true => Move::KingSideCastle,
+      false => Move::QueenSideCastle,
+    };
+    if board.is_legal_move(castle, color) {
+      return None;
+    }
+    let rights = board.get_castling_rights(color);
+    let (has_right, rook_col, between) = match kingside {
+      true => (rights.can_kingside_castle(), 7, 5..7),
+      false => (rights.can_queenside_castle(), 0, 1..4),
+    };
+    let rook_pos = Position::new(from.get_row(), rook_col);
+    if !has_right || !matches!(board.get_piece(rook_pos), Some(rook) if rook.is_rook()) {
+      return Some(IllegalMoveReason::CastleRightsLost);
+    }
+    if between
+      .map(|col| Position::new(from.get_row(), col))
+      .any(|pos| board.has_piece(pos))
+    {
+      return Some(IllegalMoveReason::PathBlocked);
+    }
+    if board.is_in_check(color) {
+      return Some(IllegalMoveReason::CastleOutOfCheck);
+    }
+    return Some(IllegalMoveReason::CastleThroughCheck);
+  }
+
+  if board.is_legal_move(Move::Piece(from, to), color) {
+    return None;
+  }
+  if board.has_ally_piece(to, color) {
+    return Some(IllegalMoveReason::OwnPieceOnTarget);
+  }
+  if piece.is_legal_move(to, board) {
+    // piece can get there, so the only problem is the king
+    return Some(IllegalMoveReason::LeavesKingInCheck);
+  }
+  // diagonal pawn step onto an empty square, past an enemy pawn
+  let beside = Position::new(from.get_row(), to.get_col());
+  if piece.is_pawn()
+    && to.get_row() == from.pawn_up(color).get_row()
+    && (to.get_col() - from.get_col()).abs() == 1
+    && !board.has_piece(to)
+    && matches!(board.get_piece(beside), Some(pawn) if pawn.is_pawn() && pawn.get_color() != color)
+  {
+    return Some(IllegalMoveReason::EnPassantNotAvailable);
+  }
+  // try the same piece alone on an empty board
+  let lone = BoardBuilder::default().piece(piece).build();
+  match piece.is_legal_move(to, &lone) {
+    true => Some(IllegalMoveReason::PathBlocked),
+    false => Some(IllegalMoveReason::PieceCannotReach),
+  }
+}
+
+// why a SAN move is not legal for the current turn
+// None when it is legal, or only ambiguous or unreadable
+pub fn diagnose_illegal_move(game: &Game, san: &str) -> Option<IllegalMoveReason> {
+  let board = &game.board;
+  let color = game.get_turn_color();
+  let san = san.trim_end_matches(['+', '#']);
+  let king = Position::king_pos(color);
+  let castle_to = match san {
+    "0-0" | "O-O" => Some(king.next_right().next_right()),
+    "0-0-0" | "O-O-O" => Some(king.next_left().next_left()),
+    _ => None,
+  };
+  if let Some(to) = castle_to {
+    return match board.get_piece(king) {
+      Some(piece) if piece.is_king() && piece.get_color() == color => move_reason(board, king, to),
+      // king has left its home square
+      _ => Some(IllegalMoveReason::CastleRightsLost),
+    };
+  }
+  if !san.is_ascii() {
+    return None;
+  }
+
+  // [piece][file][rank][x]to[=][promotion]
+  let (san, promotion) = match san.as_bytes().last() {
+    Some(b'Q' | b'R' | b'B' | b'N' | b'K') if san.len() > 2 => {
+      (san[..san.len() - 1].trim_end_matches('='), san.as_bytes().last().copied())
+    }
+    _ => (san, None),
+  };
+  if san.len() < 2 {
+    return None;
+  }
+  let to = match Position::pgn(&san[san.len() - 2..]) {
+    Ok(to) if to.is_on_board() => to,
+    _ => return None,
+  };
+  let source = &san[..san.len() - 2];
+  let (name, source) = match source.as_bytes().first() {
+    Some(b'K') => ("king", &source[1..]),
+    Some(b'Q') => ("queen", &source[1..]),
+    Some(b'R') => ("rook", &source[1..]),
+    Some(b'B') => ("bishop", &source[1..]),
+    Some(b'N') => ("knight", &source[1..]),
+    _ => ("pawn", source),
+  };
+  let mut column = source.bytes().find(|c| (b'a'..=b'h').contains(c)).map(|c| (c - b'a') as i32);
+  let row = source.bytes().find(|c| (b'1'..=b'8').contains(c)).map(|c| (c - b'1') as i32);
+  if name == "pawn" && column.is_none() {
+    // pawn moves without a file stay on the file
+    column = Some(to.get_col());
+  }
+
+  let mut reasons = vec![];
+  for pos in (0..8).flat_map(|row| (0..8).map(move |col| Position::new(row, col))) {
+    let piece = match board.get_piece(pos) {
+      Some(piece) if piece.get_name() == name && piece.get_color() == color => piece,
+      _ => continue,
+    };
+    if matches!(column, Some(col) if col != pos.get_col())
+      || matches!(row, Some(row) if row != pos.get_row())
+    {
+      continue;
+    }
+    let reason = match promotion {
+      Some(b'K') => Some(IllegalMoveReason::InvalidPromotion),
+      Some(_) if !piece.is_pawn() || (to.get_row() != 0 && to.get_row() != 7) => {
+        Some(IllegalMoveReason::InvalidPromotion)
+      }
+      _ => move_reason(board, pos, to),
+    };
+    match reason {
+      // a piece can make the move, so it failed for another reason
+      None => return None,
+      Some(reason) => reasons.push(reason),
+    }
+  }
+  match reasons.is_empty() {
+    true => Some(IllegalMoveReason::NoPiece),
+    // the piece that got closest, e.g. blocked rather than out of reach
+    false => reasons
+      .into_iter()
+      .reduce(|closest, reason| match closest {
+        IllegalMoveReason::PieceCannotReach => reason,
+        closest => closest,
+      }),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::cwchess::DEFAULT_FEN;
   use crate::piece::Piece;
   use crate::position::Position;
 
@@ -196,6 +825,72 @@ mod tests {
     assert_eq!(game.status, Some(GameOver::BlackCheckmates));
   }
 
+  #[test]
+  fn test_pack_move() {
+    let pos = |square: &str| Position::pgn(square).unwrap();
+    let board = Board::default();
+    assert_eq!(pack_move(&board, Move::Piece(pos("e2"), pos("e4"))), Some(12 | 28 << 6));
+    assert_eq!(pack_move(&board, Move::Resign), None);
+
+    // every legal move packs to a different value and unpacks to itself
+    let board = parse_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let legal_moves = board.get_legal_moves();
+    let mut packed: Vec<u16> = legal_moves.iter().map(|m| pack_move(&board, *m).unwrap()).collect();
+    for (legal_move, packed_move) in legal_moves.iter().zip(&packed) {
+      assert_eq!(unpack_move(&board, *packed_move), Some(*legal_move));
+    }
+    packed.sort_unstable();
+    packed.dedup();
+    assert_eq!(packed.len(), legal_moves.len());
+
+    // e1 to g1
+    assert_eq!(pack_move(&board, Move::KingSideCastle), Some(4 | 6 << 6));
+    assert_eq!(
+      pack_move(&board, Move::Promotion(pos("b7"), pos("a8"), Piece::Knight(Color::White, pos("a8")))),
+      Some(49 | 56 << 6 | 1 << 12)
+    );
+    assert_eq!(unpack_move(&board, 12 | 28 << 6), None);
+  }
+
+  #[test]
+  fn test_last_move() {
+    let mut game = Game::default();
+    assert_eq!(game.last_move(), None);
+    assert_eq!(game.last_move_algebraic(), None);
+    assert_eq!(game.last_move_uci(), None);
+    assert_eq!(game.last_captured_piece(), None);
+
+    for game_move in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"] {
+      game.make_move(&GameAction::from(game_move)).expect(game_move);
+    }
+    assert_eq!(
+      game.last_move(),
+      Some(Move::Piece(Position::pgn("a7").unwrap(), Position::pgn("a6").unwrap()))
+    );
+    assert_eq!(game.last_captured_piece(), None);
+
+    game.make_move(&GameAction::from("Bc6")).expect("Bc6");
+    assert_eq!(game.last_move_algebraic(), Some("Bxc6".to_string()));
+    assert_eq!(game.last_move_uci(), Some("b5c6".to_string()));
+    assert!(matches!(game.last_captured_piece(), Some(Piece::Knight(Color::Black, _))));
+
+    // castling is the king's move, resigning is not a move
+    game.make_move(&GameAction::from("dxc6")).expect("dxc6");
+    game.make_move(&GameAction::from("0-0")).expect("0-0");
+    assert_eq!(game.last_move_algebraic(), Some("0-0".to_string()));
+    assert_eq!(game.last_move_uci(), Some("e1g1".to_string()));
+    game.make_move(&GameAction::Resign).unwrap();
+    assert_eq!(game.last_move_uci(), Some("e1g1".to_string()));
+  }
+
+  #[test]
+  fn test_ambiguous_move() {
+    let mut game = Game::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", None, None).unwrap();
+    assert_eq!(game.make_move(&GameAction::from("Nd2")), Err(GameError::AmbiguousMove));
+    assert_eq!(game.make_move(&GameAction::from("Nd3")), Err(GameError::InvalidMove));
+    assert!(game.make_move(&GameAction::from("Nbd2")).is_ok());
+  }
+
   #[test]
   fn test_promotion() {
     let mut game = Game::default();
@@ -214,4 +909,276 @@ mod tests {
       Some(Piece::Rook(Color::White, Position::pgn("c8").unwrap()))
     )
   }
+
+  #[test]
+  fn test_legal_moves() {
+    let game = Game::default();
+    let moves = game.legal_moves();
+    assert_eq!(moves.len(), 20);
+    assert!(moves.contains(&(Position::pgn("e2").unwrap(), Position::pgn("e4").unwrap())));
+
+    // both castles are listed as king moves
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", None, None).unwrap();
+    let moves = game.legal_moves();
+    assert!(moves.contains(&(Position::pgn("e1").unwrap(), Position::pgn("g1").unwrap())));
+    assert!(moves.contains(&(Position::pgn("e1").unwrap(), Position::pgn("c1").unwrap())));
+  }
+
+  #[test]
+  fn test_legal_moves_san() {
+    // knights on b1 and f1 share d2, rooks on a1 and a5 share a2-a4
+    let game = Game::from_fen("1k6/4P3/8/R7/8/8/8/RN3N1K w - - 0 1", None, None).unwrap();
+    let moves = game.legal_moves_san();
+    for expected in [
+      ("b1d2", "Nbd2"),
+      ("f1d2", "Nfd2"),
+      ("a1a3", "R1a3"),
+      ("a5a3", "R5a3"),
+      ("b1a3", "Na3"),
+      ("a5b5", "Rb5+"),
+      ("e7e8q", "e8Q+"),
+      ("e7e8n", "e8N"),
+    ] {
+      assert!(
+        moves.contains(&(expected.0.to_string(), expected.1.to_string())),
+        "missing {:?}",
+        expected
+      );
+    }
+
+    // every san is unique and parses back to its own move
+    for (coordinates, san) in &moves {
+      assert_eq!(moves.iter().filter(|(_, other)| other == san).count(), 1, "{}", san);
+      let (from, to) = match parse_san_move(&game.board, san).expect(san) {
+        Move::Piece(from, to) | Move::Promotion(from, to, _) => (from, to),
+        other => panic!("unexpected move {:?}", other),
+      };
+      assert_eq!(&format!("{}{}", from, to), &coordinates[..4]);
+    }
+  }
+
+  #[test]
+  fn test_explain_move() {
+    let game = Game::default();
+    assert_eq!(game.explain_move("e2", "e4"), Ok(None));
+    assert_eq!(game.explain_move("g1", "f3"), Ok(None));
+    assert_eq!(
+      game.explain_move("e3", "e4"),
+      Ok(Some(IllegalMoveReason::NoPiece))
+    );
+    assert_eq!(
+      game.explain_move("e7", "e5"),
+      Ok(Some(IllegalMoveReason::NotYourPiece))
+    );
+    assert_eq!(
+      game.explain_move("d1", "d2"),
+      Ok(Some(IllegalMoveReason::OwnPieceOnTarget))
+    );
+    assert_eq!(
+      game.explain_move("e2", "e5"),
+      Ok(Some(IllegalMoveReason::PieceCannotReach))
+    );
+    assert_eq!(
+      game.explain_move("d1", "d4"),
+      Ok(Some(IllegalMoveReason::PathBlocked))
+    );
+    assert_eq!(
+      game.explain_move("e1", "g1"),
+      Ok(Some(IllegalMoveReason::PathBlocked))
+    );
+    assert_eq!(game.explain_move("e2", "e9"), Err(GameError::InvalidMove));
+
+    // knight on e2 is pinned by the rook on e7
+    let game = Game::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1", None, None).unwrap();
+    assert_eq!(
+      game.explain_move("e2", "c3"),
+      Ok(Some(IllegalMoveReason::LeavesKingInCheck))
+    );
+
+    // rook on f8 covers f1
+    let game = Game::from_fen("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1", None, None).unwrap();
+    assert_eq!(
+      game.explain_move("e1", "g1"),
+      Ok(Some(IllegalMoveReason::CastleThroughCheck))
+    );
+    assert_eq!(game.explain_move("e1", "c1"), Ok(None));
+
+    // rook on e8 gives check
+    let game = Game::from_fen("4r1k1/8/8/8/8/8/8/4K2R w K - 0 1", None, None).unwrap();
+    assert_eq!(
+      game.explain_move("e1", "g1"),
+      Ok(Some(IllegalMoveReason::CastleOutOfCheck))
+    );
+
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1", None, None).unwrap();
+    assert_eq!(
+      game.explain_move("e1", "g1"),
+      Ok(Some(IllegalMoveReason::CastleRightsLost))
+    );
+
+    // black's d-pawn did not just advance two
+    let game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1", None, None).unwrap();
+    assert_eq!(
+      game.explain_move("e5", "d6"),
+      Ok(Some(IllegalMoveReason::EnPassantNotAvailable))
+    );
+    assert_eq!(
+      game.explain_move("e5", "f6"),
+      Ok(Some(IllegalMoveReason::PieceCannotReach))
+    );
+  }
+
+  #[test]
+  fn test_diagnose_illegal_move() {
+    let diagnose = |fen: &str, san: &str| {
+      diagnose_illegal_move(&Game::from_fen(fen, None, None).unwrap(), san)
+    };
+    let game = Game::default();
+    assert_eq!(diagnose_illegal_move(&game, "e4"), None);
+    assert_eq!(diagnose_illegal_move(&game, "Nf3"), None);
+    assert_eq!(
+      diagnose_illegal_move(&game, "Nd2"),
+      Some(IllegalMoveReason::OwnPieceOnTarget)
+    );
+    assert_eq!(
+      diagnose_illegal_move(&game, "e5"),
+      Some(IllegalMoveReason::PieceCannotReach)
+    );
+    assert_eq!(
+      diagnose_illegal_move(&game, "Bc4"),
+      Some(IllegalMoveReason::PathBlocked)
+    );
+    assert_eq!(
+      diagnose_illegal_move(&game, "0-0"),
+      Some(IllegalMoveReason::PathBlocked)
+    );
+    assert_eq!(
+      diagnose_illegal_move(&game, "e4Q"),
+      Some(IllegalMoveReason::InvalidPromotion)
+    );
+    assert_eq!(diagnose_illegal_move(&game, "e9"), None);
+
+    assert_eq!(
+      diagnose("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "Qd4"),
+      Some(IllegalMoveReason::NoPiece)
+    );
+    // knight on e2 is pinned by the rook on e7
+    assert_eq!(
+      diagnose("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1", "Nc3+"),
+      Some(IllegalMoveReason::LeavesKingInCheck)
+    );
+
+    // rook on f8 covers f1
+    let fen = "4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1";
+    assert_eq!(diagnose(fen, "0-0"), Some(IllegalMoveReason::CastleThroughCheck));
+    assert_eq!(diagnose(fen, "O-O-O"), None);
+    // rook on e8 gives check
+    assert_eq!(
+      diagnose("4r1k1/8/8/8/8/8/8/4K2R w K - 0 1", "0-0"),
+      Some(IllegalMoveReason::CastleOutOfCheck)
+    );
+    assert_eq!(
+      diagnose("4k3/8/8/8/8/8/8/4K2R w - - 0 1", "0-0"),
+      Some(IllegalMoveReason::CastleRightsLost)
+    );
+    assert_eq!(
+      diagnose("4k3/8/8/8/8/8/8/3K3R w - - 0 1", "0-0"),
+      Some(IllegalMoveReason::CastleRightsLost)
+    );
+
+    assert_eq!(
+      diagnose("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1", "exd6"),
+      Some(IllegalMoveReason::EnPassantNotAvailable)
+    );
+    assert_eq!(diagnose("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6"), None);
+
+    let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    assert_eq!(diagnose(fen, "a8N"), None);
+    assert_eq!(diagnose(fen, "a8K"), Some(IllegalMoveReason::InvalidPromotion));
+    assert_eq!(diagnose(fen, "Kd2Q"), Some(IllegalMoveReason::InvalidPromotion));
+  }
+
+  #[test]
+  fn test_starting_position() {
+    let mut game = Game::default();
+    assert!(game.is_standard_start());
+    game.make_move(&GameAction::from("e4")).unwrap();
+    assert!(game.is_standard_start());
+    assert_eq!(*game.starting_position(), parse_fen(DEFAULT_FEN).unwrap());
+    assert!(Game::from_fen(DEFAULT_FEN, None, None).unwrap().is_standard_start());
+
+    // chess960, without castling rights
+    let chess960 = "nrbkqbrn/pppppppp/8/8/8/8/PPPPPPPP/NRBKQBRN w - - 0 1";
+    let mut game = Game::from_fen(chess960, None, None).unwrap();
+    assert!(!game.is_standard_start());
+    game.make_move(&GameAction::from("Nb3")).unwrap();
+    game.make_move(&GameAction::from("Nb6")).unwrap();
+    assert_eq!(*game.starting_position(), parse_fen(chess960).unwrap());
+    assert_ne!(game.board, parse_fen(chess960).unwrap());
+
+    // a loaded game keeps where it started
+    let fen = game.to_fen(0, 2).unwrap();
+    let loaded = Game::from_starting_fen(Some(chess960), &fen, None, None).unwrap();
+    assert_eq!(loaded.starting_position(), game.starting_position());
+    assert_eq!(loaded.board, game.board);
+    let loaded = Game::from_starting_fen(None, &fen, None, None).unwrap();
+    assert!(loaded.is_standard_start());
+    assert_eq!(
+      Game::from_starting_fen(Some("not a fen"), DEFAULT_FEN, None, None).unwrap_err(),
+      GameError::InvalidPosition
+    );
+  }
+
+  #[test]
+  fn test_remaining_material_ratio() {
+    let mut game = Game::default();
+    assert_eq!(game.starting_material, 78);
+    assert_eq!(game.remaining_material_ratio(), 100);
+    // black gives up the queen for two pawns
+    for move_str in ["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxd2", "Bxd2"] {
+      game.make_move(&GameAction::from(move_str)).unwrap();
+    }
+    // 78 - 3 pawns - queen = 66
+    assert_eq!(game.remaining_material_ratio(), 84);
+
+    let kings = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1", None, None).unwrap();
+    assert_eq!(kings.remaining_material_ratio(), 0);
+    let loaded =
+      Game::from_starting_fen(None, "4k3/8/8/8/8/8/8/4K3 w - - 0 1", None, None).unwrap();
+    assert_eq!(loaded.remaining_material_ratio(), 0);
+
+    // promoting gains material, the ratio stays at 100
+    let mut game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", None, None).unwrap();
+    game.make_move(&GameAction::from("a8Q")).unwrap();
+    assert!(game.board.get_non_king_material() > game.starting_material);
+    assert_eq!(game.remaining_material_ratio(), 100);
+  }
+
+  #[test]
+  fn test_json_roundtrip() {
+    let mut game = Game::default();
+    for move_str in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "0-0"] {
+      game.make_move(&GameAction::from(move_str)).unwrap();
+    }
+    game.make_move(&GameAction::OfferDraw("f6".to_string())).unwrap();
+
+    let json = game.to_json();
+    assert!(json.contains("\"start_fen\":\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\""));
+    assert!(json.contains("\"current_player\":\"White\""));
+    let restored = Game::from_json(&json).unwrap();
+    assert_eq!(restored, game);
+
+    // a finished game keeps its result
+    game.make_move(&GameAction::Resign).unwrap();
+    assert_eq!(Game::from_json(&game.to_json()).unwrap(), game);
+
+    // the board must match the moves
+    let tampered = json.replace("\"f6\"]", "\"f5\"]");
+    assert_ne!(tampered, json);
+    match Game::from_json(&tampered).unwrap_err() {
+      ContractError::InvalidFen { reason } => assert_eq!(reason, "moves do not reach fen"),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(Game::from_json("not json").is_err());
+  }
 }
@@ -0,0 +1,95 @@
+//! Ending games early when the result is no longer in doubt.
+use super::{Game, GameOver};
+use crate::engine::{Color, Evaluate, SEARCH_BOUND};
+
+// when a game may be adjudicated as won
+pub struct AdjudicationConfig {
+  // evaluation the winning side must be ahead by
+  pub threshold_centipawns: i32,
+  // plies the losing side is given to get back under the threshold
+  pub depth: i32,
+  // full moves that must be played first
+  pub min_moves: u64,
+}
+
+impl Default for AdjudicationConfig {
+  fn default() -> Self {
+    AdjudicationConfig {
+      threshold_centipawns: 2000,
+      depth: 3,
+      min_moves: 20,
+    }
+  }
+}
+
+// win for the side ahead by more than the threshold, once more than
+// min_moves moves have been played and the losing side cannot get back
+// under the threshold within depth plies
+//
+// `moves` is the number of full moves played so far
+pub fn auto_adjudicate(game: &Game, config: &AdjudicationConfig, moves: u64) -> Option<GameOver> {
+  if game.status.is_some() || moves <= config.min_moves {
+    return None;
+  }
+
+  let total = game.board.value_for(Color::White);
+  if total.abs() <= config.threshold_centipawns {
+    return None;
+  }
+  let losing = if total > 0 { Color::Black } else { Color::White };
+
+  // search from the losing side's point of view
+  let mut board_count = 0;
+  let best = game.board.minimax(
+    config.depth,
+    -SEARCH_BOUND,
+    SEARCH_BOUND,
+    game.get_turn_color() == losing,
+    losing,
+    &mut board_count,
+    &mut vec![],
+  );
+  if best >= -config.threshold_centipawns {
+    return None;
+  }
+
+  match losing {
+    Color::Black => Some(GameOver::WhiteWinsByAdjudication),
+    Color::White => Some(GameOver::BlackWinsByAdjudication),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // rook endgame, well past the opening
+  const ROOK_ENDGAME: &str = "8/8/8/4k3/8/8/8/R3K3 b - - 0 40";
+
+  fn rook_config() -> AdjudicationConfig {
+    // a rook is only worth ~500 centipawns
+    AdjudicationConfig {
+      threshold_centipawns: 400,
+      ..AdjudicationConfig::default()
+    }
+  }
+
+  #[test]
+  fn test_auto_adjudicate() {
+    let game = Game::from_fen(ROOK_ENDGAME, None, None).unwrap();
+    assert_eq!(
+      auto_adjudicate(&game, &rook_config(), 40),
+      Some(GameOver::WhiteWinsByAdjudication)
+    );
+
+    // too early in the game
+    assert_eq!(auto_adjudicate(&game, &rook_config(), 20), None);
+
+    // not far enough ahead for the default threshold
+    assert_eq!(auto_adjudicate(&game, &AdjudicationConfig::default(), 40), None);
+
+    // balanced position
+    let game = Game::default();
+    assert_eq!(auto_adjudicate(&game, &rook_config(), 40), None);
+  }
+}
@@ -0,0 +1,71 @@
+//! Move lists for a game, generated from its history when asked for.
+use super::{uci_move, Game};
+use crate::engine::{Color, Move};
+use crate::util::format_san_move;
+
+// every move as from/to squares, e.g. ["d2d4", "d7d5"]
+pub fn long_algebraic_move_list(game: &Game) -> Vec<String> {
+  game
+    .history
+    .iter()
+    .filter_map(|(board, chess_move)| uci_move(board, *chess_move))
+    .collect()
+}
+
+// every move in san, e.g. ["d4", "d5"]
+pub fn san_move_list(game: &Game) -> Vec<String> {
+  game
+    .history
+    .iter()
+    .filter(|(_, chess_move)| *chess_move != Move::Resign)
+    .map(|(board, chess_move)| format_san_move(board, *chess_move))
+    .collect()
+}
+
+// san with move numbers, e.g. "1. d4 d5 2. c4"
+// a game that started with black to move begins "1... "
+pub fn numbered_san_list(game: &Game) -> String {
+  let black_first = game.starting_board.get_turn_color() == Color::Black;
+  let mut moves = vec![];
+  for (i, san) in san_move_list(game).into_iter().enumerate() {
+    // plies counted from white's first move of move 1
+    let ply = i + black_first as usize;
+    moves.push(match ply % 2 {
+      0 => format!("{}. {}", ply / 2 + 1, san),
+      _ if i == 0 => format!("1... {}", san),
+      _ => san,
+    });
+  }
+  moves.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::game::GameAction;
+
+  #[test]
+  fn test_queens_gambit() {
+    let mut game = Game::default();
+    for move_str in ["d4", "d5", "c4"] {
+      game.make_move(&GameAction::from(move_str)).expect(move_str);
+    }
+    assert_eq!(long_algebraic_move_list(&game), ["d2d4", "d7d5", "c2c4"]);
+    assert_eq!(san_move_list(&game), ["d4", "d5", "c4"]);
+    assert_eq!(numbered_san_list(&game), "1. d4 d5 2. c4");
+
+    game.make_move(&GameAction::Resign).unwrap();
+    assert_eq!(numbered_san_list(&game), "1. d4 d5 2. c4");
+    assert_eq!(numbered_san_list(&Game::default()), "");
+  }
+
+  #[test]
+  fn test_black_to_move() {
+    let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", None, None).unwrap();
+    for move_str in ["Kd7", "e4", "Kc6"] {
+      game.make_move(&GameAction::from(move_str)).expect(move_str);
+    }
+    assert_eq!(long_algebraic_move_list(&game), ["e8d7", "e2e4", "d7c6"]);
+    assert_eq!(numbered_san_list(&game), "1... Kd7 2. e4 Kc6");
+  }
+}
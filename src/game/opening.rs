@@ -0,0 +1,84 @@
+//! The eco code of the opening a game started with.
+use crate::board::Board;
+use crate::engine::GameResult;
+use crate::util::{format_san_move, parse_san_move};
+
+// opening line by eco code, moves in the san parse_san_move accepts
+pub struct Opening {
+  pub eco: &'static str,
+  pub moves: &'static [&'static str],
+}
+
+// common openings, a game is named after the longest line it follows
+pub const OPENINGS: &[Opening] = &[
+  Opening { eco: "A04", moves: &["Nf3"] }, // Reti Opening
+  Opening { eco: "A10", moves: &["c4"] }, // English Opening
+  Opening { eco: "A40", moves: &["d4"] }, // Queen's Pawn Opening
+  Opening { eco: "A45", moves: &["d4", "Nf6"] }, // Indian Defense
+  Opening { eco: "B00", moves: &["e4"] }, // King's Pawn Opening
+  Opening { eco: "B01", moves: &["e4", "d5"] }, // Scandinavian Defense
+  Opening { eco: "B10", moves: &["e4", "c6"] }, // Caro-Kann Defense
+  Opening { eco: "B20", moves: &["e4", "c5"] }, // Sicilian Defense
+  Opening { eco: "C00", moves: &["e4", "e6"] }, // French Defense
+  Opening { eco: "C20", moves: &["e4", "e5"] }, // King's Pawn Game
+  Opening { eco: "C40", moves: &["e4", "e5", "Nf3"] }, // King's Knight Opening
+  Opening { eco: "C42", moves: &["e4", "e5", "Nf3", "Nf6"] }, // Petrov's Defense
+  Opening { eco: "C44", moves: &["e4", "e5", "Nf3", "Nc6"] }, // King's Pawn Game
+  Opening { eco: "C45", moves: &["e4", "e5", "Nf3", "Nc6", "d4", "exd4", "Nxd4"] }, // Scotch Game
+  Opening { eco: "C50", moves: &["e4", "e5", "Nf3", "Nc6", "Bc4"] }, // Italian Game
+  Opening { eco: "C60", moves: &["e4", "e5", "Nf3", "Nc6", "Bb5"] }, // Ruy Lopez
+  Opening { eco: "D00", moves: &["d4", "d5"] }, // Queen's Pawn Game
+  Opening { eco: "D06", moves: &["d4", "d5", "c4"] }, // Queen's Gambit
+  Opening { eco: "E60", moves: &["d4", "Nf6", "c4", "g6"] }, // King's Indian Defense
+];
+
+// the most specific opening the first moves of a game follow
+//
+// moves are replayed from the starting position so the same move written
+// differently (e.g. "Nd4" and "Nxd4") matches the same line
+pub fn classify<'a>(moves: impl IntoIterator<Item = &'a str>) -> Option<&'static Opening> {
+  let longest = OPENINGS.iter().map(|opening| opening.moves.len()).max().unwrap_or(0);
+  let mut board = Board::default();
+  let mut played = vec![];
+  for move_str in moves.into_iter().take(longest) {
+    let chess_move = match parse_san_move(&board, move_str) {
+      Ok(chess_move) => chess_move,
+      Err(_) => break,
+    };
+    played.push(format_san_move(&board, chess_move).trim_end_matches(['+', '#']).to_string());
+    board = match board.play_move(chess_move) {
+      GameResult::Continuing(board) => board,
+      _ => break,
+    };
+  }
+
+  OPENINGS
+    .iter()
+    .filter(|opening| {
+      opening.moves.len() <= played.len()
+        && opening.moves.iter().zip(&played).all(|(expected, played)| expected == played)
+    })
+    .max_by_key(|opening| opening.moves.len())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_classify() {
+    assert!(classify(vec![]).is_none());
+    assert_eq!(classify(vec!["e4"]).unwrap().eco, "B00");
+    assert_eq!(classify(vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]).unwrap().eco, "C60");
+    // King's Indian Defense
+    assert_eq!(classify(vec!["d4", "Nf6", "c4", "g6", "Nc3"]).unwrap().eco, "E60");
+
+    // captures match with or without the x
+    let scotch = vec!["e4", "e5", "Nf3", "Nc6", "d4", "ed4", "Nd4"];
+    assert_eq!(classify(scotch).unwrap().eco, "C45");
+
+    // off book after the first move
+    assert_eq!(classify(vec!["e4", "a5", "Nf3"]).unwrap().eco, "B00");
+    assert!(classify(vec!["h4"]).is_none());
+  }
+}
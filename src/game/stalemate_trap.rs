@@ -0,0 +1,32 @@
+//! Spotting moves that throw away a win by stalemating the opponent.
+use super::Game;
+use crate::engine::Move;
+
+// does mv leave the opponent without legal moves while not in check
+// mv must be legal for the side to move, only the position after it is looked at
+pub fn check(game: &Game, mv: Move) -> bool {
+  let board = game.board.apply_move(mv).change_turn();
+  !board.has_legal_moves() && !board.is_in_check(board.get_turn_color())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::parse_san_move;
+
+  fn check_san(game: &Game, move_str: &str) -> bool {
+    check(game, parse_san_move(&game.board, move_str).unwrap())
+  }
+
+  #[test]
+  fn test_check() {
+    // black king cornered by king and rook
+    let game = Game::from_fen("7k/5K2/8/8/8/8/8/6R1 w - - 0 1", None, None).unwrap();
+    // the rook takes the last free square, h7
+    assert!(check_san(&game, "Rg7"));
+    // mate, not stalemate
+    assert!(!check_san(&game, "Rh1"));
+    assert!(!check_san(&game, "Rg2"));
+    assert!(!check_san(&game, "Ke7"));
+  }
+}
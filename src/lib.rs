@@ -13,6 +13,6 @@ mod piece;
 mod position;
 mod util;
 mod engine;
-mod elo;
+pub mod elo;
 
 pub use crate::error::ContractError;
\ No newline at end of file
@@ -1,21 +1,94 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+use crate::cwchess::{
+  CwChessAction, CwChessColor, CwChessGame, CwChessGameOver,
+  CwChessMatePattern, CwChessVariant,
+};
+use crate::error::ContractError;
+use crate::game::TopMove;
+use crate::state::Standing;
 use cosmwasm_std::Addr;
 
+// minimum per player block limit, about one minute at ~10 blocks/minute
+pub const MIN_BLOCK_LIMIT: u64 = 10;
+
+// range accepted for imported ratings
+pub const MIN_INITIAL_RATING: u64 = 100;
+pub const MAX_INITIAL_RATING: u64 = 3000;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {}
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
   CreateChallenge {
+    // end the game early once clearly won, with the owner's SetAdjudication
+    // settings at the time the challenge is accepted
+    #[serde(default)]
+    adjudication: bool,
+    // white must win, a draw counts as a black win, and black's clock gets
+    // ARMAGEDDON_BLACK_TIME_PERCENT of block_limit, e.g. for a tie-break
+    #[serde(default)]
+    armageddon: bool,
+    block_limit: Option<u64>,
+    // a Turn with a move only proposes it, the player sends ConfirmMove
+    // within MOVE_CONFIRM_BLOCKS to play it or CancelMove to take it back
+    #[serde(default)]
+    confirm_moves: bool,
+    // resigning takes a second Resign within RESIGN_CONFIRM_BLOCKS, against misclicks
+    #[serde(default)]
+    confirm_resign: bool,
+    max_plies: Option<u64>,
+    opponent: Option<String>,
+    play_as: Option<CwChessColor>,
+    // block height before which no moves can be made, e.g. a tournament round
+    start_at: Option<u64>,
+    // FEN to start from instead of the standard position, white to move
+    start_fen: Option<String>,
+    // sender is creator
+  },
+  // challenge from a position the creator sets up with PlacePiece and
+  // RemovePiece, starting from an empty board, then LockPosition
+  CreateDraft {
+    #[serde(default)]
+    adjudication: bool,
     block_limit: Option<u64>,
+    max_plies: Option<u64>,
     opponent: Option<String>,
     play_as: Option<CwChessColor>,
     // sender is creator
   },
+  PlacePiece {
+    challenge_id: u64,
+    // FEN letter, e.g. "Q" or "k"
+    piece: String,
+    square: String,
+    // sender is creator
+  },
+  RemovePiece {
+    challenge_id: u64,
+    square: String,
+    // sender is creator
+  },
+  // validates the draft position and opens the challenge for play
+  LockPosition {
+    challenge_id: u64,
+    // sender is creator
+  },
+  // game against a phantom opponent of a fixed rating
+  // the engine moves for the phantom, only the sender's rating changes
+  CreatePractice {
+    phantom_rating: u64,
+    // white when None
+    play_as: Option<CwChessColor>,
+    start_fen: Option<String>,
+    // sender is player
+  },
   AcceptChallenge {
     challenge_id: u64,
     // sender is player
@@ -24,15 +97,131 @@ pub enum ExecuteMsg {
     challenge_id: u64,
     // sender is creator
   },
+  // draw once both players have sent it, independent of draw offers
+  // a player's agreement is withdrawn when they move
+  AgreeDraw {
+    game_id: u64,
+    // sender is player
+  },
   DeclareTimeout {
     game_id: u64,
   },
+  // play the move the sender proposed in a confirm_moves game
+  ConfirmMove {
+    game_id: u64,
+    // sender is player
+  },
+  // drop the move the sender proposed in a confirm_moves game
+  CancelMove {
+    game_id: u64,
+    // sender is player
+  },
+  // owner ends a game that cannot be finished over the board, e.g. a
+  // correspondence game stuck near time, rated like any other result
+  // the game ends as Adjudicated, no winner is a draw
+  Adjudicate {
+    game_id: u64,
+    winner: Option<CwChessColor>,
+  },
+  // note on a game, at most one per player
+  SetGameNote {
+    game_id: u64,
+    note: String,
+    // sender is player
+  },
+  // move a finished game's notes to archival storage
+  ArchiveGameNotes {
+    game_id: u64,
+    // sender is anyone
+  },
+  SetInitialRating {
+    player: String,
+    rating: u64,
+    // sender is owner
+  },
+  // settings for games created with adjudication, see CwChessAdjudication
+  // depth is 1 to MAX_ADJUDICATION_DEPTH, threshold_centipawns 1 to
+  // MAX_ADJUDICATION_CENTIPAWNS
+  SetAdjudication {
+    depth: u8,
+    min_moves: u64,
+    threshold_centipawns: u32,
+    // sender is owner
+  },
+  // K-factor by rating, as (rating_threshold, k) pairs sorted by threshold
+  SetKSchedule {
+    schedule: Vec<(u64, u64)>,
+    // sender is owner
+  },
+  // illegal moves a player can send in a row, the next one forfeits the
+  // game, 0 turns the limit off and illegal moves are errors
+  SetMaxRejectedMoves {
+    max: u64,
+    // sender is owner
+  },
+  SetMinDrawPlies {
+    plies: u64,
+    // sender is owner
+  },
+  // messages one address can send per block, at least 1, the owner's own
+  // admin messages are not counted
+  SetRateLimit {
+    per_block: u8,
+    // sender is owner
+  },
+  Subscribe {
+    game_id: u64,
+    // contract sent a SubscriberMsg on every move and the result
+    callback: String,
+    // sender is player
+  },
   Turn {
     game_id: u64,
     action: CwChessAction,
     // sender is player
     // block is timestamp
   },
+  Unsubscribe {
+    game_id: u64,
+    callback: String,
+    // sender is player
+  },
+}
+
+// sent to subscribed contracts, see ExecuteMsg::Subscribe
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriberMsg {
+  GameUpdate {
+    fen: String,
+    game_id: u64,
+    last_move_uci: Option<String>,
+    status: Option<CwChessGameOver>,
+  },
+}
+
+/// validate CreateChallenge inputs before any state is created
+pub fn validate_create_challenge(
+  block_limit: Option<u64>,
+  opponent: Option<&Addr>,
+  sender: &Addr,
+) -> Result<(), ContractError> {
+  if opponent == Some(sender) {
+    return Err(ContractError::CannotPlaySelf {});
+  }
+  if let Some(block_limit) = block_limit {
+    if block_limit < MIN_BLOCK_LIMIT {
+      return Err(ContractError::BlockLimitTooShort {});
+    }
+  }
+  Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+  InProgress,
+  Finished,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -53,13 +242,127 @@ pub enum QueryMsg {
     game_over: Option<bool>,
     player: Option<String>,
   },
+  // id of a game in progress between the two players, in either color
+  ActiveGameBetween {
+    player_a: String,
+    player_b: String,
+  },
+  // open games are challenges, see GetChallenges
+  GamesByStatus {
+    status: GameStatus,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+  },
+  // games the player plays either color in, all statuses when None
+  GamesByPlayer {
+    player: String,
+    status: Option<GameStatus>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+  },
   ValidMove {
     game_id: u64,
     player: String,
     #[serde(rename = "move")]
     move_str: String,
   },
+  LegalMoves {
+    game_id: u64,
+    perspective: Option<CwChessColor>,
+    square: Option<String>,
+  },
+  LegalMovesSan {
+    game_id: u64,
+  },
+  // start fen and every move, packed, to replay the game locally
+  Replay {
+    game_id: u64,
+  },
+  // every move as from/to squares, the long algebraic notation uci logs use
+  UciMoves {
+    game_id: u64,
+  },
+  // moves of the piece on square, of either color, see CwChessPieceMoves
+  PieceMoves {
+    game_id: u64,
+    square: String,
+  },
+  ExplainMove {
+    game_id: u64,
+    player: String,
+    from: String,
+    to: String,
+  },
+  GamePhase {
+    game_id: u64,
+  },
+  // piece counts for both sides and white's count minus black's per type
+  MaterialImbalance {
+    game_id: u64,
+  },
+  // advisory: whether for_color is hopelessly lost on material
+  ShouldResign {
+    game_id: u64,
+    for_color: CwChessColor,
+  },
+  // n best moves for the side to move, best first, at most 5
+  // depth is capped at 3
+  TopMoves {
+    game_id: u64,
+    n: u8,
+    depth: u8,
+  },
+  PositionEvaluation {
+    fen: String,
+  },
+  // side to move, check and legal moves with san for any position, with
+  // the only move when there is just one and pinned pieces
+  AnalyzeFen {
+    fen: String,
+  },
+  // whether the side to move can force mate within moves of its own,
+  // moves is capped at 2
+  ForcedMate {
+    fen: String,
+    moves: u8,
+  },
+  // games that started from the position in fen, e.g. a puzzle
+  GamesFromPosition {
+    fen: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+  },
+  CompletedGame {
+    game_id: u64,
+  },
+  // full pgn of a completed game, with tags
+  ArchivedPgn {
+    game_id: u64,
+  },
+  // (player, note) for each note on the game, archived or not
+  GameNotes {
+    game_id: u64,
+  },
+  RecentCompletedGames {
+    limit: u32,
+  },
+  OpeningStats {
+    eco_code: String,
+  },
+  // eco codes with at least min_games games, best win rate for color first
+  BestOpeningsForColor {
+    color: CwChessColor,
+    min_games: u64,
+  },
   GetRatings {},
+  // rated players, highest rating first, then most games played
+  // start_after is the last player of the previous page
+  Leaderboard {
+    start_after: Option<String>,
+    limit: Option<u32>,
+  },
+  // variants the deployed contract can play
+  SupportedVariants {},
   GetTurn {
     game_id: u64,
     player: String,
@@ -72,6 +375,8 @@ pub struct GameSummary {
   pub block_limit: Option<u64>,
   pub block_start: u64,
   pub game_id: u64,
+  pub last_move_uci: Option<String>,
+  pub mate_pattern: Option<CwChessMatePattern>,
   pub player1: String,
   pub player2: String,
   pub status: Option<CwChessGameOver>,
@@ -84,6 +389,8 @@ impl From<&CwChessGame> for GameSummary {
       block_limit: game.block_limit,
       block_start: game.block_start,
       game_id: game.game_id,
+      last_move_uci: game.last_move_uci.clone(),
+      mate_pattern: game.mate_pattern.clone(),
       player1: game.player1.to_string(),
       player2: game.player2.to_string(),
       status: game.status.clone(),
@@ -106,4 +413,103 @@ impl From<(Addr, u64)> for RatingSummary {
       rating: tuple.1,
     }
   }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LeaderboardEntry {
+  pub games_played: u64,
+  pub player: String,
+  pub rating: u64,
+}
+
+impl From<(Addr, Standing)> for LeaderboardEntry {
+  fn from((player, standing): (Addr, Standing)) -> Self {
+    LeaderboardEntry {
+      games_played: standing.games_played,
+      player: player.to_string(),
+      rating: standing.rating,
+    }
+  }
+}
+
+// a move found by QueryMsg::TopMoves
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MoveEvaluation {
+  // san, e.g. "Nf3"
+  pub algebraic: String,
+  // centipawns for the side to move
+  pub evaluation: i32,
+  // expected line in san, starting with the move
+  pub pv: Vec<String>,
+  // from/to squares, e.g. "g1f3"
+  pub uci: String,
+}
+
+impl From<TopMove> for MoveEvaluation {
+  fn from(top_move: TopMove) -> Self {
+    MoveEvaluation {
+      algebraic: top_move.algebraic,
+      evaluation: top_move.evaluation,
+      pv: top_move.pv,
+      uci: top_move.uci,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameReplay {
+  // moves played, from and to squares as row * 8 + col in bits 0-5 and
+  // 6-11, promotion piece in bits 12-14: 0 none, 1 knight, 2 bishop, 3 rook,
+  // 4 queen. castling is the king's move, e.g. e1 to g1
+  pub moves: Vec<u16>,
+  pub start_fen: String,
+}
+
+impl From<(String, Vec<u16>)> for GameReplay {
+  fn from((start_fen, moves): (String, Vec<u16>)) -> Self {
+    GameReplay { moves, start_fen }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct VariantSummary {
+  pub id: String,
+  pub name: String,
+  pub start_fen: String,
+}
+
+impl From<&CwChessVariant> for VariantSummary {
+  fn from(variant: &CwChessVariant) -> Self {
+    VariantSummary {
+      id: variant.id.to_string(),
+      name: variant.name.to_string(),
+      start_fen: variant.start_fen.to_string(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_create_challenge() {
+    let sender = Addr::unchecked("creator");
+    let opponent = Addr::unchecked("opponent");
+
+    assert!(validate_create_challenge(None, None, &sender).is_ok());
+    assert!(validate_create_challenge(Some(MIN_BLOCK_LIMIT), Some(&opponent), &sender).is_ok());
+    match validate_create_challenge(None, Some(&sender), &sender).unwrap_err() {
+      ContractError::CannotPlaySelf { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match validate_create_challenge(Some(MIN_BLOCK_LIMIT - 1), None, &sender).unwrap_err() {
+      ContractError::BlockLimitTooShort { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+}
@@ -1,3 +1,4 @@
+use crate::board::tables::{attacks, KING_ATTACKS, KNIGHT_ATTACKS};
 use crate::board::Board;
 use crate::engine::{Color, Move};
 use crate::position::Position;
@@ -15,7 +16,7 @@ use core::convert::TryFrom;
 /// 2. The validity of legal attacks
 /// 3. Move generation
 /// 4. Material and positional value
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Piece {
   King(Color, Position),
   Queen(Color, Position),
@@ -25,135 +26,135 @@ pub enum Piece {
   Pawn(Color, Position),
 }
 
-const WHITE_KING_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-2.0, -3.0, -3.0, -4.0, -4.0, -3.0, -3.0, -2.0],
-  [-1.0, -2.0, -2.0, -2.0, -2.0, -2.0, -2.0, -1.0],
-  [2.0, 2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 2.0],
-  [2.0, 3.0, 1.0, 0.0, 0.0, 1.0, 3.0, 2.0],
+const WHITE_KING_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-20, -30, -30, -40, -40, -30, -30, -20],
+  [-10, -20, -20, -20, -20, -20, -20, -10],
+  [20, 20, 0, 0, 0, 0, 20, 20],
+  [20, 30, 10, 0, 0, 10, 30, 20],
 ];
 
-const BLACK_KING_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [2.0, 3.0, 1.0, 0.0, 0.0, 1.0, 3.0, 2.0],
-  [2.0, 2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 2.0],
-  [-1.0, -2.0, -2.0, -2.0, -2.0, -2.0, -2.0, -1.0],
-  [-2.0, -3.0, -3.0, -4.0, -4.0, -3.0, -3.0, -2.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
-  [-3.0, -4.0, -4.0, -5.0, -5.0, -4.0, -4.0, -3.0],
+const BLACK_KING_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [20, 30, 10, 0, 0, 10, 30, 20],
+  [20, 20, 0, 0, 0, 0, 20, 20],
+  [-10, -20, -20, -20, -20, -20, -20, -10],
+  [-20, -30, -30, -40, -40, -30, -30, -20],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
+  [-30, -40, -40, -50, -50, -40, -40, -30],
 ];
 
-const WHITE_QUEEN_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-2.0, -1.0, -1.0, -0.5, -0.5, -1.0, -1.0, -2.0],
-  [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-1.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -1.0],
-  [-0.5, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -0.5],
-  [0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -0.5],
-  [-1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.0, -1.0],
-  [-1.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-1.0, -0.0, -1.0, -0.5, -0.5, -0.5, -1.0, -2.0],
+const WHITE_QUEEN_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-20, -10, -10, -5, -5, -10, -10, -20],
+  [-10, 0, 0, 0, 0, 0, 0, -10],
+  [-10, 0, 5, 5, 5, 5, 0, -10],
+  [-5, 0, 5, 5, 5, 5, 0, -5],
+  [0, 0, 5, 5, 5, 5, 0, -5],
+  [-10, 5, 5, 5, 5, 5, 0, -10],
+  [-10, 0, 5, 0, 0, 0, 0, -10],
+  [-10, 0, -10, -5, -5, -5, -10, -20],
 ];
-const BLACK_QUEEN_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-1.0, -0.0, -1.0, -0.5, -0.5, -0.5, -1.0, -2.0],
-  [-1.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.0, -1.0],
-  [0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -0.5],
-  [-0.5, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -0.5],
-  [-1.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, -1.0],
-  [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-2.0, -1.0, -1.0, -0.5, -0.5, -1.0, -1.0, -2.0],
+const BLACK_QUEEN_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-10, 0, -10, -5, -5, -5, -10, -20],
+  [-10, 0, 5, 0, 0, 0, 0, -10],
+  [-10, 5, 5, 5, 5, 5, 0, -10],
+  [0, 0, 5, 5, 5, 5, 0, -5],
+  [-5, 0, 5, 5, 5, 5, 0, -5],
+  [-10, 0, 5, 5, 5, 5, 0, -10],
+  [-10, 0, 0, 0, 0, 0, 0, -10],
+  [-20, -10, -10, -5, -5, -10, -10, -20],
 ];
 
-const WHITE_ROOK_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
-  [0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0],
+const WHITE_ROOK_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [0, 0, 0, 0, 0, 0, 0, 0],
+  [5, 10, 10, 10, 10, 10, 10, 5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [0, 0, 0, 5, 5, 0, 0, 0],
 ];
 
-const BLACK_ROOK_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [-0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.5],
-  [0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5],
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+const BLACK_ROOK_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [0, 0, 0, 5, 5, 0, 0, 0],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [-5, 0, 0, 0, 0, 0, 0, -5],
+  [5, 10, 10, 10, 10, 10, 10, 5],
+  [0, 0, 0, 0, 0, 0, 0, 0],
 ];
 
-const WHITE_BISHOP_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-2.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -2.0],
-  [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-1.0, 0.0, 0.5, 1.0, 1.0, 0.5, 0.0, -1.0],
-  [-1.0, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5, -1.0],
-  [-1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, -1.0],
-  [-1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0],
-  [-1.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.5, -1.0],
-  [-2.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -2.0],
+const WHITE_BISHOP_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-20, -10, -10, -10, -10, -10, -10, -20],
+  [-10, 0, 0, 0, 0, 0, 0, -10],
+  [-10, 0, 5, 10, 10, 5, 0, -10],
+  [-10, 5, 5, 10, 10, 5, 5, -10],
+  [-10, 0, 10, 10, 10, 10, 0, -10],
+  [-10, 10, 10, 10, 10, 10, 10, -10],
+  [-10, 5, 0, 0, 0, 0, 5, -10],
+  [-20, -10, -10, -10, -10, -10, -10, -20],
 ];
 
-const BLACK_BISHOP_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-2.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -2.0],
-  [-1.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.5, -1.0],
-  [-1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0],
-  [-1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, -1.0],
-  [-1.0, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5, -1.0],
-  [-1.0, 0.0, 0.5, 1.0, 1.0, 0.5, 0.0, -1.0],
-  [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
-  [-2.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -2.0],
+const BLACK_BISHOP_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-20, -10, -10, -10, -10, -10, -10, -20],
+  [-10, 5, 0, 0, 0, 0, 5, -10],
+  [-10, 10, 10, 10, 10, 10, 10, -10],
+  [-10, 0, 10, 10, 10, 10, 0, -10],
+  [-10, 5, 5, 10, 10, 5, 5, -10],
+  [-10, 0, 5, 10, 10, 5, 0, -10],
+  [-10, 0, 0, 0, 0, 0, 0, -10],
+  [-20, -10, -10, -10, -10, -10, -10, -20],
 ];
 
-const WHITE_KNIGHT_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-5.0, -4.0, -3.0, -3.0, -3.0, -3.0, -4.0, -5.0],
-  [-4.0, -2.0, 0.0, 0.0, 0.0, 0.0, -2.0, -4.0],
-  [-3.0, 0.0, 1.0, 1.5, 1.5, 1.0, 0.0, -3.0],
-  [-3.0, 0.5, 1.5, 2.0, 2.0, 1.5, 0.5, -3.0],
-  [-3.0, 0.0, 1.5, 2.0, 2.0, 1.5, 0.0, -3.0],
-  [-3.0, 0.5, 1.0, 1.5, 1.5, 1.0, 0.5, -3.0],
-  [-4.0, -2.0, 0.0, 0.5, 0.5, 0.0, -2.0, -4.0],
-  [-5.0, -4.0, -3.0, -3.0, -3.0, -3.0, -4.0, -5.0],
+const WHITE_KNIGHT_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-50, -40, -30, -30, -30, -30, -40, -50],
+  [-40, -20, 0, 0, 0, 0, -20, -40],
+  [-30, 0, 10, 15, 15, 10, 0, -30],
+  [-30, 5, 15, 20, 20, 15, 5, -30],
+  [-30, 0, 15, 20, 20, 15, 0, -30],
+  [-30, 5, 10, 15, 15, 10, 5, -30],
+  [-40, -20, 0, 5, 5, 0, -20, -40],
+  [-50, -40, -30, -30, -30, -30, -40, -50],
 ];
 
-const BLACK_KNIGHT_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [-5.0, -4.0, -3.0, -3.0, -3.0, -3.0, -4.0, -5.0],
-  [-4.0, -2.0, 0.0, 0.5, 0.5, 0.0, -2.0, -4.0],
-  [-3.0, 0.5, 1.0, 1.5, 1.5, 1.0, 0.5, -3.0],
-  [-3.0, 0.0, 1.5, 2.0, 2.0, 1.5, 0.0, -3.0],
-  [-3.0, 0.5, 1.5, 2.0, 2.0, 1.5, 0.5, -3.0],
-  [-3.0, 0.0, 1.0, 1.5, 1.5, 1.0, 0.0, -3.0],
-  [-4.0, -2.0, 0.0, 0.0, 0.0, 0.0, -2.0, -4.0],
-  [-5.0, -4.0, -3.0, -3.0, -3.0, -3.0, -4.0, -5.0],
+const BLACK_KNIGHT_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [-50, -40, -30, -30, -30, -30, -40, -50],
+  [-40, -20, 0, 5, 5, 0, -20, -40],
+  [-30, 5, 10, 15, 15, 10, 5, -30],
+  [-30, 0, 15, 20, 20, 15, 0, -30],
+  [-30, 5, 15, 20, 20, 15, 5, -30],
+  [-30, 0, 10, 15, 15, 10, 0, -30],
+  [-40, -20, 0, 0, 0, 0, -20, -40],
+  [-50, -40, -30, -30, -30, -30, -40, -50],
 ];
 
-const WHITE_PAWN_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
-  [5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0],
-  [1.0, 1.0, 2.0, 3.0, 3.0, 2.0, 1.0, 1.0],
-  [0.5, 0.5, 1.0, 2.5, 2.5, 1.0, 0.5, 0.5],
-  [0.0, 0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 0.0],
-  [0.5, -0.5, -1.0, 0.0, 0.0, -1.0, -0.5, 0.5],
-  [0.5, 1.5, -1.0, -2.0, -2.0, 1.0, 1.5, 0.5],
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+const WHITE_PAWN_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [0, 0, 0, 0, 0, 0, 0, 0],
+  [50, 50, 50, 50, 50, 50, 50, 50],
+  [10, 10, 20, 30, 30, 20, 10, 10],
+  [5, 5, 10, 25, 25, 10, 5, 5],
+  [0, 0, 0, 20, 20, 0, 0, 0],
+  [5, -5, -10, 0, 0, -10, -5, 5],
+  [5, 15, -10, -20, -20, 10, 15, 5],
+  [0, 0, 0, 0, 0, 0, 0, 0],
 ];
 
-const BLACK_PAWN_POSITION_WEIGHTS: [[f64; 8]; 8] = [
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
-  [0.5, 1.5, -1.0, -2.0, -2.0, 1.0, 1.5, 0.5],
-  [0.5, -0.5, -1.0, 0.0, 0.0, -1.0, -0.5, 0.5],
-  [0.0, 0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 0.0],
-  [0.5, 0.5, 1.0, 2.5, 2.5, 1.0, 0.5, 0.5],
-  [1.0, 1.0, 2.0, 3.0, 3.0, 2.0, 1.0, 1.0],
-  [5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0],
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+const BLACK_PAWN_POSITION_WEIGHTS: [[i32; 8]; 8] = [
+  [0, 0, 0, 0, 0, 0, 0, 0],
+  [5, 15, -10, -20, -20, 10, 15, 5],
+  [5, -5, -10, 0, 0, -10, -5, 5],
+  [0, 0, 0, 20, 20, 0, 0, 0],
+  [5, 5, 10, 25, 25, 10, 5, 5],
+  [10, 10, 20, 30, 30, 20, 10, 10],
+  [50, 50, 50, 50, 50, 50, 50, 50],
+  [0, 0, 0, 0, 0, 0, 0, 0],
 ];
 
 impl core::fmt::Display for Piece {
@@ -237,16 +238,23 @@ impl Piece {
     }
   }
 
-  /// Get the weighted value of a piece. This simply factors in position
-  /// to the pieces value. For example, a knight that is in the center is
-  /// more favorable than a knight on the side of the board. Similarly,
-  /// a king in the center of the board is highly unfavorable compared to
-  /// a king its respective side.
+  /// Get the value of a piece in centipawns. This simply factors in
+  /// position to the pieces value. For example, a knight that is in the
+  /// center is more favorable than a knight on the side of the board.
+  /// Similarly, a king in the center of the board is highly unfavorable
+  /// compared to a king its respective side.
   ///
-  /// Additionally, the weighted value of the piece is 10 times greater than
-  /// its material value, plus or minus a weight ranging between 5.0 and -5.0.
+  /// Additionally, the weighted value of the piece is 100 times its
+  /// material value, plus or minus a weight ranging between 50 and -50.
   #[inline]
-  pub fn get_weighted_value(&self) -> f64 {
+  pub fn get_weighted_value(&self) -> i32 {
+    self.get_position_weight() + self.get_material_value() * 100
+  }
+
+  /// Get the positional part of the weighted value, between 50 and -50
+  /// centipawns.
+  #[inline]
+  pub fn get_position_weight(&self) -> i32 {
     let weights = match self {
       Self::King(c, _) => match c {
         Color::White => WHITE_KING_POSITION_WEIGHTS,
@@ -274,7 +282,6 @@ impl Piece {
       },
     };
     weights[(7 - self.get_pos().get_row()) as usize][self.get_pos().get_col() as usize]
-      + (self.get_material_value() * 10) as f64
   }
 
   /// Get the color of a given piece.
@@ -365,6 +372,17 @@ impl Piece {
     }
   }
 
+  /// Is this piece a pawn one step away from promoting?
+  #[inline]
+  pub fn is_promoting_pawn(&self) -> bool {
+    if let Self::Pawn(c, pos) = self {
+      let row = pos.pawn_up(*c).get_row();
+      row == 0 || row == 7
+    } else {
+      false
+    }
+  }
+
   /// Is this piece in the starting position for the queenside rook?
   ///
   /// This method will only return true for rooks that are in the position
@@ -414,6 +432,17 @@ impl Piece {
   /// This is used for move generation.
   #[inline]
   pub(crate) fn get_legal_moves(&self, board: &Board) -> Vec<Move> {
+    let color = self.get_color();
+    self
+      .get_pseudo_legal_moves(board)
+      .into_iter()
+      .filter(|m| board.keeps_king_safe(*m, color))
+      .collect()
+  }
+
+  /// Get every move the piece could make if its own king's safety were
+  /// ignored.
+  pub(crate) fn get_pseudo_legal_moves(&self, board: &Board) -> Vec<Move> {
     let mut result = Vec::new();
     match *self {
       Self::Pawn(ally_color, pos) => {
@@ -466,7 +495,8 @@ impl Piece {
         }
         if board.can_kingside_castle(ally_color) {
           result.push(Move::KingSideCastle);
-        } else if board.can_queenside_castle(ally_color) {
+        }
+        if board.can_queenside_castle(ally_color) {
           result.push(Move::QueenSideCastle);
         }
       }
@@ -563,12 +593,12 @@ impl Piece {
       .filter(|x| match x {
         Move::Piece(from, to) => {
           if from.is_on_board() && to.is_on_board() {
-            board.is_legal_move(*x, color)
+            board.is_pseudo_legal_move(*x, color)
           } else {
             false
           }
         }
-        _ => board.is_legal_move(*x, color),
+        _ => board.is_pseudo_legal_move(*x, color),
       })
       .collect::<Vec<Move>>()
   }
@@ -599,7 +629,7 @@ impl Piece {
           || (board.has_no_piece(new_pos) && new_pos == up)
       }
 
-      Self::King(_, pos) => pos.is_adjacent_to(new_pos),
+      Self::King(_, pos) => attacks(&KING_ATTACKS, pos, new_pos),
 
       Self::Queen(_, pos) => {
         if pos.is_orthogonal_to(new_pos) {
@@ -659,7 +689,7 @@ impl Piece {
         }
       }
 
-      Self::Knight(_, pos) => pos.is_knight_move(new_pos),
+      Self::Knight(_, pos) => attacks(&KNIGHT_ATTACKS, pos, new_pos),
     }
   }
 
@@ -682,7 +712,7 @@ impl Piece {
           || new_pos == up.next_right()
       }
 
-      Self::King(_, pos) => pos.is_adjacent_to(new_pos),
+      Self::King(_, pos) => attacks(&KING_ATTACKS, pos, new_pos),
 
       Self::Queen(_, pos) => {
         if pos.is_orthogonal_to(new_pos) {
@@ -742,7 +772,7 @@ impl Piece {
         }
       }
 
-      Self::Knight(_, pos) => pos.is_knight_move(new_pos),
+      Self::Knight(_, pos) => attacks(&KNIGHT_ATTACKS, pos, new_pos),
     }
   }
 }
@@ -73,7 +73,7 @@ pub const H6: Position = Position::new(5, 7);
 pub const H7: Position = Position::new(6, 7);
 pub const H8: Position = Position::new(7, 7);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Position {
   row: i32,
   col: i32,
@@ -163,6 +163,19 @@ impl Position {
     }
   }
 
+  /// Get this position as seen from a color's side of the board.
+  ///
+  /// White sees the board as is. Black sees it rotated so that its own
+  /// pieces are on the bottom ranks, e.g. `H8` becomes `A1` and `E2`
+  /// becomes `D7`.
+  #[inline]
+  pub fn relative_to(&self, color: Color) -> Self {
+    match color {
+      Color::White => *self,
+      Color::Black => Self::new(7 - self.row, 7 - self.col),
+    }
+  }
+
   /// Is this position a valid spot on the board?
   #[inline]
   pub fn is_on_board(&self) -> bool {
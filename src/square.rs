@@ -1,7 +1,7 @@
 use crate::piece::Piece;
 
 /// Essentially a container for a single piece on a board.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Square {
   piece: Option<Piece>,
 }
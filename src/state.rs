@@ -1,30 +1,79 @@
-use cosmwasm_std::{Addr, StdResult, Storage};
+use cosmwasm_std::{Addr, Decimal, Order, StdResult, Storage};
 use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::iter::Peekable;
 
-use crate::cwchess::{CwChessColor, CwChessGame};
+use crate::cwchess::{CwChessAdjudication, CwChessColor, CwChessGame, CwChessGameOver};
+use crate::elo::{elo, EloConfig, EloRating, Outcomes};
+use crate::error::ContractError;
+use crate::util::pgn_sanitizer::{sanitize, Roster};
 
 // STATE
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct State {
+  // settings copied into games created with adjudication
+  #[serde(default)]
+  pub adjudication: CwChessAdjudication,
+  // (rating_threshold, k) pairs sorted by threshold, empty uses the default k
+  #[serde(default)]
+  pub k_schedule: Vec<(u64, u64)>,
+  // illegal moves a player can send in a row before forfeiting, 0 for no limit
+  #[serde(default)]
+  pub max_rejected_moves: u64,
+  // plies that must be played before a draw can be offered or accepted
+  #[serde(default)]
+  pub min_draw_plies: u64,
   pub owner: Addr,
+  // messages one address can send per block
+  #[serde(default = "default_rate_limit_per_block")]
+  pub rate_limit_per_block: u8,
 }
 
 pub const STATE: Item<State> = Item::new("state");
 
+pub const DEFAULT_RATE_LIMIT_PER_BLOCK: u8 = 10;
+
+fn default_rate_limit_per_block() -> u8 {
+  DEFAULT_RATE_LIMIT_PER_BLOCK
+}
+
+// messages counted for (sender, block height), see util::rate_limiter
+// only the sender's latest block is kept
+pub const RATE_LIMITER: Map<(&Addr, u64), u8> = Map::new("rate_limiter");
+
 // CHALLENGES
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Challenge {
+  // game is adjudicated with the owner's settings, see State
+  #[serde(default)]
+  pub adjudication: bool,
+  // draws count as black wins, see CwChessGame
+  #[serde(default)]
+  pub armageddon: bool,
   pub block_created: u64,
   pub block_limit: Option<u64>,
   pub challenge_id: u64,
+  // moves need confirming, see CwChessGame
+  #[serde(default)]
+  pub confirm_moves: bool,
+  // resigning needs confirming, see CwChessGame
+  #[serde(default)]
+  pub confirm_resign: bool,
   pub created_by: Addr,
+  // position still being set up by the creator, see LockPosition
+  // start_fen holds the position and the challenge cannot be accepted
+  #[serde(default)]
+  pub draft: bool,
+  pub max_plies: Option<u64>,
   pub play_as: Option<CwChessColor>,
   pub opponent: Option<Addr>,
+  // no moves before this block height
+  pub start_at: Option<u64>,
+  pub start_fen: Option<String>,
 }
 
 pub const CHALLENGE_ID: Item<u64> = Item::new("challenge_id");
@@ -77,19 +126,35 @@ pub fn next_game_id(store: &mut dyn Storage) -> StdResult<u64> {
 }
 
 pub struct GameIndexes<'a> {
+  pub pairing_status: MultiIndex<'a, ((Addr, Addr), u8), CwChessGame, u64>,
   pub player1: MultiIndex<'a, Addr, CwChessGame, u64>,
   pub player2: MultiIndex<'a, Addr, CwChessGame, u64>,
+  pub start_position: MultiIndex<'a, u64, CwChessGame, u64>,
+  pub status: MultiIndex<'a, u8, CwChessGame, u64>,
 }
 
 impl<'a> IndexList<CwChessGame> for GameIndexes<'a> {
   fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CwChessGame>> + '_> {
-    let v: Vec<&dyn Index<CwChessGame>> = vec![&self.player1, &self.player2];
+    let v: Vec<&dyn Index<CwChessGame>> = vec![
+      &self.pairing_status,
+      &self.player1,
+      &self.player2,
+      &self.start_position,
+      &self.status,
+    ];
     Box::new(v.into_iter())
   }
 }
 
 pub fn get_games_map<'a>() -> IndexedMap<'a, u64, CwChessGame, GameIndexes<'a>> {
   let indexes = GameIndexes {
+    pairing_status: MultiIndex::new(
+      |c: &CwChessGame| {
+        (pairing_key(&c.player1, &c.player2), game_status_key(c.status.is_some()))
+      },
+      "games",
+      "games__pairing_status",
+    ),
     player1: MultiIndex::new(
       |c: &CwChessGame| c.player1.clone(),
       "games",
@@ -100,13 +165,271 @@ pub fn get_games_map<'a>() -> IndexedMap<'a, u64, CwChessGame, GameIndexes<'a>>
       "games",
       "games__player2",
     ),
+    start_position: MultiIndex::new(
+      |c: &CwChessGame| c.start_position_hash(),
+      "games",
+      "games__start_position",
+    ),
+    status: MultiIndex::new(
+      |c: &CwChessGame| game_status_key(c.status.is_some()),
+      "games",
+      "games__status",
+    ),
   };
   IndexedMap::new("games", indexes)
 }
 
+// COMPLETED GAMES
+// written once when a game ends and never modified
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameRecord {
+  // a draw counted as a black win
+  #[serde(default)]
+  pub armageddon: bool,
+  pub black: Addr,
+  pub black_elo_after: u64,
+  pub black_elo_before: u64,
+  pub created_at_block: u64,
+  pub ended_at_block: u64,
+  pub game_duration_blocks: u64,
+  pub game_id: u64,
+  pub move_count: u16,
+  pub pgn: String,
+  pub result: CwChessGameOver,
+  pub white: Addr,
+  pub white_elo_after: u64,
+  pub white_elo_before: u64,
+}
+
+impl GameRecord {
+  // pgn with the seven tag roster, site is the contract address
+  // the block time is not recorded, so the date is unknown
+  pub fn archived_pgn(&self, site: &str) -> Result<String, ContractError> {
+    sanitize(
+      &self.pgn,
+      &Roster {
+        site,
+        date: "????.??.??",
+        white: self.white.as_str(),
+        black: self.black.as_str(),
+      },
+    )
+  }
+}
+
+pub const COMPLETED_GAMES: Map<u64, GameRecord> = Map::new("completed_games");
+
+// ids of the most recently completed games, newest first
+pub const RECENT_GAMES: Item<VecDeque<u64>> = Item::new("recent_games");
+pub const RECENT_GAMES_LIMIT: usize = 20;
+
+pub fn save_game_record(store: &mut dyn Storage, record: &GameRecord) -> StdResult<()> {
+  COMPLETED_GAMES.save(store, record.game_id, record)?;
+  let mut recent = RECENT_GAMES.may_load(store)?.unwrap_or_default();
+  recent.push_front(record.game_id);
+  recent.truncate(RECENT_GAMES_LIMIT);
+  RECENT_GAMES.save(store, &recent)
+}
+
+// OPENING STATS
+// results of completed games by the eco code of their opening
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OpeningStats {
+  pub black_wins: u64,
+  pub draws: u64,
+  pub total_games: u64,
+  pub white_wins: u64,
+}
+
+impl OpeningStats {
+  // add a result, outcome is from white's point of view
+  pub fn record(&mut self, outcome: &Outcomes) {
+    self.total_games += 1;
+    match outcome {
+      Outcomes::WIN => self.white_wins += 1,
+      Outcomes::LOSS => self.black_wins += 1,
+      Outcomes::DRAW => self.draws += 1,
+    }
+  }
+
+  // share of games won by color, zero before any games
+  pub fn win_rate(&self, color: &CwChessColor) -> Decimal {
+    if self.total_games == 0 {
+      return Decimal::zero();
+    }
+    let wins = match color {
+      CwChessColor::White => self.white_wins,
+      CwChessColor::Black => self.black_wins,
+    };
+    Decimal::from_ratio(wins, self.total_games)
+  }
+}
+
+pub const OPENING_STATS: Map<&str, OpeningStats> = Map::new("opening_stats");
+
+pub fn record_opening_result(
+  store: &mut dyn Storage,
+  eco_code: &str,
+  outcome: &Outcomes,
+) -> StdResult<OpeningStats> {
+  OPENING_STATS.update(store, eco_code, |stats| -> StdResult<_> {
+    let mut stats = stats.unwrap_or_default();
+    stats.record(outcome);
+    Ok(stats)
+  })
+}
+
+// SUBSCRIBERS
+// contracts notified of every move and the result of a game
+pub const SUBSCRIBERS: Map<u64, Vec<Addr>> = Map::new("subscribers");
+pub const MAX_SUBSCRIBERS: usize = 8;
+
+// GAME NOTES
+// one note per player and game, never changed once set
+pub const GAME_NOTES: Map<(u64, Addr), String> = Map::new("game_notes");
+// notes moved out of GAME_NOTES once the game has been over for a while
+pub const ARCHIVED_GAME_NOTES: Map<(u64, Addr), String> = Map::new("archived_game_notes");
+pub const MAX_NOTE_CHARS: usize = 500;
+// about 30 days of 6 second blocks
+pub const NOTE_ARCHIVE_BLOCKS: u64 = 432_000;
+
+// save every game again so that indexes added since it was saved include it
+pub fn reindex_games(storage: &mut dyn Storage) -> StdResult<()> {
+  let games_map = get_games_map();
+  let game_ids = games_map
+    .keys(storage, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<u64>>>()?;
+  for game_id in game_ids {
+    let game = games_map.load(storage, game_id)?;
+    games_map.save(storage, game_id, &game)?;
+  }
+  Ok(())
+}
+
+// pair key for the games pairing_status index, the same whichever color each plays
+pub fn pairing_key(player_a: &Addr, player_b: &Addr) -> (Addr, Addr) {
+  if player_a <= player_b {
+    (player_a.clone(), player_b.clone())
+  } else {
+    (player_b.clone(), player_a.clone())
+  }
+}
+
+// key for the games status index
+pub fn game_status_key(game_over: bool) -> u8 {
+  game_over as u8
+}
+
 // RATINGS
 pub const RATINGS: Map<Addr, u64> = Map::new("ratings");
 
+// LEADERBOARD
+// standing of every rated player, kept in step with RATINGS by save_rating
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Standing {
+  pub games_played: u64,
+  pub rating: u64,
+}
+
+pub struct StandingIndexes<'a> {
+  pub rank: MultiIndex<'a, (u64, u64), Standing, Addr>,
+}
+
+impl<'a> IndexList<Standing> for StandingIndexes<'a> {
+  fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Standing>> + '_> {
+    let v: Vec<&dyn Index<Standing>> = vec![&self.rank];
+    Box::new(v.into_iter())
+  }
+}
+
+pub fn get_standings_map<'a>() -> IndexedMap<'a, Addr, Standing, StandingIndexes<'a>> {
+  let indexes = StandingIndexes {
+    rank: MultiIndex::new(rank_key, "standings", "standings__rank"),
+  };
+  IndexedMap::new("standings", indexes)
+}
+
+// key for the standings rank index, ascending order is highest rating
+// first, then most games played, then address
+pub fn rank_key(standing: &Standing) -> (u64, u64) {
+  (u64::MAX - standing.rating, u64::MAX - standing.games_played)
+}
+
+// save a rating and update the player's standing
+// played_game is true when the rating changes because a game ended
+pub fn save_rating(
+  storage: &mut dyn Storage,
+  addr: &Addr,
+  rating: u64,
+  played_game: bool,
+) -> StdResult<()> {
+  RATINGS.save(storage, addr.clone(), &rating)?;
+  get_standings_map().update(storage, addr.clone(), |standing| -> StdResult<_> {
+    let mut standing = standing.unwrap_or_default();
+    standing.rating = rating;
+    if played_game {
+      standing.games_played += 1;
+    }
+    Ok(standing)
+  })?;
+  Ok(())
+}
+
+// give every rated player without a standing one, for ratings saved
+// before standings were kept
+pub fn backfill_standings(storage: &mut dyn Storage) -> StdResult<()> {
+  let ratings = RATINGS
+    .range(storage, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<(Addr, u64)>>>()?;
+  let standings = get_standings_map();
+  let games_map = get_games_map();
+  for (addr, rating) in ratings {
+    if standings.may_load(storage, addr.clone())?.is_some() {
+      continue;
+    }
+    // rated games the player has finished, as either color
+    let mut games_played = 0;
+    for games in [&games_map.idx.player1, &games_map.idx.player2] {
+      for result in games.prefix(addr.clone()).range(storage, None, None, Order::Ascending) {
+        let (_, game) = result?;
+        if game.status.is_some() && game.phantom.is_none() {
+          games_played += 1;
+        }
+      }
+    }
+    standings.save(storage, addr, &Standing { games_played, rating })?;
+  }
+  Ok(())
+}
+
+/// the single place game results change ratings
+/// both ratings are computed before either is written, and a failed
+/// write fails the whole transaction, so neither rating changes alone
+/// outcome is from white's perspective
+pub fn atomic_elo_update(
+  storage: &mut dyn Storage,
+  white_addr: &Addr,
+  black_addr: &Addr,
+  outcome: &Outcomes,
+  elo_config: &EloConfig,
+) -> Result<(EloRating, EloRating), ContractError> {
+  let white = RATINGS
+    .may_load(storage, white_addr.clone())?
+    .map_or_else(EloRating::new, EloRating::from);
+  let black = RATINGS
+    .may_load(storage, black_addr.clone())?
+    .map_or_else(EloRating::new, EloRating::from);
+
+  let (white, black) = elo(&white, &black, outcome, elo_config);
+  save_rating(storage, white_addr, white.into(), true)?;
+  save_rating(storage, black_addr, black.into(), true)?;
+
+  Ok((white, black))
+}
+
 pub fn merge_iters<I, J, K>(
   iter1: I,
   iter2: J,
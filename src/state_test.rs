@@ -1,6 +1,13 @@
 #[cfg(test)]
 mod tests {
-  use crate::state::merge_iters;
+  use crate::cwchess::CwChessGameOver;
+  use crate::elo::{EloConfig, EloRating, Outcomes};
+  use crate::state::{
+    atomic_elo_update, get_standings_map, merge_iters, save_game_record, GameRecord, Standing,
+    COMPLETED_GAMES, RATINGS, RECENT_GAMES, RECENT_GAMES_LIMIT,
+  };
+  use cosmwasm_std::testing::MockStorage;
+  use cosmwasm_std::Addr;
 
   #[test]
   fn test_merge_iters() {
@@ -37,4 +44,68 @@ mod tests {
 
     assert_eq!(merged, vec![1, 3, 5]);
   }
+
+  #[test]
+  fn test_save_game_record() {
+    let mut store = MockStorage::new();
+    for game_id in 1..=(RECENT_GAMES_LIMIT as u64 + 5) {
+      let record = GameRecord {
+        armageddon: false,
+        black: Addr::unchecked("black"),
+        black_elo_after: 1200,
+        black_elo_before: 1200,
+        created_at_block: 1,
+        ended_at_block: 2,
+        game_duration_blocks: 1,
+        game_id,
+        move_count: 0,
+        pgn: "1/2-1/2".to_string(),
+        result: CwChessGameOver::DrawAccepted,
+        white: Addr::unchecked("white"),
+        white_elo_after: 1200,
+        white_elo_before: 1200,
+      };
+      save_game_record(&mut store, &record).unwrap();
+    }
+
+    // every record is kept, only the recent list is capped
+    assert!(COMPLETED_GAMES.has(&store, 1));
+    let recent = RECENT_GAMES.load(&store).unwrap();
+    assert_eq!(recent.len(), RECENT_GAMES_LIMIT);
+    assert_eq!(recent.front(), Some(&(RECENT_GAMES_LIMIT as u64 + 5)));
+    assert_eq!(recent.back(), Some(&6));
+  }
+
+  #[test]
+  fn test_atomic_elo_update() {
+    let white = Addr::unchecked("white");
+    let black = Addr::unchecked("black");
+
+    for (outcome, expected) in [
+      (Outcomes::WIN, (1016, 984)),
+      (Outcomes::LOSS, (984, 1016)),
+      (Outcomes::DRAW, (1000, 1000)),
+    ] {
+      // missing ratings start at the default
+      let mut store = MockStorage::new();
+      let (white_rating, black_rating) =
+        atomic_elo_update(&mut store, &white, &black, &outcome, &EloConfig::new()).unwrap();
+
+      assert_eq!(white_rating, EloRating::from(expected.0));
+      assert_eq!(black_rating, EloRating::from(expected.1));
+      assert_eq!(RATINGS.load(&store, white.clone()).unwrap(), expected.0);
+      assert_eq!(RATINGS.load(&store, black.clone()).unwrap(), expected.1);
+      // the game counts towards both standings
+      let standing = get_standings_map().load(&store, white.clone()).unwrap();
+      assert_eq!(standing, Standing { games_played: 1, rating: expected.0 });
+    }
+
+    // existing ratings are used
+    let mut store = MockStorage::new();
+    RATINGS.save(&mut store, white.clone(), &500).unwrap();
+    RATINGS.save(&mut store, black.clone(), &1500).unwrap();
+    atomic_elo_update(&mut store, &white, &black, &Outcomes::WIN, &EloConfig::new()).unwrap();
+    assert_eq!(RATINGS.load(&store, white.clone()).unwrap(), 531);
+    assert_eq!(RATINGS.load(&store, black).unwrap(), 1469);
+  }
 }
@@ -1,3 +1,9 @@
+pub mod chess_notation;
+pub mod fp_math;
+pub mod pgn_sanitizer;
+pub mod rate_limiter;
+pub mod rng;
+
 use crate::board::{Board, BoardBuilder};
 use crate::piece::Piece;
 use crate::position::Position;
@@ -201,13 +207,18 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
           true => Color::White,
           false => Color::Black,
         };
+        // Shredder-FEN / X-FEN name the castling rook by its file, only
+        // rooks in the corners can castle so h and a are kingside and queenside
         match c.to_ascii_lowercase() {
-          'k' => {
+          'k' | 'h' => {
             builder = builder.enable_kingside_castle(color);
           }
-          'q' => {
+          'q' | 'a' => {
             builder = builder.enable_queenside_castle(color);
           }
+          'b'..='g' => {
+            return Err(String::from("unsupported castling rook file"));
+          }
           _ => {
             return Err(String::from("invalid castling side"));
           }
@@ -231,7 +242,7 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
 
 // parse Short Algebraic Notation (SAN)
 //
-// move parsing is strict and should not include any +, #, etc characters.
+// move parsing is strict, a trailing + or # is the only annotation allowed.
 //
 // returns Err("ambiguous") if there are multiple possibilities
 // e.g. "Nxc4" when there are N at e2 and d6
@@ -240,6 +251,7 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
 // returns Err(InvalidMove) if there is a parse error or the move is
 // not valid based on the current board position and turn.
 pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
+  let move_str = move_str.trim_end_matches(['+', '#']);
   if move_str == "0-0" {
     return Ok(Move::KingSideCastle {});
   } else if move_str == "0-0-0" {
@@ -330,18 +342,20 @@ pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
   // find moves that end on target square and are correct piece type
   let mut candidates = vec![];
   for legal_move in board.get_legal_moves() {
-    if let Move::Piece(from, to) = legal_move {
-      if move_to == to {
-        if let Some(board_piece) = board.get_piece(from) {
-          // filter based on type
-          let pos = board_piece.get_pos();
-          if board_piece.get_name() == piece.get_name()
-            && (column.is_none() || column == Some(pos.get_col()))
-            && (row.is_none() || row == Some(pos.get_row()))
-          {
-            candidates.push(board_piece);
-          }
-        }
+    let from = match legal_move {
+      Move::Piece(from, to) if move_to == to => from,
+      // promotions are listed once per piece, keep one per pawn
+      Move::Promotion(from, to, promotion) if move_to == to && promotion.is_queen() => from,
+      _ => continue,
+    };
+    if let Some(board_piece) = board.get_piece(from) {
+      // filter based on type
+      let pos = board_piece.get_pos();
+      if board_piece.get_name() == piece.get_name()
+        && (column.is_none() || column == Some(pos.get_col()))
+        && (row.is_none() || row == Some(pos.get_row()))
+      {
+        candidates.push(board_piece);
       }
     }
   }
@@ -359,6 +373,101 @@ pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
   }
 }
 
+// format a legal move for the current turn in the SAN parse_san_move accepts
+//
+// pieces of the same type that can reach the same square are told apart by
+// file, then rank, then both. promotions have no "=" (e.g. "e8Q") and
+// checks and mates get a trailing + or #.
+pub fn format_san_move(board: &Board, chess_move: Move) -> String {
+  let mut san = match chess_move {
+    Move::KingSideCastle => "0-0".to_string(),
+    Move::QueenSideCastle => "0-0-0".to_string(),
+    Move::Resign => return String::new(),
+    Move::Piece(from, to) | Move::Promotion(from, to, _) => {
+      let piece = match board.get_piece(from) {
+        Some(piece) => piece,
+        None => return String::new(),
+      };
+      let from_str = from.to_string();
+      let capture = board.has_piece(to) || (piece.is_pawn() && from.get_col() != to.get_col());
+
+      let mut san = String::new();
+      if piece.is_pawn() {
+        // pawn captures always name the file they come from
+        if capture {
+          san.push_str(&from_str[..1]);
+        }
+      } else {
+        san.push(san_letter(&piece));
+        // other pieces of the same type that can reach the same square
+        let others: Vec<Position> = board
+          .get_legal_moves()
+          .into_iter()
+          .filter_map(|legal_move| match legal_move {
+            Move::Piece(other, other_to) if other_to == to && other != from => board
+              .get_piece(other)
+              .filter(|other_piece| other_piece.get_name() == piece.get_name())
+              .map(|_| other),
+            _ => None,
+          })
+          .collect();
+        if others.iter().all(|other| other.get_col() != from.get_col()) {
+          if !others.is_empty() {
+            san.push_str(&from_str[..1]);
+          }
+        } else if others.iter().all(|other| other.get_row() != from.get_row()) {
+          san.push_str(&from_str[1..]);
+        } else {
+          san.push_str(&from_str);
+        }
+      }
+      if capture {
+        san.push('x');
+      }
+      san.push_str(&to.to_string());
+
+      match chess_move {
+        Move::Promotion(_, _, promoted_to) => san.push(san_letter(&promoted_to)),
+        // pawns reaching the last rank become queens by default
+        _ if piece.is_pawn() && (to.get_row() == 0 || to.get_row() == 7) => san.push('Q'),
+        _ => {}
+      }
+      san
+    }
+  };
+
+  let board_after = board.apply_eval_move(chess_move);
+  if board_after.is_checkmate() {
+    san.push('#');
+  } else if board_after.is_in_check(board_after.get_turn_color()) {
+    san.push('+');
+  }
+  san
+}
+
+// upper case piece letter used in SAN, P for pawns
+fn san_letter(piece: &Piece) -> char {
+  match piece {
+    Piece::King(_, _) => 'K',
+    Piece::Knight(_, _) => 'N',
+    Piece::Bishop(_, _) => 'B',
+    Piece::Queen(_, _) => 'Q',
+    Piece::Pawn(_, _) => 'P',
+    Piece::Rook(_, _) => 'R',
+  }
+}
+
+// 64 bit FNV-1a hash
+// unlike DefaultHasher the result is fixed, so it is safe to store
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in bytes {
+    hash ^= *byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -404,6 +513,39 @@ mod tests {
     )
   }
 
+  #[test]
+  fn test_fnv1a_hash() {
+    // published test vectors
+    assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
+  }
+
+  #[test]
+  fn test_shredder_fen() {
+    // castling rights by rook file read the same as KQkq
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1").unwrap();
+    assert_eq!(format_fen(&board, 0, 1).unwrap(), start);
+
+    // white may only castle kingside, black only queenside
+    let board = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w Ha - 0 1").unwrap();
+    assert!(board.get_castling_rights(Color::White).can_kingside_castle());
+    assert!(!board.get_castling_rights(Color::White).can_queenside_castle());
+    assert!(!board.get_castling_rights(Color::Black).can_kingside_castle());
+    assert!(board.get_castling_rights(Color::Black).can_queenside_castle());
+    assert!(board.is_legal_move(Move::KingSideCastle, Color::White));
+    assert!(!board.is_legal_move(Move::QueenSideCastle, Color::White));
+    let board = match board.play_move(Move::KingSideCastle) {
+      GameResult::Continuing(board) => board,
+      e => panic!("unexpected error: {:?}", e),
+    };
+    assert!(board.is_legal_move(Move::QueenSideCastle, Color::Black));
+
+    // rooks away from the corners cannot castle in this engine
+    assert!(parse_fen("rk5r/8/8/8/8/8/8/RK5R w BHbh - 0 1").is_err());
+  }
+
   #[test]
   fn test_parse_san_move() {
     let mut board = Board::default();
@@ -439,4 +581,25 @@ mod tests {
       "no matching move".to_string()
     );
   }
+
+  #[test]
+  fn test_format_san_move() {
+    let mut board = Board::default();
+    assert_eq!(format_san_move(&board, Move::Piece(G1, F3)), "Nf3");
+    assert_eq!(format_san_move(&board, Move::Piece(E2, E4)), "e4");
+
+    for chess_move in [Move::Piece(E2, E4), Move::Piece(D7, D5)] {
+      board = match board.play_move(chess_move) {
+        GameResult::Continuing(board) => board,
+        e => panic!("unexpected error: {:?}", e),
+      };
+    }
+    assert_eq!(format_san_move(&board, Move::Piece(E4, D5)), "exd5");
+
+    // fool's mate
+    let board = parse_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2").unwrap();
+    assert_eq!(format_san_move(&board, Move::Piece(D8, H4)), "Qh4#");
+    assert_eq!(parse_san_move(&board, "Qh4#").expect("Qh4#"), Move::Piece(D8, H4));
+  }
+
 }
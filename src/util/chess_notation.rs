@@ -0,0 +1,133 @@
+//! Format checks for submitted moves.
+//!
+//! These only look at the shape of the string, one pass over at most a few
+//! bytes, so obviously malformed input can be turned away before a game is
+//! loaded. A string that passes may still not be a legal move.
+
+/// Longest SAN accepted, e.g. `exd8=Q+` or `Qh4xe1#`.
+pub const MAX_SAN_LEN: usize = 7;
+
+const CASTLES: [&str; 4] = ["O-O", "O-O-O", "0-0", "0-0-0"];
+
+/// Is `s` shaped like a move in standard algebraic notation?
+///
+/// Accepts an optional piece letter, an optional source file and rank, an
+/// optional `x`, the destination square, an optional promotion piece with
+/// or without `=`, and an optional `+` or `#`. Castling may be written with
+/// letters or zeros.
+pub fn is_valid_san(s: &str) -> bool {
+  if s.len() > MAX_SAN_LEN || !s.is_ascii() {
+    return false;
+  }
+  let s = s.strip_suffix(['+', '#']).unwrap_or(s);
+  if CASTLES.contains(&s) {
+    return true;
+  }
+
+  let mut bytes = s.as_bytes();
+  // promotion on the first or last rank, e.g. "=Q" or "Q"
+  if let [rest @ .., b'1' | b'8', b'=', piece] | [rest @ .., b'1' | b'8', piece] = bytes {
+    if is_promotion_piece(*piece) {
+      bytes = &bytes[..rest.len() + 1];
+    }
+  }
+  let source = match bytes {
+    [source @ .., file, rank] if is_file(*file) && is_rank(*rank) => source,
+    _ => return false,
+  };
+
+  // [piece][file][rank][x], a capture needs something before the x
+  let mut prefix = source;
+  if let [piece, rest @ ..] = prefix {
+    if is_piece(*piece) {
+      prefix = rest;
+    }
+  }
+  if let [file, rest @ ..] = prefix {
+    if is_file(*file) {
+      prefix = rest;
+    }
+  }
+  if let [rank, rest @ ..] = prefix {
+    if is_rank(*rank) {
+      prefix = rest;
+    }
+  }
+  match prefix {
+    [] => true,
+    [b'x'] => source.len() > 1,
+    _ => false,
+  }
+}
+
+/// Is `s` a move as from and to squares, e.g. `e2e4` or `e7e8q`?
+pub fn is_valid_uci(s: &str) -> bool {
+  match s.as_bytes() {
+    [from_file, from_rank, to_file, to_rank, promotion @ ..] => {
+      is_file(*from_file)
+        && is_rank(*from_rank)
+        && is_file(*to_file)
+        && is_rank(*to_rank)
+        && matches!(promotion, [] | [b'q' | b'r' | b'b' | b'n'])
+    }
+    _ => false,
+  }
+}
+
+fn is_file(c: u8) -> bool {
+  (b'a'..=b'h').contains(&c)
+}
+
+fn is_rank(c: u8) -> bool {
+  (b'1'..=b'8').contains(&c)
+}
+
+fn is_piece(c: u8) -> bool {
+  matches!(c, b'K' | b'Q' | b'R' | b'B' | b'N')
+}
+
+fn is_promotion_piece(c: u8) -> bool {
+  matches!(c, b'Q' | b'R' | b'B' | b'N')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_valid_san() {
+    let valid = [
+      "e4", "d5", "a3", "h6", "exd5", "axb8", "hxg1", "Nf3", "Nc6", "Bb5", "Ba4", "Qh5", "Qxf7",
+      "Qxf7#", "Rxe8+", "Kg1", "Ke2", "Rad1", "Rfe1", "R1e2", "R8e7", "Nbd7", "Ngf6", "N1f3",
+      "Nexc4", "Ne2xc4", "Qh4xe1", "Qh4xe1#", "Qa1b2", "e8=Q", "e8Q", "exd8=Q+", "exd8Q+",
+      "a1=N", "b8=R#", "fxg1=B", "h1N", "c8=Q#", "O-O", "O-O-O", "O-O+", "O-O-O#", "0-0",
+      "0-0-0", "0-0+", "e2e4", "e7e8Q", "Kxh8", "d4+", "Bxh7+",
+    ];
+    assert_eq!(valid.len(), 50);
+    for san in valid {
+      assert!(is_valid_san(san), "{}", san);
+    }
+
+    let invalid = [
+      "", "e", "e9", "i4", "E4", "4e", "e4=Q", "xe4", "Pe4", "Ze4", "nf3", "N", "Nf", "Nf9",
+      "Ni3", "Nxxf3", "Nf3x", "Nf3++", "+e4", "e4 ", " e4", "e4!", "e4?", "e8=K", "e8=", "e8=q",
+      "=Qe8", "exd8=Q+!", "Qh4xe1##", "O-O-O-O", "O-", "OO", "o-o", "0-0-", "00", "Qh44", "Qa1b2c3",
+      "Nf3f3f3", "e2-e4", "e2:e4", "e4e", "e4Q", "Rx", "x", "++", "#", "é4", "e4\n", "resign",
+      "draw",
+    ];
+    assert_eq!(invalid.len(), 50);
+    for san in invalid {
+      assert!(!is_valid_san(san), "{}", san);
+    }
+  }
+
+  #[test]
+  fn test_is_valid_uci() {
+    for uci in ["e2e4", "g1f3", "e7e8q", "a2a1n", "h7h8r", "b7b8b", "a1h8"] {
+      assert!(is_valid_uci(uci), "{}", uci);
+    }
+    for uci in ["", "e2", "e2e", "e2e9", "i2e4", "e2e4Q", "e7e8k", "e2e4q1", "E2E4", "e2 e4"] {
+      assert!(!is_valid_uci(uci), "{}", uci);
+    }
+  }
+}
@@ -0,0 +1,225 @@
+//! Fixed-point arithmetic for the contract, where floats are not allowed.
+//!
+//! Values are unsigned and scaled by `1 << PREC`, so with `PREC = 10`
+//! the number `1.0` is stored as `1024` and `0.5` as `512`. Every function
+//! here takes and returns values in that format unless noted otherwise.
+//!
+//! Products are taken in `u128` before scaling back down, so an
+//! intermediate result only overflows when the final one would.
+
+/// Number of fractional bits, `1.0 == 1 << PREC`
+pub const PREC: u64 = 10;
+/// 1.0 in fixed-point
+pub const ONE: u64 = 1 << PREC;
+/// 0.5 in fixed-point
+pub const HALF: u64 = 1 << (PREC - 1);
+/// ln(2) in fixed-point
+#[allow(dead_code)]
+pub const LN2: u64 = 710;
+/// ln(10) in fixed-point
+pub const LN10: u64 = 2358;
+/// e in fixed-point
+pub const E: u64 = 2784;
+
+/// Calculates a * b
+pub fn fp_mul(a: u64, b: u64) -> u64 {
+  ((a as u128 * b as u128) >> PREC) as u64
+}
+
+/// Calculates the exponential function e^x
+///
+/// Uses the first terms of the Taylor series, so it is most accurate
+/// for small x. Use `fp_exp_int` for the integer part of larger values.
+pub fn fp_exp(x: u64) -> u64 {
+  let mut result = ONE; // Start with 1 in fixed-point
+  let mut term = ONE;   // The current term, starts with x^0 / 0! = 1
+
+  for i in 1..=10 {
+    // Calculate x^i / i!
+    term = fp_mul(term, x) / (i as u64);
+    // Add the term to the result
+    result += term;
+
+    // Break early if the term is too small to affect the result
+    if term == 0 {
+      break;
+    }
+  }
+
+  result
+}
+
+/// Calculates the exponential function e^x for integer
+///
+/// `x` is a plain integer, not fixed-point. The result is fixed-point.
+pub fn fp_exp_int(x: u64) -> u64 {
+  let mut s = ONE;
+  for _ in 1..=x {
+    s = fp_mul(s, E);
+  }
+  s
+}
+
+/// Calculates 10^x using fixed-point arithmetic
+pub fn fp_pow10(x: u64) -> u64 {
+  // multiply x by ln(10) to convert to e^x form
+  let exponent = fp_mul(x, LN10);
+
+  // compute e^(x * ln(10))
+  let e1 = exponent >> PREC;
+  let e2 = exponent - (e1 << PREC);
+
+  fp_mul(fp_exp_int(e1), fp_exp(e2))
+}
+
+/// Calculates log2(x)
+///
+/// The result is unsigned, so values of x below 1.0 return 0.
+#[allow(dead_code)]
+pub fn fp_log2(x: u64) -> u64 {
+  let one = ONE;
+  if x < one {
+    return 0;
+  }
+
+  // integer part: shift x down into [1.0, 2.0)
+  let mut int_part = 0;
+  let mut y = x;
+  while y >= 2 * one {
+    y >>= 1;
+    int_part += 1;
+  }
+
+  // fractional part: one bit per squaring
+  let mut result = int_part << PREC;
+  for bit in (0..PREC).rev() {
+    y = (y * y) >> PREC;
+    if y >= 2 * one {
+      y >>= 1;
+      result |= 1 << bit;
+    }
+  }
+
+  result
+}
+
+/// Calculates ln(x)
+///
+/// The result is unsigned, so values of x below 1.0 return 0.
+#[allow(dead_code)]
+pub fn fp_ln(x: u64) -> u64 {
+  fp_mul(fp_log2(x), LN2)
+}
+
+/// Calculates the square root of x with Newton's method
+#[allow(dead_code)]
+pub fn fp_sqrt(x: u64) -> u64 {
+  if x == 0 {
+    return 0;
+  }
+
+  // sqrt(x / 2^PREC) * 2^PREC == sqrt(x * 2^PREC)
+  let n = (x as u128) << PREC;
+  let mut guess = n;
+  loop {
+    let next = (guess + n / guess) / 2;
+    if next >= guess {
+      return guess as u64;
+    }
+    guess = next;
+  }
+}
+
+/// Calculates a / b
+///
+/// Panics if `b` is zero, like integer division.
+pub fn fp_div(a: u64, b: u64) -> u64 {
+  (((a as u128) << PREC) / b as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn to_fp(x: f64) -> u64 {
+    (x * (1 << PREC) as f64) as u64
+  }
+
+  // within 1% of the true value
+  fn assert_close(actual: u64, expected: f64) {
+    let actual = actual as f64 / (1 << PREC) as f64;
+    assert!(
+      (actual - expected).abs() <= expected.abs() * 0.01,
+      "{} is not within 1% of {}",
+      actual,
+      expected
+    );
+  }
+
+  #[test]
+  fn test_fp_exp() {
+    for x in [0.0, 0.25, 0.5, 0.9, 1.5] {
+      assert_close(fp_exp(to_fp(x)), f64::exp(x));
+    }
+    for x in [0, 1, 2, 5] {
+      assert_close(fp_exp_int(x), f64::exp(x as f64));
+    }
+  }
+
+  #[test]
+  fn test_fp_pow10() {
+    for x in [0.0, 0.5, 1.0, 2.0, 3.25] {
+      assert_close(fp_pow10(to_fp(x)), f64::powf(10.0, x));
+    }
+  }
+
+  #[test]
+  fn test_fp_log2() {
+    assert_eq!(fp_log2(to_fp(0.5)), 0);
+    assert_eq!(fp_log2(to_fp(1.0)), 0);
+    for x in [1.5, 2.0, 3.0, 10.0, 1000.0] {
+      assert_close(fp_log2(to_fp(x)), f64::log2(x));
+    }
+  }
+
+  #[test]
+  fn test_fp_ln() {
+    assert_eq!(fp_ln(to_fp(0.5)), 0);
+    assert_eq!(fp_ln(ONE), 0);
+    for x in [2.0, std::f64::consts::E, 10.0, 1000.0] {
+      assert_close(fp_ln(to_fp(x)), f64::ln(x));
+    }
+  }
+
+  #[test]
+  fn test_fp_mul() {
+    for (a, b) in [(1.0, 2.0), (0.5, 0.5), (3.0, 4.0), (1000.0, 1000.0)] {
+      assert_close(fp_mul(to_fp(a), to_fp(b)), a * b);
+    }
+  }
+
+  #[test]
+  fn test_no_intermediate_overflow() {
+    // the shifted or multiplied operands are past u64::MAX
+    let big = u64::MAX >> 4;
+    assert_eq!(fp_mul(big, ONE), big);
+    assert_eq!(fp_div(big, ONE), big);
+    assert_close(fp_sqrt(to_fp(1e12)), 1e6);
+    assert_close(fp_pow10(to_fp(9.0)), 1e9);
+  }
+
+  #[test]
+  fn test_fp_sqrt() {
+    assert_eq!(fp_sqrt(0), 0);
+    for x in [0.25, 1.0, 2.0, 9.0, 1234.5] {
+      assert_close(fp_sqrt(to_fp(x)), f64::sqrt(x));
+    }
+  }
+
+  #[test]
+  fn test_fp_div() {
+    for (a, b) in [(1.0, 2.0), (3.0, 4.0), (10.0, 3.0), (1000.0, 0.5)] {
+      assert_close(fp_div(to_fp(a), to_fp(b)), a / b);
+    }
+  }
+}
@@ -0,0 +1,264 @@
+//! Cleans PGN from third-party sources before it is stored on chain.
+//!
+//! Only the first game is kept. Its headers are cut down to the seven tag
+//! roster, with values the contract chooses, so names, IP addresses and
+//! timestamps from the source never reach public state. Movetext keeps
+//! moves, move numbers, NAGs, variations and comments annotating a move,
+//! minus clock and engine evaluation commands.
+//!
+//! Archived games go through it too, see `GameRecord::archived_pgn`.
+
+use crate::error::ContractError;
+
+/// Longest sanitized PGN accepted, in bytes.
+pub const MAX_PGN_BYTES: usize = 8192;
+
+/// Event tag value for every stored game.
+pub const EVENT: &str = "On-Chain Game";
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Embedded commands dropped from comments.
+const STRIPPED_COMMANDS: [&str; 2] = ["[%clk", "[%eval"];
+
+/// Tag values chosen by the contract, Event is always [`EVENT`].
+pub struct Roster<'a> {
+  /// The contract address.
+  pub site: &'a str,
+  /// The block date as `YYYY.MM.DD`.
+  pub date: &'a str,
+  /// `?` unless the player is known to the contract, names from the source are not kept.
+  pub white: &'a str,
+  /// Like `white`.
+  pub black: &'a str,
+}
+
+/// Sanitize the first game in `pgn`, with the tags of `roster`.
+pub fn sanitize(pgn: &str, roster: &Roster) -> Result<String, ContractError> {
+  let (result_tag, movetext) = first_game(pgn);
+  let (tokens, termination) = clean_movetext(&movetext);
+  let result = termination
+    .or_else(|| RESULTS.iter().copied().find(|result| Some(*result) == result_tag.as_deref()))
+    .unwrap_or("*");
+
+  let tags = [
+    ("Event", EVENT),
+    ("Site", roster.site),
+    ("Date", roster.date),
+    ("Round", "-"),
+    ("White", roster.white),
+    ("Black", roster.black),
+    ("Result", result),
+  ];
+  let mut output = String::new();
+  for (name, value) in tags {
+    output.push_str(&format!("[{} \"{}\"]\n", name, tag_value(value)));
+  }
+  output.push('\n');
+  for token in tokens {
+    output.push_str(&token);
+    output.push(' ');
+  }
+  output.push_str(result);
+  output.push('\n');
+
+  if output.len() > MAX_PGN_BYTES {
+    return Err(ContractError::PgnTooLong {
+      length: output.len() as u64,
+    });
+  }
+  Ok(output)
+}
+
+// Result tag value and movetext of the first game
+fn first_game(pgn: &str) -> (Option<String>, String) {
+  let mut result_tag = None;
+  let mut movetext = String::new();
+  for line in pgn.lines() {
+    let line = line.trim();
+    if line.starts_with('%') {
+      // escaped line
+      continue;
+    }
+    if line.starts_with('[') && !line.starts_with("[%") {
+      if !movetext.trim().is_empty() {
+        // headers of the next game
+        break;
+      }
+      if let Some(value) = line.strip_prefix("[Result ") {
+        result_tag = value.split('"').nth(1).map(String::from);
+      }
+      continue;
+    }
+    movetext.push_str(line);
+    movetext.push('\n');
+  }
+  (result_tag, movetext)
+}
+
+// movetext tokens up to the termination marker, and the marker if found
+fn clean_movetext(movetext: &str) -> (Vec<String>, Option<&'static str>) {
+  let mut tokens = vec![];
+  // comments are only kept right after a move or NAG
+  let mut after_move = false;
+  let mut chars = movetext.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '{' => {
+        let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+        if after_move {
+          if let Some(comment) = clean_comment(&comment) {
+            tokens.push(format!("{{{}}}", comment));
+          }
+        }
+      }
+      ';' => {
+        // rest of line comment
+        chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+      }
+      '(' | ')' => {
+        tokens.push(c.to_string());
+        after_move = false;
+      }
+      c if c.is_whitespace() => {}
+      c => {
+        let mut token = c.to_string();
+        while let Some(&next) = chars.peek() {
+          if next.is_whitespace() || "{};()".contains(next) {
+            break;
+          }
+          token.push(next);
+          chars.next();
+        }
+        let token: String = token.chars().filter(|c| c.is_ascii_graphic()).collect();
+        if token.is_empty() {
+          continue;
+        }
+        if let Some(result) = RESULTS.iter().find(|result| **result == token) {
+          return (tokens, Some(result));
+        }
+        // move numbers, e.g. "12." or "12..."
+        after_move = !token.chars().all(|c| c.is_ascii_digit() || c == '.');
+        tokens.push(token);
+      }
+    }
+  }
+  (tokens, None)
+}
+
+// comment text without clock and eval commands, None when nothing is left
+fn clean_comment(comment: &str) -> Option<String> {
+  let mut comment = comment.to_string();
+  for command in STRIPPED_COMMANDS {
+    while let Some(start) = comment.find(command) {
+      let end = comment[start..]
+        .find(']')
+        .map(|end| start + end + 1)
+        .unwrap_or(comment.len());
+      comment.replace_range(start..end, " ");
+    }
+  }
+  let comment: String = comment
+    .chars()
+    .filter(|c| (c.is_ascii_graphic() || *c == ' ') && *c != '{' && *c != '}')
+    .collect();
+  let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+  if comment.is_empty() {
+    None
+  } else {
+    Some(comment)
+  }
+}
+
+// printable ASCII without characters that would end the tag value
+fn tag_value(value: &str) -> String {
+  value
+    .chars()
+    .filter(|c| (c.is_ascii_graphic() || *c == ' ') && *c != '"' && *c != '\\')
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ROSTER: Roster = Roster {
+    site: "contract0",
+    date: "2026.10.15",
+    white: "?",
+    black: "?",
+  };
+
+  fn headers(result: &str) -> String {
+    format!(
+      "[Event \"On-Chain Game\"]\n[Site \"contract0\"]\n[Date \"2026.10.15\"]\n\
+       [Round \"-\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"{}\"]\n\n",
+      result
+    )
+  }
+
+  #[test]
+  fn test_sanitize_tags() {
+    let pgn = "[Event \"Rated Blitz game\"]\n\
+               [Site \"https://example.org/abc\"]\n\
+               [Date \"2024.01.02\"]\n\
+               [White \"alice\"]\n\
+               [Black \"bob\"]\n\
+               [Result \"1-0\"]\n\
+               [UTCTime \"12:34:56\"]\n\
+               [IPAddress \"10.0.0.1\"]\n\
+               [X-Weird_Tag \"value\"]\n\
+               \n\
+               1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n";
+    let sanitized = sanitize(pgn, &ROSTER).unwrap();
+    assert_eq!(
+      sanitized,
+      headers("1-0") + "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"
+    );
+    for removed in ["alice", "bob", "12:34:56", "10.0.0.1", "Weird", "example"] {
+      assert!(!sanitized.contains(removed), "{}", removed);
+    }
+  }
+
+  #[test]
+  fn test_sanitize_comments() {
+    let pgn = "[Result \"*\"]\n\
+               {Game played on my laptop} 1. e4 {[%clk 0:05:00] [%eval 0.3] Best by test \u{265F}} \
+               e5 {[%clk 0:04:58]} 2. Nf3 $1 {Développement} ; line comment\n\
+               (2. f4 {gambit}) Nc6 *\n";
+    let sanitized = sanitize(pgn, &ROSTER).unwrap();
+    assert_eq!(
+      sanitized,
+      headers("*") + "1. e4 {Best by test} e5 2. Nf3 $1 {Dveloppement} ( 2. f4 {gambit} ) Nc6 *\n"
+    );
+    assert!(sanitized.is_ascii());
+  }
+
+  #[test]
+  fn test_sanitize_long_comment() {
+    let long = "a".repeat(MAX_PGN_BYTES);
+    let pgn = format!("1. e4 {{{}}} e5 *", long);
+    match sanitize(&pgn, &ROSTER).unwrap_err() {
+      ContractError::PgnTooLong { length } => assert!(length > MAX_PGN_BYTES as u64),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the same comment in front of the game is dropped
+    let pgn = format!("{{{}}} 1. e4 e5 *", long);
+    assert_eq!(sanitize(&pgn, &ROSTER).unwrap(), headers("*") + "1. e4 e5 *\n");
+  }
+
+  #[test]
+  fn test_sanitize_multiple_games() {
+    let pgn = "[Event \"first\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n\
+               [Event \"second\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+    assert_eq!(
+      sanitize(pgn, &ROSTER).unwrap(),
+      headers("0-1") + "1. f3 e5 2. g4 Qh4# 0-1\n"
+    );
+
+    // games without headers in between end at the result
+    let pgn = "1. d4 d5 1/2-1/2\n1. e4 e5 1-0\n";
+    assert_eq!(sanitize(pgn, &ROSTER).unwrap(), headers("1/2-1/2") + "1. d4 d5 1/2-1/2\n");
+  }
+}
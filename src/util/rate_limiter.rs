@@ -0,0 +1,73 @@
+//! Per block limit on the messages one address can send.
+//!
+//! Each sender has a count for the block they last sent a message in. A
+//! count from an earlier block is ignored and removed the next time the
+//! sender is checked, so nothing needs cleaning up separately.
+use cosmwasm_std::{Addr, Order, StdResult, Storage};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::state::RATE_LIMITER;
+
+/// Count one more message from `sender` in `block_height`.
+///
+/// Fails with `RateLimitExceeded` once `max_per_block` messages have been
+/// counted in the block. A message that fails reverts its count along with
+/// the rest of its transaction, so only successful messages are counted.
+pub fn check_and_increment(
+  storage: &mut dyn Storage,
+  sender: &Addr,
+  block_height: u64,
+  max_per_block: u8,
+) -> Result<(), ContractError> {
+  let count = RATE_LIMITER.may_load(storage, (sender, block_height))?.unwrap_or(0);
+  if count >= max_per_block {
+    return Err(ContractError::RateLimitExceeded { max: max_per_block });
+  }
+  if count == 0 {
+    prune(storage, sender, block_height)?;
+  }
+  RATE_LIMITER.save(storage, (sender, block_height), &(count + 1))?;
+  Ok(())
+}
+
+// remove the sender's counts from blocks before block_height
+fn prune(storage: &mut dyn Storage, sender: &Addr, block_height: u64) -> StdResult<()> {
+  let old_blocks = RATE_LIMITER
+    .prefix(sender)
+    .keys(storage, None, Some(Bound::exclusive(block_height)), Order::Ascending)
+    .collect::<StdResult<Vec<u64>>>()?;
+  for old_block in old_blocks {
+    RATE_LIMITER.remove(storage, (sender, old_block));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cosmwasm_std::testing::MockStorage;
+
+  #[test]
+  fn test_check_and_increment() {
+    let mut storage = MockStorage::new();
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+
+    for _ in 0..3 {
+      check_and_increment(&mut storage, &alice, 10, 3).unwrap();
+    }
+    match check_and_increment(&mut storage, &alice, 10, 3).unwrap_err() {
+      ContractError::RateLimitExceeded { max } => assert_eq!(max, 3),
+      e => panic!("unexpected error: {:?}", e),
+    }
+    // other senders and later blocks have their own counts
+    check_and_increment(&mut storage, &bob, 10, 3).unwrap();
+    check_and_increment(&mut storage, &alice, 11, 3).unwrap();
+
+    // the count from block 10 is gone
+    assert_eq!(RATE_LIMITER.may_load(&storage, (&alice, 10)).unwrap(), None);
+    assert_eq!(RATE_LIMITER.load(&storage, (&alice, 11)).unwrap(), 1);
+    assert_eq!(RATE_LIMITER.load(&storage, (&bob, 10)).unwrap(), 1);
+  }
+}
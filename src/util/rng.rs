@@ -0,0 +1,96 @@
+//! Deterministic randomness for game setup, such as color assignment.
+//!
+//! Every node must reach the same result, so the seed comes from block
+//! data plus caller-supplied bytes. Block data is public and partly chosen
+//! by the block proposer, so this must not decide anything worth paying
+//! to manipulate.
+use cosmwasm_std::Env;
+
+/// SplitMix64 generator.
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Rng { state: seed }
+  }
+
+  /// Next value over the full u64 range.
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  /// Next value in `0..n`, without modulo bias.
+  ///
+  /// Panics if `n` is zero.
+  pub fn next_range(&mut self, n: u64) -> u64 {
+    assert!(n > 0, "empty range");
+    // reject the top partial bucket so every result is equally likely
+    let zone = u64::MAX - (u64::MAX % n);
+    loop {
+      let value = self.next_u64();
+      if value < zone {
+        return value % n;
+      }
+    }
+  }
+}
+
+/// Seed from the current block and `extra`, e.g. a game or challenge id.
+pub fn seed_from(env: &Env, extra: &[u8]) -> u64 {
+  let mut rng = Rng::new(env.block.height);
+  let mut mix = |value: u64| {
+    rng.state ^= value;
+    rng.next_u64()
+  };
+  mix(env.block.time.nanos());
+  let mut seed = 0;
+  for chunk in env.block.chain_id.as_bytes().chunks(8).chain(extra.chunks(8)) {
+    let mut bytes = [0u8; 8];
+    bytes[..chunk.len()].copy_from_slice(chunk);
+    seed = mix(u64::from_le_bytes(bytes));
+  }
+  seed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cosmwasm_std::testing::mock_env;
+
+  #[test]
+  fn test_reproducible() {
+    let env = mock_env();
+    assert_eq!(seed_from(&env, b"game 1"), seed_from(&env, b"game 1"));
+    assert_ne!(seed_from(&env, b"game 1"), seed_from(&env, b"game 2"));
+
+    let mut other_block = mock_env();
+    other_block.block.height += 1;
+    assert_ne!(seed_from(&env, b"game 1"), seed_from(&other_block, b"game 1"));
+
+    let mut rng1 = Rng::new(42);
+    let mut rng2 = Rng::new(42);
+    for _ in 0..100 {
+      assert_eq!(rng1.next_range(1000), rng2.next_range(1000));
+    }
+  }
+
+  #[test]
+  fn test_next_range_distribution() {
+    let mut rng = Rng::new(seed_from(&mock_env(), &[]));
+    let mut buckets = [0u32; 6];
+    for _ in 0..6000 {
+      buckets[rng.next_range(6) as usize] += 1;
+    }
+    // each bucket expects 1000
+    for count in buckets.iter() {
+      assert!((850..1150).contains(count), "{:?}", buckets);
+    }
+    assert_eq!(rng.next_range(1), 0);
+  }
+}